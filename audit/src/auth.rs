@@ -2,15 +2,23 @@ use std::env;
 use std::sync::LazyLock;
 
 use axum::extract::Query;
-use axum::extract::State;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
 use axum::response::Redirect;
+use axum::response::Response;
 use axum_extra::extract::CookieJar;
 use axum_extra::extract::cookie::Cookie;
 use axum_extra::extract::cookie::SameSite;
 use axum_macros::debug_handler;
 use base64ct::Base64UrlUnpadded;
 use base64ct::Encoding;
-use leptos_axum::extract;
+use chrono::DateTime;
+use chrono::Utc;
+use http::StatusCode;
+use http::request::Parts;
+use leptos::logging::log;
+use leptos::prelude::use_context;
 use rand::Rng;
 use rand::rng;
 use serde::Deserialize;
@@ -20,7 +28,6 @@ use sha2::Sha256;
 use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::state::AppState;
 
 /// Environment variables needed during the OAuth PKCE flow.
 struct AuthVars {
@@ -160,23 +167,11 @@ pub async fn handle_pkce_code(
     Ok((jar, Redirect::to("/gateway")))
 }
 
-/// This function is called when the current surgeon logs out, removing the auth token from the
-/// database client, deleting the auth token cookie, and removing the current
-/// [`Surgeon`](crate::surgeon::Surgeon) from global state.
+/// This function is called when the current surgeon logs out, deleting the auth token and verifier
+/// cookies. DB clients are created per request from the auth token cookie (see
+/// [`with_client_token`](crate::db::with_client_token)), so there is no server state to reset.
 #[debug_handler]
-pub async fn handle_kill_session(
-    State(AppState { db, surgeon, .. }): State<AppState>,
-    mut jar: CookieJar,
-) -> Result<(CookieJar, Redirect), AppError> {
-    // A dummy DB client, so that we can replace the `gel_tokio::Client` that contains
-    // surgeon-specific globals
-    let client = gel_tokio::create_client()
-        .await
-        .expect("expected DB client to be created");
-
-    db.set(client)?;
-    surgeon.set(None)?;
-
+pub async fn handle_kill_session(mut jar: CookieJar) -> Result<(CookieJar, Redirect), AppError> {
     jar = jar
         .remove(Cookie::from("gel-auth-token"))
         .remove(Cookie::from("gel-pkce-verifier"));
@@ -184,22 +179,180 @@ pub async fn handle_kill_session(
     Ok((jar, Redirect::to("/")))
 }
 
-/// Get the current surgeon's JWT from the `gel-auth-token` cookie.
-pub async fn get_jwt_cookie() -> Result<Option<String>, AppError> {
-    let auth_token = extract::<CookieJar>()
-        .await?
+/// The prefix of server function URLs.
+const API_PREFIX: &str = "/api/";
+
+/// The prefix of server functions that don't require a signed-in surgeon, such as those used to
+/// render public content. Set it with `#[server(prefix = "/api/public")]`.
+const PUBLIC_API_PREFIX: &str = "/api/public/";
+
+/// Pages that require a signed-in surgeon, along with everything below them. Signing up and
+/// accepting the terms require a Gel Auth identity, even though the [`Surgeon`] doesn't exist yet.
+///
+/// [`Surgeon`]: crate::model::Surgeon
+const PROTECTED_PAGES: [&str; 3] = ["/protected", "/signup", "/terms"];
+
+/// The claims used from a Gel Auth JWT.
+#[derive(Debug, Deserialize)]
+struct Claims {
+    /// The ID of the `ext::auth::Identity`.
+    sub: Uuid,
+    /// The expiry, in seconds since the Unix epoch.
+    exp: i64,
+}
+
+/// The signed-in surgeon's auth token, with its decoded claims, attached to the request by
+/// [`require_auth`]. The token's signature is never checked on the server, so the claims are only
+/// as trustworthy as the cookie they came from. Only Gel, which verifies the token whenever it is
+/// used as the `ext::auth::client_token` global, may be trusted to identify the surgeon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthSession {
+    /// The `ext::auth::Identity` claimed by the token. Anyone can make a token with any identity,
+    /// so this must not be used for access control.
+    pub unverified_identity_id: Uuid,
+    pub auth_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AuthSession {
+    /// Decode a Gel Auth JWT, rejecting it if it is malformed or has expired at `now`. The
+    /// signature isn't checked here (see [`AuthSession`]). This check only keeps expired tokens
+    /// from reaching the DB, so that the surgeon can be sent to sign in again, rather than seeing
+    /// a query error.
+    pub fn from_token(auth_token: &str, now: DateTime<Utc>) -> Result<Self, AppError> {
+        let payload = match auth_token.split('.').collect::<Vec<_>>()[..] {
+            [_header, payload, _signature] => payload,
+            _ => return Err(AppError::Auth("the auth token is not a JWT".to_string())),
+        };
+
+        let payload = Base64UrlUnpadded::decode_vec(payload)
+            .map_err(|err| AppError::Auth(format!("unable to decode the auth token: {err}")))?;
+
+        let Claims { sub, exp } = serde_json::from_slice(&payload)?;
+
+        let expires_at = DateTime::from_timestamp(exp, 0).ok_or_else(|| {
+            AppError::Auth(format!("the auth token expiry is out of range: {exp}"))
+        })?;
+
+        if expires_at <= now {
+            return Err(AppError::Auth(format!(
+                "the auth token expired at {expires_at}"
+            )));
+        }
+
+        Ok(Self {
+            unverified_identity_id: sub,
+            auth_token: auth_token.to_string(),
+            expires_at,
+        })
+    }
+}
+
+/// Whether the path is a protected page or server function.
+fn requires_auth(path: &str) -> bool {
+    if path.starts_with(API_PREFIX) {
+        return !path.starts_with(PUBLIC_API_PREFIX);
+    }
+
+    PROTECTED_PAGES.iter().any(|page| {
+        path.strip_prefix(page)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// Middleware that validates the `gel-auth-token` cookie on every protected page and server
+/// function, and attaches the [`AuthSession`] to the request's extensions. Without a valid token,
+/// server functions return `401 Unauthorized`, and pages restart the PKCE flow at `/signin`. An
+/// expired or malformed token is removed, so that it isn't sent again.
+pub async fn require_auth(jar: CookieJar, mut request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+
+    if !requires_auth(path) {
+        return next.run(request).await;
+    }
+
+    let is_api = path.starts_with(API_PREFIX);
+
+    let session = jar
         .get("gel-auth-token")
-        .map(|cookie| cookie.value().to_string());
+        .map(|cookie| AuthSession::from_token(cookie.value(), Utc::now()));
+
+    let jar = match session {
+        Some(Ok(session)) => {
+            request.extensions_mut().insert(session);
+
+            return next.run(request).await;
+        }
+        Some(Err(err)) => {
+            log!("rejected the auth token for {path}: {err}");
+
+            jar.remove(Cookie::from("gel-auth-token"))
+        }
+        None => jar,
+    };
+
+    if is_api {
+        (jar, StatusCode::UNAUTHORIZED).into_response()
+    } else {
+        (jar, Redirect::to("/signin")).into_response()
+    }
+}
 
-    Ok(auth_token)
+/// Get the [`AuthSession`] that [`require_auth`] attached to the current request, if any.
+pub fn auth_session() -> Option<AuthSession> {
+    use_context::<Parts>().and_then(|parts| parts.extensions.get::<AuthSession>().cloned())
 }
 
 #[cfg(test)]
 mod tests {
-    use leptos::logging::log;
+    use axum::Extension;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::middleware::from_fn;
+    use axum::routing::get;
+    use http::header::COOKIE;
+    use http::header::LOCATION;
+    use http::header::SET_COOKIE;
+    use tower::ServiceExt;
 
     use super::*;
 
+    fn token(claims: &str) -> String {
+        format!(
+            "{}.{}.signature",
+            Base64UrlUnpadded::encode_string(br#"{"alg":"HS256","typ":"JWT"}"#),
+            Base64UrlUnpadded::encode_string(claims.as_bytes())
+        )
+    }
+
+    fn expiring_at(exp: i64) -> String {
+        token(&format!(
+            r#"{{"sub":"6f2d3c9e-1a4b-4c8d-9e2f-0a1b2c3d4e5f","exp":{exp}}}"#
+        ))
+    }
+
+    async fn respond(path: &str, auth_token: Option<&str>) -> Response {
+        let app = Router::new()
+            .route(
+                "/{*path}",
+                get(|Extension(session): Extension<AuthSession>| async move {
+                    session.unverified_identity_id.to_string()
+                }),
+            )
+            .route("/", get(|| async { "public" }))
+            .layer(from_fn(require_auth));
+
+        let mut request = http::Request::builder().uri(path);
+
+        if let Some(auth_token) = auth_token {
+            request = request.header(COOKIE, format!("gel-auth-token={auth_token}"));
+        }
+
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
     #[test]
     fn generates_pkce() {
         let pkce = generate_pkce();
@@ -212,4 +365,71 @@ mod tests {
 
         assert!(pkce.verifier.len() == pkce.challenge.len() && pkce.verifier.len() == 43);
     }
+
+    #[test]
+    fn decodes_auth_token() {
+        let now = DateTime::from_timestamp(1_000, 0).unwrap();
+        let session = AuthSession::from_token(&expiring_at(2_000), now).unwrap();
+
+        assert_eq!(
+            session.unverified_identity_id.to_string(),
+            "6f2d3c9e-1a4b-4c8d-9e2f-0a1b2c3d4e5f"
+        );
+        assert_eq!(session.expires_at.timestamp(), 2_000);
+    }
+
+    #[test]
+    fn rejects_expired_and_malformed_tokens() {
+        let now = DateTime::from_timestamp(2_000, 0).unwrap();
+
+        assert!(AuthSession::from_token(&expiring_at(2_000), now).is_err());
+        assert!(AuthSession::from_token("not-a-jwt", now).is_err());
+        assert!(AuthSession::from_token(&token(r#"{"exp":3000}"#), now).is_err());
+        assert!(AuthSession::from_token("a.!!!.c", now).is_err());
+    }
+
+    #[test]
+    fn matches_protected_paths() {
+        assert!(requires_auth("/protected"));
+        assert!(requires_auth("/protected/report"));
+        assert!(requires_auth("/terms"));
+        assert!(requires_auth("/api/get_plots12345"));
+        assert!(!requires_auth("/api/public/markdown_from_file12345"));
+        assert!(!requires_auth("/"));
+        assert!(!requires_auth("/signin"));
+        assert!(!requires_auth("/protectedness"));
+    }
+
+    #[tokio::test]
+    async fn attaches_session_for_valid_token() {
+        let token = expiring_at(Utc::now().timestamp() + 3_600);
+        let response = respond("/protected/add", Some(&token)).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_server_functions_without_token() {
+        let response = respond("/api/get_plots12345", None).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn restarts_sign_in_for_expired_token() {
+        let token = expiring_at(Utc::now().timestamp() - 1);
+        let response = respond("/protected/add", Some(&token)).await;
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()[LOCATION], "/signin");
+        let cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        assert!(cookie.starts_with("gel-auth-token=") && cookie.contains("Max-Age=0"));
+    }
+
+    #[tokio::test]
+    async fn allows_public_paths_without_token() {
+        let response = respond("/", None).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 }
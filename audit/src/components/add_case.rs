@@ -1,5 +1,6 @@
 #[cfg(feature = "ssr")] use chrono::Datelike;
 #[cfg(feature = "ssr")] use gel_protocol::named_args;
+#[cfg(feature = "ssr")] use leptos::logging::log;
 use leptos::prelude::ActionForm;
 use leptos::prelude::ClassAttribute;
 use leptos::prelude::ElementChild;
@@ -15,15 +16,12 @@ use leptos::prelude::view;
 use leptos::server::OnceResource;
 
 #[cfg(feature = "ssr")] use crate::bounded::Bounded;
-#[cfg(feature = "ssr")] use crate::db::db;
 use crate::error::AppError;
-#[cfg(feature = "ssr")] use crate::model::Adverse;
 #[cfg(feature = "ssr")] use crate::model::AfterVa;
 #[cfg(feature = "ssr")] use crate::model::BeforeVa;
 #[cfg(feature = "ssr")] use crate::model::Biometry;
 #[cfg(feature = "ssr")] use crate::model::Case;
 use crate::model::FormCase;
-#[cfg(feature = "ssr")] use crate::model::Formula;
 use crate::model::Iol;
 #[cfg(feature = "ssr")] use crate::model::OpIol;
 #[cfg(feature = "ssr")] use crate::model::OpRefraction;
@@ -31,12 +29,13 @@ use crate::model::Iol;
 #[cfg(feature = "ssr")] use crate::model::RefCyl;
 #[cfg(feature = "ssr")] use crate::model::Refraction;
 #[cfg(feature = "ssr")] use crate::model::Sia;
-#[cfg(feature = "ssr")] use crate::model::Side;
 #[cfg(feature = "ssr")] use crate::model::Site;
-#[cfg(feature = "ssr")] use crate::model::SurgeonCase;
+use crate::model::SurgeonCase;
 #[cfg(feature = "ssr")] use crate::model::Target;
 #[cfg(feature = "ssr")] use crate::model::TargetCyl;
 #[cfg(feature = "ssr")] use crate::model::Va;
+#[cfg(feature = "ssr")] use crate::plots::update_surgeon_constants;
+#[cfg(feature = "ssr")] use crate::repository::repository;
 
 /// Display a form that inserts a `SurgeonCas` on submit.
 #[component]
@@ -460,51 +459,92 @@ pub fn AddCase() -> impl IntoView {
                         </label>
                     </div>
                 </fieldset>
+                <fieldset id="add-ks-after">
+                    "Postop keratometry (optional, used to measure your SIA)"
+                    <div id="k1-after">
+                        <label>
+                            "K1 power (30–65 D)"
+                            <input
+                                type="number"
+                                min=30
+                                max=65
+                                step=0.01
+                                name="case[k1_after_power]"
+                            />
+                        </label>
+                        <label>
+                            "K1 axis (0–179°)"
+                            <input
+                                type="number"
+                                min=0
+                                max=179
+                                step=1
+                                name="case[k1_after_axis]"
+                            />
+                        </label>
+                    </div>
+                    <div id="k2-after">
+                        <label>
+                            "K2 power (30–65 D)"
+                            <input
+                                type="number"
+                                min=30
+                                max=65
+                                step=0.01
+                                name="case[k2_after_power]"
+                            />
+                        </label>
+                        <label>
+                            "K2 axis (0–179°)"
+                            <input
+                                type="number"
+                                min=0
+                                max=179
+                                step=1
+                                name="case[k2_after_axis]"
+                            />
+                        </label>
+                    </div>
+                </fieldset>
                 <input type="submit" value="Submit case" />
             </div>
         </ActionForm>
     }
 }
 
-/// Return a [`Vec`] of all [`Iol`]s in the database.
+/// Return a [`Vec`] of all [`Iol`]s in the repository.
 #[server]
-pub async fn get_iols() -> Result<Vec<Iol>, AppError> {
-    let json = db()
-        .await?
-        .query_json("select Iol { model, name, company, focus, toric };", &())
-        .await?
-        .to_string();
-
-    Ok(serde_json::from_str::<Vec<Iol>>(json.as_str()).unwrap_or_default())
-}
+pub async fn get_iols() -> Result<Vec<Iol>, AppError> { repository()?.iols().await }
 
-/// Insert a [`SurgeonCas`] into the database on form submit.
+/// Insert a [`SurgeonCas`] into the repository on form submit.
 #[server]
-pub async fn insert_form_case(case: FormCase) -> Result<String, AppError> {
-    let client = db().await?;
-    let surgeon_case = case.into_surgeon_case().await?;
-
-    let inserted_case_json =
-        insert_surgeon_case(&client, surgeon_case)
-            .await?
-            .ok_or(AppError::Db(
-                "no JSON was returned after inserting the case".to_string(),
-            ))?;
-
-    Ok(inserted_case_json)
-
-    // TODO: If possible, capture the returned JSON on form submit, deserialize it into a
-    // SurgeonCase, and redirect to a view showing the inserted case, with a button to add another
-    // case (or simply show it above the form to add another case).
+pub async fn insert_form_case(case: FormCase) -> Result<SurgeonCase, AppError> {
+    let repository = repository()?;
+    let surgeon_case = case.into_surgeon_case(repository.as_ref()).await?;
+    let surgeon_case = repository.insert_case(surgeon_case).await?;
+
+    // Reoptimize the surgeon's constants now, rather than on every view of their report. The case
+    // is already saved, so a failure here must not be reported to the form (a resubmit would
+    // insert a duplicate), and the constants will be reoptimized with the next case.
+    if let Err(err) = update_surgeon_constants(repository.as_ref()).await {
+        log!("unable to update the surgeon's constants: {err}");
+    }
+
+    Ok(surgeon_case)
+
+    // TODO: Redirect to a view showing the inserted case, with a button to add another case (or
+    // simply show it above the form to add another case).
 }
 
-/// Insert a [`SurgeonCase`] into the database using the given [`gel_tokio::Client`]. Passing
-/// in the client makes it possible to use custom [`Client`](gel_tokio::Client)s for tests.
+/// Insert a [`SurgeonCase`] into the database using the given [`gel_tokio::Client`], returning the
+/// case as stored (with its assigned `number`). Passing in the client makes it possible to use
+/// custom [`Client`](gel_tokio::Client)s for tests, and to share the insert with the
+/// [`GelRepository`](crate::repository::GelRepository).
 #[cfg(feature = "ssr")]
 pub async fn insert_surgeon_case(
     client: &gel_tokio::Client,
     surgeon_case: SurgeonCase,
-) -> Result<Option<String>, AppError> {
+) -> Result<SurgeonCase, AppError> {
     let SurgeonCase {
         date,
         site,
@@ -520,6 +560,7 @@ pub async fn insert_surgeon_case(
                         cct,
                         wtw,
                     },
+                ks_after,
                 target:
                     Target {
                         formula,
@@ -581,227 +622,236 @@ pub async fn insert_surgeon_case(
         ..
     } = surgeon_case;
 
-    let year = date.year();
-    let date = date.to_string();
-    let site_name = site.map(|Site { name }| name).unwrap_or("{}".to_string());
-
-    let side = match side {
-        Side::Right => "Side.Right",
-        Side::Left => "Side.Left",
-    };
-
-    // NOTE: We don't need to cast the integer types, because they are just going into a format
-    // string. The <int32> will be inferred based on the object field types in Gel.
-    let (flat_k_power, flat_k_axis, steep_k_power, steep_k_axis, cct, wtw) = (
-        ks.flat_power(),
-        ks.flat_axis(),
-        ks.steep_power(),
-        ks.steep_axis(),
-        cct.map(|cct| cct.inner().to_string())
-            .unwrap_or("{}".to_string()),
-        wtw.map(|wtw| wtw.inner().to_string())
-            .unwrap_or("{}".to_string()),
-    );
-
-    let formula = if let Some(formula) = formula {
-        match formula {
-            Formula::AscrsKrs => "Formula.AscrsKrs",
-            Formula::Barrett => "Formula.Barrett",
-            Formula::BarrettTrueK => "Formula.BarrettTrueK",
-            Formula::Evo => "Formula.Evo",
-            Formula::Haigis => "Formula.Haigis",
-            Formula::HaigisL => "Formula.HaigisL",
-            Formula::HillRbf => "Formula.HillRbf",
-            Formula::HofferQ => "Formula.HofferQ",
-            Formula::Holladay1 => "Formula.Holladay1",
-            Formula::Holladay2 => "Formula.Holladay2",
-            Formula::Kane => "Formula.Kane",
-            Formula::Okulix => "Formula.Okulix",
-            Formula::Olsen => "Formula.Olsen",
-            Formula::SrkT => "Formula.SrkT",
-            Formula::Other => "Formula.Other",
-        }
-    } else {
-        "{}"
-    };
-
-    let target_cyl = if let Some(TargetCyl { power, axis }) = target_cyl {
-        format!("(select(insert TargetCyl {{ power := {power}, axis := {axis} }}))")
-    } else {
-        "{}".to_string()
-    };
-
-    let iol_axis = if let Some(iol_axis) = iol_axis {
-        format!("{iol_axis}")
-    } else {
-        "{}".to_string()
-    };
-
-    let adverse = if let Some(adverse) = adverse {
-        match adverse {
-            Adverse::Rhexis => "Adverse.Rhexis",
-            Adverse::Pc => "Adverse.Pc",
-            Adverse::Zonule => "Adverse.Zonule",
-            Adverse::Other => "Adverse.Other",
-        }
-    } else {
-        "{}"
-    };
-
-    let va_raw_before = if let Some(Va { num, den }) = va_raw_before {
-        format!("(select (insert Va {{ num := {num}, den := {den} }}))")
-    } else {
-        "{}".to_string()
-    };
-
-    let va_best_after = if let Some(Va { num, den }) = va_best_after {
-        format!("(select (insert Va {{ num := {num}, den := {den} }}))")
-    } else {
-        "{}".to_string()
-    };
-
-    let ref_before_cyl = if let Some(RefCyl { power, axis }) = ref_before_cyl {
-        format!("(select (insert RefCyl {{ power := {power}, axis := {axis} }}))")
-    } else {
-        "{}".to_string()
-    };
-
-    let ref_after_cyl = if let Some(RefCyl { power, axis }) = ref_after_cyl {
-        format!("(select (insert RefCyl {{ power := {power}, axis := {axis} }}))")
-    } else {
-        "{}".to_string()
-    };
-
-    let query = format!(
-        r#"
-with QueryBiometry := (insert Biometry {{
-    al := {al},
-
-    ks := (select(insert Ks {{
-        flat := (select(insert K {{
-            power := {flat_k_power},
-            axis := {flat_k_axis}
-        }})),
-
-        steep := (select(insert K {{
-            power := {steep_k_power},
-            axis := {steep_k_axis}
-        }}))
-    }})),
-
-    acd := {acd},
-    lt := {lt},
-    cct := {cct},
-    wtw := {wtw}
-}}),
-
-QueryTarget := (insert Target {{
-    formula := {formula},
-    custom_constant := {custom_constant},
-    se := {target_se},
-    cyl := {target_cyl}
-}}),
-
-QueryIol := (select (insert OpIol {{
-    iol := (select Iol filter .model = "{iol_model}"),
-    se := {iol_se},
-    axis := {iol_axis}
-}})),
-
-QueryVa := (insert OpVa {{
-    before := (select (insert BeforeVa {{
-        best := (select (insert Va {{
-            num := {va_best_before_num},
-            den := {va_best_before_den}
-        }})),
-
-        raw := {va_raw_before}
-    }})),
-
-    after := (select (insert AfterVa {{
-        best := {va_best_after},
-
-        raw := (select (insert Va {{
-            num := {va_raw_after_num},
-            den := {va_raw_after_den}
-        }}))
-    }}))
-}}),
-
-QueryRefraction := (select (insert OpRefraction {{
-    before := (select (insert Refraction {{
-        sph := {ref_before_sph},
-        cyl := {ref_before_cyl}
-    }})),
-
-    after := (select (insert Refraction {{
-        sph := {ref_after_sph},
-        cyl := {ref_after_cyl}
-    }}))
-}})),
-
-QueryCas := (insert Cas {{
-    side := {side},
+    let query = r#"
+with QueryBiometry := (insert Biometry {
+    al := <int64>$al,
+
+    ks := (select(insert Ks {
+        flat := (select(insert K {
+            power := <int64>$flat_k_power,
+            axis := <int64>$flat_k_axis
+        })),
+
+        steep := (select(insert K {
+            power := <int64>$steep_k_power,
+            axis := <int64>$steep_k_axis
+        }))
+    })),
+
+    acd := <int64>$acd,
+    lt := <int64>$lt,
+    cct := <optional int64>$cct,
+    wtw := <optional int64>$wtw
+}),
+
+QueryKsAfter := (
+    for ks in (
+        flat_power := <optional int64>$ks_after_flat_power,
+        flat_axis := <optional int64>$ks_after_flat_axis,
+        steep_power := <optional int64>$ks_after_steep_power,
+        steep_axis := <optional int64>$ks_after_steep_axis
+    ) union (insert Ks {
+        flat := (select (insert K { power := ks.flat_power, axis := ks.flat_axis })),
+        steep := (select (insert K { power := ks.steep_power, axis := ks.steep_axis }))
+    })
+),
+
+QueryTarget := (insert Target {
+    formula := <Formula><optional str>$formula,
+    custom_constant := <bool>$custom_constant,
+    se := <int64>$target_se,
+
+    cyl := (
+        for cyl in (
+            power := <optional int64>$target_cyl_power,
+            axis := <optional int64>$target_cyl_axis
+        ) union (insert TargetCyl { power := cyl.power, axis := cyl.axis })
+    )
+}),
+
+QueryIol := (select (insert OpIol {
+    iol := (select Iol filter .model = <str>$iol_model),
+    se := <int64>$iol_se,
+    axis := <optional int64>$iol_axis
+})),
+
+QueryVa := (insert OpVa {
+    before := (select (insert BeforeVa {
+        best := (select (insert Va {
+            num := <int64>$va_best_before_num,
+            den := <int64>$va_best_before_den
+        })),
+
+        raw := (
+            for va in (
+                num := <optional int64>$va_raw_before_num,
+                den := <optional int64>$va_raw_before_den
+            ) union (insert Va { num := va.num, den := va.den })
+        )
+    })),
+
+    after := (select (insert AfterVa {
+        best := (
+            for va in (
+                num := <optional int64>$va_best_after_num,
+                den := <optional int64>$va_best_after_den
+            ) union (insert Va { num := va.num, den := va.den })
+        ),
+
+        raw := (select (insert Va {
+            num := <int64>$va_raw_after_num,
+            den := <int64>$va_raw_after_den
+        }))
+    }))
+}),
+
+QueryRefraction := (select (insert OpRefraction {
+    before := (select (insert Refraction {
+        sph := <int64>$ref_before_sph,
+
+        cyl := (
+            for cyl in (
+                power := <optional int64>$ref_before_cyl_power,
+                axis := <optional int64>$ref_before_cyl_axis
+            ) union (insert RefCyl { power := cyl.power, axis := cyl.axis })
+        )
+    })),
+
+    after := (select (insert Refraction {
+        sph := <int64>$ref_after_sph,
+
+        cyl := (
+            for cyl in (
+                power := <optional int64>$ref_after_cyl_power,
+                axis := <optional int64>$ref_after_cyl_axis
+            ) union (insert RefCyl { power := cyl.power, axis := cyl.axis })
+        )
+    }))
+})),
+
+QueryCas := (insert Cas {
+    side := <Side><str>$side,
     biometry := (select QueryBiometry),
+    ks_after := (select QueryKsAfter),
     target := (select QueryTarget),
-    year := {year},
-    main := {main},
-    sia := (select (insert Sia {{ power := {sia_power}, axis := {sia_axis} }})),
+    year := <int64>$year,
+    main := <int64>$main,
+    sia := (select (insert Sia { power := <int64>$sia_power, axis := <int64>$sia_axis })),
     iol := (select QueryIol),
-    adverse := {adverse},
+    adverse := <Adverse><optional str>$adverse,
     va := (select QueryVa),
     refraction := (select QueryRefraction)
-}}),
+}),
 
-QuerySurgeonCas := (insert SurgeonCas {{
+QuerySurgeonCas := (insert SurgeonCas {
     surgeon := (select global cur_surgeon),
-    side := {side},
-    date := <cal::local_date>"{date}",
+    side := <Side><str>$side,
+    date := <cal::local_date><str>$date,
 
-    site := (select (insert Site {{
-        name := "{site_name}"
-    }} unless conflict on .name else (select Site))),
+    site := (
+        for name in <optional str>$site union (
+            select (insert Site {
+                name := name
+            } unless conflict on .name else (select Site))
+        )
+    ),
 
     cas := (select QueryCas)
-}})
+})
 
-select QuerySurgeonCas {{
+select QuerySurgeonCas {
     number,
     date,
-    site: {{ name }},
+    site: { name },
 
-    cas: {{
+    case := .cas {
         side,
-        biometry: {{ al, ks, acd, lt, cct, wtw }},
+
+        biometry: {
+            al,
+            ks: { flat: { power, axis }, steep: { power, axis } },
+            acd,
+            lt,
+            cct,
+            wtw
+        },
+
+        ks_after: { flat: { power, axis }, steep: { power, axis } },
+        target: { formula, custom_constant, se, cyl: { power, axis } },
         main,
-        sia: {{ power, axis }},
+        sia: { power, axis },
 
-        iol: {{
-            iol: {{ model, name, company, focus, toric }},
+        iol: {
+            iol: { model, name, company, focus, toric },
             se,
             axis
-        }},
+        },
 
         adverse,
 
-        va: {{
-            before: {{ best: {{ num, den }}, raw: {{ num, den }} }},
-            after: {{ best: {{ num, den }}, raw: {{ num, den }} }},
-        }},
-
-        refraction: {{
-            before: {{ sph, cyl: {{ power, axis}} }},
-            after: {{ sph, cyl: {{ power, axis}} }}
-        }}
-    }}
-}};
-        "#
-    );
-
-    let case = client
-        .query_single_json(query, &())
-        .await?
-        .map(|json| json.as_ref().to_string());
+        va: {
+            before: { best: { num, den }, raw: { num, den } },
+            after: { best: { num, den }, raw: { num, den } },
+        },
+
+        refraction: {
+            before: { sph, cyl: { power, axis} },
+            after: { sph, cyl: { power, axis} }
+        }
+    }
+};
+    "#;
+
+    // Optional objects (such as `ks_after`) are inserted from a tuple of optional arguments, which
+    // is empty (so nothing is inserted) unless all of its elements are present.
+    let args = named_args! {
+        "al" => i64::from(al.inner()),
+        "flat_k_power" => i64::from(ks.flat_power()),
+        "flat_k_axis" => i64::from(ks.flat_axis()),
+        "steep_k_power" => i64::from(ks.steep_power()),
+        "steep_k_axis" => i64::from(ks.steep_axis()),
+        "acd" => i64::from(acd.inner()),
+        "lt" => i64::from(lt.inner()),
+        "cct" => cct.map(|cct| i64::from(cct.inner())),
+        "wtw" => wtw.map(|wtw| i64::from(wtw.inner())),
+        "ks_after_flat_power" => ks_after.map(|ks| i64::from(ks.flat_power())),
+        "ks_after_flat_axis" => ks_after.map(|ks| i64::from(ks.flat_axis())),
+        "ks_after_steep_power" => ks_after.map(|ks| i64::from(ks.steep_power())),
+        "ks_after_steep_axis" => ks_after.map(|ks| i64::from(ks.steep_axis())),
+        "formula" => formula.map(|formula| formula.to_string()),
+        "custom_constant" => custom_constant,
+        "target_se" => i64::from(target_se.inner()),
+        "target_cyl_power" => target_cyl.map(|TargetCyl { power, .. }| i64::from(power.inner())),
+        "target_cyl_axis" => target_cyl.map(|TargetCyl { axis, .. }| i64::from(axis.inner())),
+        "iol_model" => iol_model,
+        "iol_se" => i64::from(iol_se.inner()),
+        "iol_axis" => iol_axis.map(|axis| i64::from(axis.inner())),
+        "va_best_before_num" => i64::from(va_best_before_num.inner()),
+        "va_best_before_den" => i64::from(va_best_before_den.inner()),
+        "va_raw_before_num" => va_raw_before.map(|Va { num, .. }| i64::from(num.inner())),
+        "va_raw_before_den" => va_raw_before.map(|Va { den, .. }| i64::from(den.inner())),
+        "va_best_after_num" => va_best_after.map(|Va { num, .. }| i64::from(num.inner())),
+        "va_best_after_den" => va_best_after.map(|Va { den, .. }| i64::from(den.inner())),
+        "va_raw_after_num" => i64::from(va_raw_after_num.inner()),
+        "va_raw_after_den" => i64::from(va_raw_after_den.inner()),
+        "ref_before_sph" => i64::from(ref_before_sph.inner()),
+        "ref_before_cyl_power" => ref_before_cyl.map(|RefCyl { power, .. }| i64::from(power.inner())),
+        "ref_before_cyl_axis" => ref_before_cyl.map(|RefCyl { axis, .. }| i64::from(axis.inner())),
+        "ref_after_sph" => i64::from(ref_after_sph.inner()),
+        "ref_after_cyl_power" => ref_after_cyl.map(|RefCyl { power, .. }| i64::from(power.inner())),
+        "ref_after_cyl_axis" => ref_after_cyl.map(|RefCyl { axis, .. }| i64::from(axis.inner())),
+        "side" => side.to_string(),
+        "year" => i64::from(date.year()),
+        "main" => i64::from(main.inner()),
+        "sia_power" => i64::from(sia_power.inner()),
+        "sia_axis" => i64::from(sia_axis.inner()),
+        "adverse" => adverse.map(|adverse| adverse.to_string()),
+        "date" => date.to_string(),
+        "site" => site.map(|Site { name }| name),
+    };
 
-    Ok(case)
+    client
+        .query_single::<SurgeonCase, _>(query, &args)
+        .await?
+        .ok_or(AppError::Db("unable to insert the case".to_string()))
 }
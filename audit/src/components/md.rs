@@ -1,4 +1,6 @@
 use std::env;
+#[cfg(feature = "ssr")] use std::path::Component;
+#[cfg(feature = "ssr")] use std::path::Path;
 use std::path::PathBuf;
 use std::sync::LazyLock;
 
@@ -32,8 +34,14 @@ pub trait MdParse {
     fn md_parse(self) -> impl Future<Output = Result<String, AppError>> + Send;
 }
 
-impl MdParse for PathBuf {
-    async fn md_parse(self) -> Result<String, AppError> { markdown_from_file(self).await }
+/// A markdown file in [`MARKDOWN_PATH`], named relative to it (such as `landing.md`).
+#[derive(Clone, Copy, Debug)]
+pub struct MarkdownPage(pub &'static str);
+
+impl MdParse for MarkdownPage {
+    async fn md_parse(self) -> Result<String, AppError> {
+        markdown_from_file(self.0.to_string()).await
+    }
 }
 
 impl MdParse for &str {
@@ -68,9 +76,85 @@ pub fn Markdown<T: MdParse + Clone + Send + Sync + 'static>(md: T) -> impl IntoV
     }
 }
 
-#[server]
-pub async fn markdown_from_file(path: PathBuf) -> Result<String, AppError> {
+// Markdown is public content, so this doesn't require a signed-in surgeon (see `require_auth`).
+#[server(prefix = "/api/public")]
+pub async fn markdown_from_file(page: String) -> Result<String, AppError> {
+    let path = resolve_markdown_page(&MARKDOWN_PATH, &page).await?;
     let markdown = tokio::fs::read_to_string(path).await?;
 
     Ok(to_html(&markdown))
 }
+
+/// Resolve a markdown page to its file in `root`. The page name comes from the client, so only a
+/// relative `.md` path without `..` is accepted, and the file it resolves to (after following any
+/// symlinks) must still be in `root`.
+#[cfg(feature = "ssr")]
+async fn resolve_markdown_page(root: &Path, page: &str) -> Result<PathBuf, AppError> {
+    let invalid = || AppError::Bounds(format!("invalid markdown page {page:?}"));
+    let page = Path::new(page);
+
+    let is_relative = page
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+
+    if !is_relative || page.extension().is_none_or(|extension| extension != "md") {
+        return Err(invalid());
+    }
+
+    let root = tokio::fs::canonicalize(root).await?;
+    let path = tokio::fs::canonicalize(root.join(page)).await?;
+
+    if path.starts_with(&root) {
+        Ok(path)
+    } else {
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "ssr")] use std::fs;
+
+    #[cfg(feature = "ssr")] use uuid::Uuid;
+
+    #[cfg(feature = "ssr")] use super::*;
+
+    #[cfg(feature = "ssr")]
+    #[tokio::test]
+    async fn rejects_markdown_pages_outside_the_directory() {
+        let content = env::temp_dir().join(format!("audit-md-test-{}", Uuid::new_v4()));
+        let root = content.join("markdown");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("landing.md"), "# Landing").unwrap();
+        fs::write(content.join(".env"), "GEL_SECRET_KEY=secret").unwrap();
+
+        let landing = resolve_markdown_page(&root, "landing.md").await.unwrap();
+        assert_eq!(fs::read_to_string(landing).unwrap(), "# Landing");
+
+        for page in [
+            "../.env",
+            "../markdown/landing.md",
+            "./landing.md",
+            "/etc/passwd",
+            ".env",
+            "",
+        ] {
+            assert!(
+                matches!(
+                    resolve_markdown_page(&root, page).await,
+                    Err(AppError::Bounds(_))
+                ),
+                "expected {page:?} to be rejected"
+            );
+        }
+
+        let absolute = root.join("landing.md");
+        assert!(
+            resolve_markdown_page(&root, absolute.to_str().unwrap())
+                .await
+                .is_err()
+        );
+
+        fs::remove_dir_all(content).unwrap();
+    }
+}
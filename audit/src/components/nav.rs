@@ -30,6 +30,7 @@ pub fn Nav() -> impl IntoView {
                     "sign out"
                 </a>
                 <a href="/protected/add">"add a case"</a>
+                <a href="/protected/profile">"profile"</a>
             </div>
         </header>
         <Outlet />
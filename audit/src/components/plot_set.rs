@@ -1,7 +1,10 @@
+use chrono::NaiveDate;
 use leptos::either::Either;
 use leptos::prelude::ClassAttribute;
 use leptos::prelude::ElementChild;
 use leptos::prelude::Get;
+use leptos::prelude::GetUntracked;
+use leptos::prelude::GlobalAttributes;
 use leptos::prelude::InnerHtmlAttribute;
 use leptos::prelude::IntoView;
 use leptos::prelude::Resource;
@@ -11,44 +14,230 @@ use leptos::prelude::Suspense;
 use leptos::prelude::component;
 use leptos::prelude::server;
 use leptos::prelude::view;
+use leptos_router::hooks::use_query_map;
+use leptos_router::params::ParamsMap;
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::bounded::Bounded;
 use crate::error::AppError;
+use crate::model::CaseFilter;
+use crate::model::Cohort;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::Focus;
+use crate::model::Formula;
+use crate::model::Side;
 use crate::model::Year;
+#[cfg(feature = "ssr")] use crate::model::get_current_surgeon;
+#[cfg(feature = "ssr")] use crate::plots::AlpinsMetric;
 #[cfg(feature = "ssr")] use crate::plots::AsPlot;
-#[cfg(feature = "ssr")] use crate::plots::Cohort;
+#[cfg(feature = "ssr")] use crate::plots::BootstrapParams;
+#[cfg(feature = "ssr")] use crate::plots::CartesianData;
+#[cfg(feature = "ssr")] use crate::plots::CaseCompare;
+#[cfg(feature = "ssr")] use crate::plots::Period;
+#[cfg(feature = "ssr")] use crate::plots::RollingWindow;
+#[cfg(feature = "ssr")] use crate::plots::ToricConversion;
+#[cfg(feature = "ssr")] use crate::plots::TrendMetric;
+#[cfg(feature = "ssr")] use crate::plots::TrendParams;
+#[cfg(feature = "ssr")] use crate::plots::describe_significance;
 #[cfg(feature = "ssr")] use crate::plots::get_compare;
+#[cfg(feature = "ssr")] use crate::plots::get_range_compare;
+#[cfg(feature = "ssr")] use crate::plots::get_surgeon_constants;
+#[cfg(feature = "ssr")] use crate::plots::get_trend_cases;
+#[cfg(feature = "ssr")] use crate::theme::PlotTheme;
+use crate::theme::Theme;
+
+/// Controls for the report: a subgroup filter (see [`CaseFilter`]), applied to all of the plots,
+/// and an optional date range to compare (see [`RangePlotSet`]). The form submits its values as
+/// query parameters with a full page load, so that the plots are rendered (and their scripts run)
+/// on the server.
+#[component]
+pub fn ReportForm() -> impl IntoView {
+    let query = use_query_map().get_untracked();
+    let param = |key: &str| query.get(key).unwrap_or_default();
+    let filter = CaseFilter::from_params(|key| query.get(key));
+    let surgeon_selected = matches!(range_params(&query), Some((_, Cohort::Surgeon)));
+
+    let focus_options = Focus::ALL
+        .into_iter()
+        .map(|focus| {
+            let selected = filter.focus.as_ref() == Some(&focus);
+            view! {
+                <option value=focus.to_string() selected=selected>
+                    {focus.label().to_string()}
+                </option>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let formula_options = Formula::ALL
+        .into_iter()
+        .map(|formula| {
+            let selected = filter.formula == Some(formula);
+            view! {
+                <option value=formula.to_string() selected=selected>
+                    {formula.label().to_string()}
+                </option>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let theme = theme_param(&query);
+    let theme_options = Theme::ALL
+        .into_iter()
+        .map(|option| {
+            let selected = option == theme;
+            view! {
+                <option value=option.to_string() selected=selected>
+                    {option.label().to_string()}
+                </option>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let side_options = [Side::Right, Side::Left]
+        .into_iter()
+        .map(|side| {
+            let selected = filter.side.as_ref() == Some(&side);
+            view! {
+                <option value=side.to_string() selected=selected>
+                    {side.to_string()}
+                </option>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    view! {
+        <form method="get" id="report-form" class="report-form">
+            <fieldset id="report-filter">
+                <legend>"Filter cases (applies to both you and the comparison group)"</legend>
+                <label>"IOL model" <input type="text" name="iol" value=param("iol") /></label>
+                <label>
+                    "IOL focus"
+                    <select name="focus">
+                        <option value="">"Any"</option>
+                        {focus_options}
+                    </select>
+                </label>
+                <label>
+                    "Toric"
+                    <select name="toric">
+                        <option value="">"Any"</option>
+                        <option value="true" selected={filter.toric == Some(true)}>
+                            "Toric"
+                        </option>
+                        <option value="false" selected={filter.toric == Some(false)}>
+                            "Nontoric"
+                        </option>
+                    </select>
+                </label>
+                <label>
+                    "Formula"
+                    <select name="formula">
+                        <option value="">"Any"</option>
+                        {formula_options}
+                    </select>
+                </label>
+                <label>
+                    "Site (your cases only)" <input type="text" name="site" value=param("site") />
+                </label>
+                <label>
+                    "Side"
+                    <select name="side">
+                        <option value="">"Any"</option>
+                        {side_options}
+                    </select>
+                </label>
+                <label>
+                    "Minimum AL (mm)"
+                    <input
+                        type="number"
+                        min=12
+                        max=38
+                        step=0.01
+                        name="al_min"
+                        value=param("al_min")
+                    />
+                </label>
+                <label>
+                    "Maximum AL (mm)"
+                    <input
+                        type="number"
+                        min=12
+                        max=38
+                        step=0.01
+                        name="al_max"
+                        value=param("al_max")
+                    />
+                </label>
+            </fieldset>
+            <fieldset id="report-range">
+                <legend>"Compare a date range (optional)"</legend>
+                <label>"From" <input type="date" name="start" value=param("start") /></label>
+                <label>"To" <input type="date" name="end" value=param("end") /></label>
+                <label>
+                    "Compare with"
+                    <select name="cohort">
+                        <option value="Peers" selected={!surgeon_selected}>
+                            "The peer cohort (same years)"
+                        </option>
+                        <option value="Surgeon" selected=surgeon_selected>
+                            "Your cases outside the range"
+                        </option>
+                    </select>
+                </label>
+            </fieldset>
+            <label>"Plot colours" <select name="theme">{theme_options}</select></label>
+            <input type="submit" value="Update report" />
+        </form>
+    }
+}
+
+/// Read a [`CaseFilter`] from the query parameters submitted by [`ReportForm`].
+fn filter_params(query: &ParamsMap) -> CaseFilter { CaseFilter::from_params(|key| query.get(key)) }
+
+/// Read the [`Theme`] from the query parameters submitted by [`ReportForm`].
+fn theme_param(query: &ParamsMap) -> Theme { Theme::from_param(query.get("theme").as_deref()) }
+
+/// Read a [`DateRange`] and [`Cohort`] from the query parameters submitted by [`ReportForm`].
+/// Returns [`None`] if either date is missing or invalid.
+fn range_params(query: &ParamsMap) -> Option<(DateRange, Cohort)> {
+    let date = |key: &str| {
+        query
+            .get(key)
+            .and_then(|date| NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d").ok())
+    };
+
+    let range = DateRange::new(date("start")?, date("end")?).ok()?;
+    let cohort = match query.get("cohort").as_deref() {
+        Some("Surgeon") => Cohort::Surgeon,
+        _ => Cohort::Peers,
+    };
+
+    Some((range, cohort))
+}
 
 #[component]
 pub fn PlotSet() -> impl IntoView {
     let year =
         RwSignal::new(Year::new(2025).expect("2025 should be a valid input to `Year::new()`"));
+    let query = use_query_map();
 
-    let plot_resource = Resource::new_blocking(move || year.get(), move |_| get_plots(year.get()));
+    let plot_resource = Resource::new_blocking(
+        move || {
+            let query = query.get();
+            (year.get(), filter_params(&query), theme_param(&query))
+        },
+        move |(year, filter, theme)| get_plots(year, filter, theme),
+    );
 
     view! {
         <Suspense fallback=|| { "Loading plots..." }>
             <div class="plot-group">
                 {Suspend::new(async move {
                     if let Ok(plots) = plot_resource.await {
-                        let plots = plots
-                            .into_iter()
-                            .map(|PlotSet { title, info, plot }| {
-                                view! {
-                                    <div class="plot-container">
-                                        <div class="plot">
-                                        <h2 class="plot-title">{title}</h2>
-                                        <div class="plot-traces" inner_html=plot></div>
-                                        </div>
-                                        <div class="plot-info">{info}</div>
-                                        </div>
-                                }
-                            })
-                        .collect::<Vec<_>>();
-
-                        Either::Left(plots)
+                        Either::Left(plot_views(plots))
                     } else {
                         Either::Right("no plots were found")
                     }
@@ -58,6 +247,71 @@ pub fn PlotSet() -> impl IntoView {
     }
 }
 
+/// Comparison plots for the surgeon's cases from the date range chosen in [`ReportForm`] (such as
+/// the period since a change in technique), against either the peer cohort or their own cases
+/// outside the range. Nothing is shown until a range is chosen.
+#[component]
+pub fn RangePlotSet() -> impl IntoView {
+    let query = use_query_map();
+
+    let plot_resource = Resource::new_blocking(
+        move || {
+            let query = query.get();
+            (
+                range_params(&query),
+                filter_params(&query),
+                theme_param(&query),
+            )
+        },
+        |(range, filter, theme)| async move {
+            match range {
+                Some((period, cohort)) => {
+                    let params = CompareParams {
+                        period,
+                        cohort,
+                        filter,
+                    };
+
+                    get_range_plots(params, theme).await.map(Some)
+                }
+                None => Ok(None),
+            }
+        },
+    );
+
+    view! {
+        <Suspense fallback=|| { "Loading plots..." }>
+            <div class="plot-group">
+                {Suspend::new(async move {
+                    match plot_resource.await {
+                        Ok(Some(plots)) => Either::Left(plot_views(plots)),
+                        Ok(None) => Either::Right(String::new()),
+                        Err(err) => Either::Right(err.to_string()),
+                    }
+                })}
+            </div>
+        </Suspense>
+    }
+}
+
+/// Render each [`PlotSet`] with its title and information text.
+fn plot_views(plots: Vec<PlotSet>) -> impl IntoView {
+    plots
+        .into_iter()
+        .map(|PlotSet { title, info, plot }| {
+            view! {
+                <div class="plot-container">
+                    <div class="plot">
+                    <h2 class="plot-title">{title}</h2>
+                    <div class="plot-traces" inner_html=plot></div>
+                    </div>
+                    <div class="plot-info">{info}</div>
+                    </div>
+            }
+        })
+        .collect::<Vec<_>>()
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct PlotSet {
     pub title: Option<String>,
@@ -66,22 +320,117 @@ pub struct PlotSet {
 }
 
 #[server]
-pub async fn get_plots(year: Year) -> Result<Vec<PlotSet>, AppError> {
-    let compare = get_compare(year, Cohort::Peers).await?;
+pub async fn get_plots(
+    year: Year,
+    filter: CaseFilter,
+    theme: Theme,
+) -> Result<Vec<PlotSet>, AppError> {
+    let params = CompareParams {
+        period: year,
+        cohort: Cohort::Peers,
+        filter,
+    };
+
+    let compare = get_compare(&params).await?;
+    let theme = theme.plot_theme();
     // Eventually, we will want the surgeon to be able to compare to their prior data.
     //
-    // let self_compare = get_compare(year, Cohort::Surgeon).await?;
+    // let self_compare = get_compare(&CompareParams { cohort: Cohort::Surgeon, ..params }).await?;
     //
     // and then create the same 4 plots.
 
-    let plot = compare
-        .polar_cyl_before()
-        .plot()
-        .to_inline_html(Some("cyl-before"));
+    let trend_cases = get_trend_cases().await?;
+
+    let plot = get_surgeon_constants()
+        .await?
+        .plot(&theme)
+        .to_inline_html(Some("surgeon-constants"));
+
+    let surgeon_constants = PlotSet {
+        title: Some(String::from("Personalized IOL constants")),
+        info: Some(String::from(
+            "Lens constants back-calculated from all of your cases, for each IOL model with at \
+             least 2 cases, that would have zeroed your mean prediction error with each \
+             thin-lens formula. Constants are A for SRK/T, pACD for Hoffer Q, SF for Holladay 1, \
+             and a0 for Haigis.",
+        )),
+        plot,
+    };
+
+    let trends = [
+        (
+            TrendMetric::PredictionError,
+            "trend-prediction-error",
+            "Prediction error over time",
+        ),
+        (
+            TrendMetric::Cylinder,
+            "trend-cylinder",
+            "Postop astigmatism over time",
+        ),
+        (
+            TrendMetric::Adverse,
+            "trend-adverse",
+            "Adverse events over time",
+        ),
+    ]
+    .into_iter()
+    .map(|(metric, id, title)| PlotSet {
+        title: Some(String::from(title)),
+        info: Some(String::from(
+            "Your quarterly mean and rolling mean (over your last 20 cases), across all years, \
+             compared with the peer cohort mean ± 1 SD for each year.",
+        )),
+        plot: trend_cases
+            .trend(TrendParams {
+                metric,
+                period: Period::Quarter,
+                window: RollingWindow::default(),
+            })
+            .plot(&theme)
+            .to_inline_html(Some(id)),
+    });
+
+    Ok(compare_plot_sets(&compare, &theme)
+        .await?
+        .into_iter()
+        .chain([surgeon_constants])
+        .chain(trends)
+        .collect())
+}
+
+/// Create the comparison plots for the surgeon's cases from the given [`DateRange`], against
+/// either the peer cohort from the same years, or the surgeon's own cases outside the range, for
+/// the subgroup in the [`CaseFilter`].
+#[server]
+pub async fn get_range_plots(
+    params: CompareParams<DateRange>,
+    theme: Theme,
+) -> Result<Vec<PlotSet>, AppError> {
+    let compare = get_range_compare(&params).await?;
+
+    compare_plot_sets(&compare, &theme.plot_theme()).await
+}
+
+/// Create the plots that compare the surgeon with their reference group, in display order.
+#[cfg(feature = "ssr")]
+async fn compare_plot_sets(
+    compare: &CaseCompare,
+    theme: &PlotTheme,
+) -> Result<Vec<PlotSet>, AppError> {
+    // Bootstrapping is slow, so it is only done for the plots that show its results.
+    let bootstrap = BootstrapParams::new();
+
+    let cyl_before = compare.polar_cyl_before();
+    let plot = cyl_before.plot(theme).to_inline_html(Some("cyl-before"));
 
     let (title, info) = (
         String::from("Preop astigmatism"),
-        String::from("cyl before info"),
+        format!(
+            "Preop corneal astigmatism in double-angle form, with the centroid and confidence \
+             ellipse for each group. {}",
+            describe_significance(&cyl_before.significance())
+        ),
     );
 
     let cyl_before = PlotSet {
@@ -90,14 +439,16 @@ pub async fn get_plots(year: Year) -> Result<Vec<PlotSet>, AppError> {
         plot,
     };
 
-    let plot = compare
-        .polar_cyl_after()
-        .plot()
-        .to_inline_html(Some("cyl-after"));
+    let cyl_after = compare.polar_cyl_after();
+    let plot = cyl_after.plot(theme).to_inline_html(Some("cyl-after"));
 
     let (title, info) = (
         String::from("Postop astigmatism"),
-        String::from("cyl after info"),
+        format!(
+            "Postop refractive astigmatism in double-angle form, with the centroid and confidence \
+             ellipse for each group. {}",
+            describe_significance(&cyl_after.significance())
+        ),
     );
 
     let cyl_after = PlotSet {
@@ -107,13 +458,37 @@ pub async fn get_plots(year: Year) -> Result<Vec<PlotSet>, AppError> {
     };
 
     let plot = compare
+        .polar_toric(&ToricConversion::default())
+        .plot(theme)
+        .to_inline_html(Some("cyl-toric"));
+
+    let (title, info) = (
+        String::from("Toric IOL cylinder"),
+        String::from(
+            "Toric IOL cylinder at the corneal plane, using an IOL-to-cornea ratio of 1.46.",
+        ),
+    );
+
+    let cyl_toric = PlotSet {
+        title: Some(title),
+        info: Some(info),
+        plot,
+    };
+
+    let cyl_target_error = compare
         .polar_cyl_target_error()
-        .plot()
+        .bootstrap(bootstrap.clone());
+    let plot = cyl_target_error
+        .plot(theme)
         .to_inline_html(Some("cyl-target-error"));
 
     let (title, info) = (
         String::from("Astigmatic target error"),
-        String::from("cyl target error info"),
+        format!(
+            "The difference between the target and achieved postop astigmatism in double-angle \
+             form, with a bootstrap 95% confidence ellipse for each centroid. {}",
+            describe_significance(&cyl_target_error.significance())
+        ),
     );
 
     let cyl_target_error = PlotSet {
@@ -122,14 +497,23 @@ pub async fn get_plots(year: Year) -> Result<Vec<PlotSet>, AppError> {
         plot,
     };
 
-    let plot = compare
-        .cartesian_delta_cyl()
-        .plot()
-        .to_inline_html(Some("cyl-delta"));
+    let cyl_delta = compare.cartesian_delta_cyl();
+    let plot = cyl_delta.plot(theme).to_inline_html(Some("cyl-delta"));
+
+    let regression = |data: &CartesianData| {
+        data.regression()
+            .map_or_else(|| String::from("too few cases"), |fit| fit.to_string())
+    };
 
     let (title, info) = (
         String::from("Astigmatism magnitude"),
-        String::from("cyl delta info"),
+        format!(
+            "Postop refractive astigmatism against preop corneal astigmatism, with the mean ± 1 SD \
+             for each 0.25 D bin of preop astigmatism. Surgeon regression: {}. Peer cohort \
+             regression: {}.",
+            regression(&cyl_delta.surgeon),
+            regression(&cyl_delta.cohort)
+        ),
     );
 
     let cyl_delta = PlotSet {
@@ -138,5 +522,160 @@ pub async fn get_plots(year: Year) -> Result<Vec<PlotSet>, AppError> {
         plot,
     };
 
-    Ok(vec![cyl_before, cyl_after, cyl_target_error, cyl_delta])
+    let prediction_error = compare.prediction_error();
+    let plot = prediction_error
+        .plot(theme)
+        .to_inline_html(Some("se-prediction-error"));
+
+    let (title, info) = (
+        String::from("Spherical equivalent prediction error"),
+        format!(
+            "Surgeon: {}. Peer cohort: {}. Surgeon mean error with bootstrap 95% CI: {}. {}",
+            prediction_error.surgeon,
+            prediction_error.cohort,
+            prediction_error
+                .surgeon
+                .mean_error_interval(&bootstrap)
+                .map_or_else(|| String::from("no cases"), |interval| interval.to_string()),
+            describe_significance(&prediction_error.significance())
+        ),
+    );
+
+    let se_prediction_error = PlotSet {
+        title: Some(title),
+        info: Some(info),
+        plot,
+    };
+
+    let power_vectors = compare.power_vectors();
+    let plot = power_vectors
+        .plot(theme)
+        .to_inline_html(Some("power-vectors"));
+
+    let (title, info) = (
+        String::from("Postop refraction power vectors"),
+        format!(
+            "The astigmatic components of the postop refraction, as Thibos power vectors (J0 \
+             against J45). Surgeon: {}. Peer cohort: {}.",
+            power_vectors.surgeon, power_vectors.cohort
+        ),
+    );
+
+    let power_vectors = PlotSet {
+        title: Some(title),
+        info: Some(info),
+        plot,
+    };
+
+    let cumulative_va = compare.cumulative_va();
+    let plot = cumulative_va
+        .plot(theme)
+        .to_inline_html(Some("cumulative-va"));
+
+    let (title, info) = (
+        String::from("Cumulative visual acuity"),
+        String::from(
+            "Postoperative uncorrected visual acuity, compared with preoperative corrected visual \
+             acuity. Each bar shows the percentage of eyes achieving that Snellen line or better.",
+        ),
+    );
+
+    let cumulative_va = PlotSet {
+        title: Some(title),
+        info: Some(info),
+        plot,
+    };
+
+    let assumed_sia = get_current_surgeon()
+        .await?
+        .map(|surgeon| surgeon.sia)
+        .unwrap_or_default();
+
+    let measured_sia = compare.measured_sia(&assumed_sia);
+    let plot = measured_sia
+        .plot(theme)
+        .to_inline_html(Some("measured-sia"));
+
+    let (title, info) = (
+        String::from("Measured SIA"),
+        format!(
+            "The vector mean of the keratometric change for cases with postop keratometry, \
+             compared with your default SIA. Right eyes: {}. Left eyes: {}.",
+            measured_sia.right, measured_sia.left
+        ),
+    );
+
+    let measured_sia = PlotSet {
+        title: Some(title),
+        info: Some(info),
+        plot,
+    };
+
+    let alpins = compare.alpins();
+    let alpins_info = format!(
+        "Surgeon: {}. Peer cohort: {}.",
+        alpins.surgeon, alpins.cohort
+    );
+
+    let plot = alpins
+        .histogram(AlpinsMetric::AngleOfError)
+        .plot(theme)
+        .to_inline_html(Some("alpins-angle-of-error"));
+
+    let alpins_angle_of_error = PlotSet {
+        title: Some(String::from("Angle of error (Alpins)")),
+        info: Some(alpins_info.clone()),
+        plot,
+    };
+
+    let plot = alpins
+        .histogram(AlpinsMetric::CorrectionIndex)
+        .plot(theme)
+        .to_inline_html(Some("alpins-correction-index"));
+
+    let alpins_correction_index = PlotSet {
+        title: Some(String::from("Correction index (Alpins)")),
+        info: Some(alpins_info),
+        plot,
+    };
+
+    let adverse = compare.adverse();
+    let plot = adverse.plot(theme).to_inline_html(Some("adverse-rates"));
+
+    let adverse_rates = PlotSet {
+        title: Some(String::from("Adverse event rates")),
+        info: Some(format!(
+            "The rate of each adverse event category, with 95% Wilson confidence intervals. {}",
+            describe_significance(&adverse.significance())
+        )),
+        plot,
+    };
+
+    let pcr_funnel = adverse.pcr_funnel();
+    let plot = pcr_funnel.plot(theme).to_inline_html(Some("pcr-funnel"));
+
+    let pcr_funnel = PlotSet {
+        title: Some(String::from("PCR funnel plot")),
+        info: Some(format!(
+            "Your posterior capsule rupture rate against your case volume, with 95% and 99.8% \
+             control limits around the peer cohort rate. {pcr_funnel}."
+        )),
+        plot,
+    };
+
+    Ok(vec![
+        cyl_before,
+        cyl_after,
+        cyl_toric,
+        cyl_target_error,
+        cyl_delta,
+        se_prediction_error,
+        power_vectors,
+        cumulative_va,
+        alpins_angle_of_error,
+        alpins_correction_index,
+        measured_sia,
+        adverse_rates,
+        pcr_funnel,
+    ])
 }
@@ -41,6 +41,11 @@ impl From<mailgun_rs::SendError> for AppError {
     fn from(err: mailgun_rs::SendError) -> Self { Self::Server(format!("{err}")) }
 }
 
+#[cfg(feature = "ssr")]
+impl From<lettre::transport::smtp::Error> for AppError {
+    fn from(err: lettre::transport::smtp::Error) -> Self { Self::Server(format!("{err}")) }
+}
+
 impl<T> From<PoisonError<T>> for AppError {
     fn from(err: PoisonError<T>) -> Self { Self::State(format!("{err}")) }
 }
@@ -1,5 +1,4 @@
 #![deny(clippy::wildcard_imports)]
-#![feature(lock_value_accessors)]
 #![feature(new_range_api)]
 #![feature(string_remove_matches)]
 #![forbid(unsafe_code)]
@@ -23,15 +22,19 @@ pub mod bounded;
 pub mod components;
 #[cfg(feature = "ssr")] pub mod db;
 pub mod error;
+pub mod formula;
 pub mod macros;
 #[cfg(feature = "ssr")] pub mod mail;
 #[cfg(feature = "ssr")] pub mod mock;
 pub mod model;
 #[cfg(feature = "ssr")] pub mod plots;
 #[cfg(feature = "ssr")] pub mod query;
+#[cfg(feature = "ssr")] pub mod report;
+#[cfg(feature = "ssr")] pub mod repository;
 pub mod routes;
 #[cfg(feature = "ssr")] pub mod state;
 #[cfg(test)] pub mod tests;
+pub mod theme;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
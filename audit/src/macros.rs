@@ -1,8 +1,3 @@
-#[macro_export]
-macro_rules! some_or_empty {
-    ($($id:ident),+) => (let ($($id),+) = ($($crate::db::some_or_empty($id),)+);)
-}
-
 #[cfg(test)]
 mod tests {
     use audit_macro::RangeBounded;
@@ -1,37 +1,105 @@
+//! Transactional email. A [`Mailer`] builds transport-neutral [`MailParts`] and hands them to a
+//! [`MailTransport`], which is selected with the `MAIL_TRANSPORT` environment variable.
+
+mod file_drop;
+mod mailgun;
+mod memory;
+mod smtp;
+
 use std::env;
+use std::fmt::Debug;
 use std::sync::Arc;
 use std::sync::LazyLock;
 
+pub use file_drop::*;
+use futures::future::BoxFuture;
 use leptos::prelude::use_context;
-use mailgun_rs::Attachment;
-use mailgun_rs::EmailAddress;
-use mailgun_rs::Mailgun;
-use mailgun_rs::MailgunRegion;
-use mailgun_rs::Message;
-use mailgun_rs::SendResponse;
+use lettre::Address;
+use lettre::message::Attachment;
+use lettre::message::Mailbox;
+use lettre::message::MultiPart;
+use lettre::message::SinglePart;
+use lettre::message::header::ContentType;
+pub use mailgun::*;
+pub use memory::*;
+pub use smtp::*;
 
 use crate::error::AppError;
 use crate::model::Email;
 use crate::model::Surgeon;
+use crate::report::OutcomeReport;
 use crate::state::AppState;
 
-#[derive(Debug)]
+/// A backend that delivers [`MailParts`] on behalf of an [`EmailSender`].
+pub trait MailTransport: Debug + Send + Sync {
+    fn send<'a>(
+        &'a self,
+        sender: &'a EmailSender,
+        parts: &'a MailParts,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+}
+
+#[derive(Clone, Debug)]
 pub struct Mailer {
     pub sender: EmailSender,
-    pub mailgun: Mailgun,
+    pub transport: Arc<dyn MailTransport>,
 }
 
-// This impl is necessary because the [`Mailgun`] foreign type is not [`Clone`].
-impl Clone for Mailer {
-    fn clone(&self) -> Self {
+impl Mailer {
+    pub fn new(sender: EmailSender, transport: impl MailTransport + 'static) -> Self {
         Self {
-            sender: self.sender.clone(),
-            mailgun: Mailgun {
-                api_key: self.mailgun.api_key.clone(),
-                domain: self.mailgun.domain.clone(),
-            },
+            sender,
+            transport: Arc::new(transport),
         }
     }
+
+    /// Configure a [`Mailer`] from environment variables. `MAIL_TRANSPORT` is one of `mailgun`
+    /// (the default), `smtp` or `file` (or `memory`, in tests), and each transport reads its own
+    /// variables (see `.sample.env`).
+    pub fn from_env() -> Result<Self, AppError> {
+        let sender = EmailSender {
+            name: env_var_or("MAIL_SENDER_NAME", "MAILGUN_SENDER_NAME")?,
+            email: env_var_or("MAIL_SENDER_EMAIL", "MAILGUN_SENDER_EMAIL")?.try_into()?,
+        };
+
+        let transport: Arc<dyn MailTransport> =
+            match env::var("MAIL_TRANSPORT").unwrap_or_default().as_str() {
+                "" | "mailgun" => Arc::new(MailgunTransport::from_env()?),
+                "smtp" => Arc::new(SmtpTransport::from_env()?),
+                "file" => Arc::new(FileDropTransport::new(env_var("MAIL_DROP_DIR")?)),
+                // Emails sent in memory are never delivered, so a misconfigured server would
+                // silently drop them.
+                #[cfg(test)]
+                "memory" => Arc::new(MemoryTransport::default()),
+                other => {
+                    return Err(AppError::Server(format!(
+                        "unknown MAIL_TRANSPORT {other:?}, expected mailgun, smtp or file"
+                    )));
+                }
+            };
+
+        Ok(Self { sender, transport })
+    }
+
+    pub async fn send(&self, parts: &MailParts) -> Result<(), AppError> {
+        self.transport.send(&self.sender, parts).await
+    }
+}
+
+/// Read a required environment variable for mail configuration.
+fn env_var(key: &str) -> Result<String, AppError> {
+    env::var(key)
+        .map_err(|_| AppError::Server(format!("expected {key} environment variable to be present")))
+}
+
+/// Read a required environment variable for mail configuration, falling back to the `legacy`
+/// variable that it replaced, so that existing deployments keep working.
+fn env_var_or(key: &str, legacy: &str) -> Result<String, AppError> {
+    env::var(key).or_else(|_| env::var(legacy)).map_err(|_| {
+        AppError::Server(format!(
+            "expected {key} (or {legacy}) environment variable to be present"
+        ))
+    })
 }
 
 pub fn mailer() -> Result<Arc<Mailer>, AppError> {
@@ -50,80 +118,50 @@ pub struct EmailSender {
     pub email: Email,
 }
 
-impl From<EmailSender> for EmailAddress {
-    fn from(sender: EmailSender) -> Self {
-        EmailAddress::builder()
-            .name(Some(sender.name))
-            .address(sender.email.inner())
-            .build()
-    }
-}
-
 pub static MAILER: LazyLock<Mailer> = LazyLock::new(|| {
-    let api_key = env::var("MAILGUN_API_KEY")
-        .expect("expected MAILGUN_API_KEY environment variable to be present");
-
-    let domain = env::var("MAILGUN_DOMAIN")
-        .expect("expected MAILGUN_DOMAIN environment variable to be present");
-
-    let sender_name = env::var("MAILGUN_SENDER_NAME")
-        .expect("expected MAILGUN_SENDER_NAME environment variable to be present");
-
-    let sender_email: Email = env::var("MAILGUN_SENDER_EMAIL")
-        .expect("expected MAILGUN_SENDER_EMAIL environment variable to be present")
-        .try_into()
-        .expect("expected the MAILGUN_SENDER_EMAIL environment variable to be a valid email");
-
-    Mailer {
-        sender: EmailSender {
-            name: sender_name,
-            email: sender_email,
-        },
-        mailgun: Mailgun { api_key, domain },
-    }
+    Mailer::from_env().expect("expected the mail transport to be configured in the environment")
 });
 
 pub enum EmailType {
     Welcome,
+    /// A scheduled outcome report, with its plots attached.
+    Report(OutcomeReport),
 }
 
-pub fn email_sign_up(surgeon: &Surgeon) -> Message {
-    let name = if let Some(full_name) = surgeon.full_name.clone() {
-        full_name
-    } else {
-        surgeon.email.inner()
-    };
-
-    Message::builder()
-        .to(vec![
-            EmailAddress::builder()
-                .name(surgeon.full_name.clone())
-                .address(surgeon.email.inner())
-                .build(),
-        ])
-        .subject("the subject")
-        .text(format!("Thanks for signing up, {name}!",))
-        .build()
-}
-
-pub struct MailParts {
-    region: MailgunRegion,
-    sender: Email,
-    message: Message,
-    attachments: Option<Vec<Attachment>>,
+/// A recipient of an email.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipient {
+    pub name: Option<String>,
+    pub email: Email,
 }
 
-impl Default for MailParts {
-    fn default() -> Self {
+impl From<&Surgeon> for Recipient {
+    fn from(surgeon: &Surgeon) -> Self {
         Self {
-            region: MailgunRegion::US,
-            sender: Email::new("no-reply@viceye.au").unwrap(),
-            message: Default::default(),
-            attachments: None,
+            name: surgeon.full_name.clone(),
+            email: surgeon.email.clone(),
         }
     }
 }
 
+/// A file attached to an email.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MailAttachment {
+    pub filename: String,
+    /// The MIME type, such as `image/svg+xml`.
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// A plain text email, independent of the [`MailTransport`] that delivers it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MailParts {
+    pub to: Vec<Recipient>,
+    pub subject: String,
+    pub text: String,
+    pub attachments: Vec<MailAttachment>,
+}
+
 impl MailParts {
     pub fn new() -> Self {
         Self {
@@ -131,35 +169,102 @@ impl MailParts {
         }
     }
 
-    pub fn region(mut self, region: MailgunRegion) -> Self {
-        self.region = region;
+    pub fn to(mut self, to: Vec<Recipient>) -> Self {
+        self.to = to;
 
         self
     }
 
-    pub fn sender(mut self, sender: Email) -> Self {
-        self.sender = sender;
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = subject.into();
 
         self
     }
 
-    pub fn message(mut self, message: Message) -> Self {
-        self.message = message;
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = text.into();
 
         self
     }
 
-    pub fn attachments(mut self, attachments: Vec<Attachment>) -> Self {
-        self.attachments = Some(attachments);
+    pub fn attachments(mut self, attachments: Vec<MailAttachment>) -> Self {
+        self.attachments = attachments;
 
         self
     }
+
+    /// Build a MIME message, for transports that deliver standard email rather than calling an API.
+    pub fn mime(&self, sender: &EmailSender) -> Result<lettre::Message, AppError> {
+        fn mailbox(name: Option<String>, email: &Email) -> Result<Mailbox, AppError> {
+            let address = email
+                .inner()
+                .parse::<Address>()
+                .map_err(|err| AppError::Bounds(format!("invalid email address {email}: {err}")))?;
+
+            Ok(Mailbox::new(name, address))
+        }
+
+        let mut builder = lettre::Message::builder()
+            .from(mailbox(Some(sender.name.clone()), &sender.email)?)
+            .subject(&self.subject);
+
+        for recipient in &self.to {
+            builder = builder.to(mailbox(recipient.name.clone(), &recipient.email)?);
+        }
+
+        let text = SinglePart::plain(self.text.clone());
+
+        let message = if self.attachments.is_empty() {
+            builder.singlepart(text)
+        } else {
+            let multipart = self.attachments.iter().try_fold(
+                MultiPart::mixed().singlepart(text),
+                |multipart, attachment| {
+                    let content_type = ContentType::parse(&attachment.content_type)
+                        .map_err(|err| AppError::Bounds(format!("{err}")))?;
+
+                    let part = Attachment::new(attachment.filename.clone())
+                        .body(attachment.content.clone(), content_type);
+
+                    Ok::<_, AppError>(multipart.singlepart(part))
+                },
+            )?;
+
+            builder.multipart(multipart)
+        };
+
+        message.map_err(|err| AppError::Server(format!("unable to build email: {err}")))
+    }
 }
 
-pub async fn transactional_email(
-    surgeon: &Surgeon,
-    email_type: EmailType,
-) -> Result<SendResponse, AppError> {
+pub fn email_sign_up(surgeon: &Surgeon) -> MailParts {
+    let name = if let Some(full_name) = surgeon.full_name.clone() {
+        full_name
+    } else {
+        surgeon.email.inner()
+    };
+
+    MailParts::new()
+        .to(vec![surgeon.into()])
+        .subject("the subject")
+        .text(format!("Thanks for signing up, {name}!",))
+}
+
+pub fn email_report(surgeon: &Surgeon, report: &OutcomeReport) -> MailParts {
+    let name = surgeon
+        .preferred_name
+        .clone()
+        .or(surgeon.full_name.clone())
+        .unwrap_or(surgeon.email.inner());
+
+    MailParts::new()
+        .to(vec![surgeon.into()])
+        .subject(report.subject())
+        .text(format!("Hi {name},\n\n{}", report.body()))
+        .attachments(report.attachments.clone())
+}
+
+pub async fn transactional_email(surgeon: &Surgeon, email_type: EmailType) -> Result<(), AppError> {
     let mailer = mailer()?;
 
     transactional_email_with_mailer(surgeon, email_type, mailer).await
@@ -173,23 +278,59 @@ pub(crate) async fn transactional_email_with_mailer(
     surgeon: &Surgeon,
     email_type: EmailType,
     mailer: Arc<Mailer>,
-) -> Result<SendResponse, AppError> {
-    let message = match email_type {
+) -> Result<(), AppError> {
+    let parts = match email_type {
         EmailType::Welcome => email_sign_up(surgeon),
+        EmailType::Report(report) => email_report(surgeon, &report),
     };
 
-    let sender: EmailAddress = mailer.sender.clone().into();
+    mailer.send(&parts).await
+}
 
-    let result = mailer
-        .mailgun
-        .async_send(MailgunRegion::US, &sender, message, None)
-        .await;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    match result {
-        Ok(response) => Ok(response),
-        Err(err) => Err(err.into()),
+    fn sender() -> EmailSender {
+        EmailSender {
+            name: String::from("Cataract Audit"),
+            email: Email::new("no-reply@example.com").unwrap(),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {}
+    fn parts() -> MailParts {
+        MailParts::new()
+            .to(vec![Recipient {
+                name: Some(String::from("Jane Surgeon")),
+                email: Email::new("jane@example.com").unwrap(),
+            }])
+            .subject("Your report")
+            .text("Hi Jane")
+    }
+
+    #[test]
+    fn builds_plain_mime_message() {
+        let message = String::from_utf8(parts().mime(&sender()).unwrap().formatted()).unwrap();
+
+        assert!(message.contains("From: \"Cataract Audit\" <no-reply@example.com>\r\n"));
+        assert!(message.contains("To: \"Jane Surgeon\" <jane@example.com>\r\n"));
+        assert!(message.contains("Subject: Your report\r\n"));
+        assert!(message.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(message.ends_with("Hi Jane\r\n"));
+    }
+
+    #[test]
+    fn builds_mime_message_with_attachments() {
+        let parts = parts().attachments(vec![MailAttachment {
+            filename: String::from("plot.svg"),
+            content_type: String::from("image/svg+xml"),
+            content: b"<svg></svg>".to_vec(),
+        }]);
+
+        let message = String::from_utf8(parts.mime(&sender()).unwrap().formatted()).unwrap();
+
+        assert!(message.contains("Content-Type: multipart/mixed;"));
+        assert!(message.contains("Content-Disposition: attachment; filename=\"plot.svg\"\r\n"));
+        assert!(message.contains("Content-Type: image/svg+xml\r\n"));
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use uuid::Uuid;
+
+use super::EmailSender;
+use super::MailParts;
+use super::MailTransport;
+use crate::error::AppError;
+
+/// Write each email to a `.eml` file in a directory, instead of delivering it. Useful for
+/// development, and for deployments where another process picks up and relays the files.
+#[derive(Debug)]
+pub struct FileDropTransport {
+    dir: PathBuf,
+}
+
+impl FileDropTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self { Self { dir: dir.into() } }
+}
+
+impl MailTransport for FileDropTransport {
+    fn send<'a>(
+        &'a self,
+        sender: &'a EmailSender,
+        parts: &'a MailParts,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let message = parts.mime(sender)?;
+            fs::create_dir_all(&self.dir)?;
+
+            // The timestamp prefix keeps the files in the order they were sent.
+            let filename = format!(
+                "{}-{}.eml",
+                Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+                Uuid::new_v4()
+            );
+
+            fs::write(self.dir.join(filename), message.formatted())?;
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::mail::Recipient;
+    use crate::model::Email;
+
+    #[tokio::test]
+    async fn writes_eml_files() {
+        let dir = env::temp_dir().join(format!("audit-mail-test-{}", Uuid::new_v4()));
+        let transport = FileDropTransport::new(&dir);
+
+        let sender = EmailSender {
+            name: String::from("Cataract Audit"),
+            email: Email::new("no-reply@example.com").unwrap(),
+        };
+
+        let parts = MailParts::new()
+            .to(vec![Recipient {
+                name: None,
+                email: Email::new("jane@example.com").unwrap(),
+            }])
+            .subject("Dropped")
+            .text("Written to disk");
+
+        transport.send(&sender, &parts).await.unwrap();
+
+        let files = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+
+        let message = fs::read_to_string(&files[0]).unwrap();
+        assert!(message.contains("To: jane@example.com\r\n"));
+        assert!(message.contains("Subject: Dropped\r\n"));
+        assert!(message.ends_with("Written to disk\r\n"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
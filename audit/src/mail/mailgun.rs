@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use futures::future::BoxFuture;
+use mailgun_rs::Attachment;
+use mailgun_rs::EmailAddress;
+use mailgun_rs::Mailgun;
+use mailgun_rs::MailgunRegion;
+use mailgun_rs::Message;
+use uuid::Uuid;
+
+use super::EmailSender;
+use super::MailAttachment;
+use super::MailParts;
+use super::MailTransport;
+use super::env_var;
+use crate::error::AppError;
+
+/// Deliver email through the Mailgun API.
+#[derive(Debug)]
+pub struct MailgunTransport {
+    mailgun: Mailgun,
+    /// Whether the domain is hosted in Mailgun's EU region, rather than the US region.
+    eu: bool,
+}
+
+impl MailgunTransport {
+    pub fn new(api_key: String, domain: String, eu: bool) -> Self {
+        Self {
+            mailgun: Mailgun { api_key, domain },
+            eu,
+        }
+    }
+
+    /// Configure the transport from `MAILGUN_API_KEY`, `MAILGUN_DOMAIN` and the optional
+    /// `MAILGUN_REGION` (`us` or `eu`, defaulting to `us`).
+    pub fn from_env() -> Result<Self, AppError> {
+        let eu = match env::var("MAILGUN_REGION")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "us" => false,
+            "eu" => true,
+            other => {
+                return Err(AppError::Server(format!(
+                    "unknown MAILGUN_REGION {other:?}, expected us or eu"
+                )));
+            }
+        };
+
+        Ok(Self::new(
+            env_var("MAILGUN_API_KEY")?,
+            env_var("MAILGUN_DOMAIN")?,
+            eu,
+        ))
+    }
+
+    fn region(&self) -> MailgunRegion {
+        if self.eu {
+            MailgunRegion::EU
+        } else {
+            MailgunRegion::US
+        }
+    }
+}
+
+impl MailTransport for MailgunTransport {
+    fn send<'a>(
+        &'a self,
+        sender: &'a EmailSender,
+        parts: &'a MailParts,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let to = parts
+                .to
+                .iter()
+                .map(|recipient| {
+                    EmailAddress::builder()
+                        .name(recipient.name.clone())
+                        .address(recipient.email.inner())
+                        .build()
+                })
+                .collect();
+
+            let message = Message::builder()
+                .to(to)
+                .subject(parts.subject.clone())
+                .text(parts.text.clone())
+                .build();
+
+            let sender = EmailAddress::builder()
+                .name(Some(sender.name.clone()))
+                .address(sender.email.inner())
+                .build();
+
+            let (attachment_dir, attachments) = if parts.attachments.is_empty() {
+                (None, None)
+            } else {
+                let (dir, attachments) = write_attachments(&parts.attachments)?;
+                (Some(dir), Some(attachments))
+            };
+
+            let result = self
+                .mailgun
+                .async_send(self.region(), &sender, message, attachments)
+                .await;
+
+            if let Some(dir) = attachment_dir {
+                fs::remove_dir_all(dir)?;
+            }
+
+            result?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Mailgun attaches files from disk, so write each attachment to a new temporary directory. The
+/// caller is responsible for removing the directory after sending.
+fn write_attachments(
+    attachments: &[MailAttachment],
+) -> Result<(PathBuf, Vec<Attachment>), AppError> {
+    let dir = env::temp_dir().join(format!("audit-mail-{}", Uuid::new_v4()));
+    fs::create_dir(&dir)?;
+
+    let attachments = attachments
+        .iter()
+        .map(|attachment| {
+            let path = dir.join(&attachment.filename);
+            fs::write(&path, &attachment.content)?;
+
+            Ok(Attachment::from(path.to_string_lossy().into_owned()))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    Ok((dir, attachments))
+}
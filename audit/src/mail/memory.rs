@@ -0,0 +1,39 @@
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+
+use super::EmailSender;
+use super::MailParts;
+use super::MailTransport;
+use crate::error::AppError;
+
+/// Capture emails in memory instead of delivering them, so that tests can make assertions about
+/// their content.
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    sent: Mutex<Vec<MailParts>>,
+}
+
+impl MemoryTransport {
+    /// The emails sent so far, in the order they were sent.
+    pub fn messages(&self) -> Vec<MailParts> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl MailTransport for MemoryTransport {
+    fn send<'a>(
+        &'a self,
+        _sender: &'a EmailSender,
+        parts: &'a MailParts,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            self.sent.lock()?.push(parts.clone());
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,71 @@
+use std::env;
+
+use futures::future::BoxFuture;
+use lettre::AsyncSmtpTransport;
+use lettre::AsyncTransport;
+use lettre::Tokio1Executor;
+use lettre::transport::smtp::authentication::Credentials;
+
+use super::EmailSender;
+use super::MailParts;
+use super::MailTransport;
+use super::env_var;
+use crate::error::AppError;
+
+/// The default port for unencrypted SMTP relays.
+const DEFAULT_PORT: u16 = 25;
+
+/// Deliver email to a plain SMTP relay, such as a hospital mail server on the local network. The
+/// connection is unencrypted, so the relay must be trusted.
+#[derive(Debug)]
+pub struct SmtpTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(host: &str, port: u16, credentials: Option<Credentials>) -> Self {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host).port(port);
+
+        if let Some(credentials) = credentials {
+            builder = builder.credentials(credentials);
+        }
+
+        Self {
+            transport: builder.build(),
+        }
+    }
+
+    /// Configure the transport from `SMTP_HOST`, and the optional `SMTP_PORT` (defaulting to 25),
+    /// `SMTP_USERNAME` and `SMTP_PASSWORD`.
+    pub fn from_env() -> Result<Self, AppError> {
+        let host = env_var("SMTP_HOST")?;
+
+        let port = match env::var("SMTP_PORT") {
+            Ok(port) => port
+                .parse()
+                .map_err(|_| AppError::Server(format!("invalid SMTP_PORT {port:?}")))?,
+            Err(_) => DEFAULT_PORT,
+        };
+
+        let credentials = match (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => Some(Credentials::new(username, password)),
+            _ => None,
+        };
+
+        Ok(Self::new(&host, port, credentials))
+    }
+}
+
+impl MailTransport for SmtpTransport {
+    fn send<'a>(
+        &'a self,
+        sender: &'a EmailSender,
+        parts: &'a MailParts,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            self.transport.send(parts.mime(sender)?).await?;
+
+            Ok(())
+        })
+    }
+}
@@ -2,16 +2,19 @@
 #[tokio::main]
 async fn main() {
     use std::sync::Arc;
-    use std::sync::RwLock;
 
     use audit::auth::handle_kill_session;
     use audit::auth::handle_pkce_code;
     use audit::auth::handle_sign_in;
+    use audit::auth::require_auth;
     use audit::mail::MAILER;
+    use audit::report::schedule_reports;
+    use audit::repository::GelRepository;
     use audit::routes::App;
     use audit::routes::shell;
     use audit::state::AppState;
     use axum::Router;
+    use axum::middleware::from_fn;
     use axum::routing::get;
     #[cfg(debug_assertions)] use dotenvy::dotenv;
     use leptos::logging::log;
@@ -34,11 +37,16 @@ async fn main() {
 
     let app_state = AppState {
         leptos_options: leptos_options.clone(),
-        db: Arc::new(RwLock::new(db_client)),
+        repository: Arc::new(GelRepository::new(db_client)),
         mailer: Arc::new(MAILER.clone()),
-        surgeon: Arc::new(RwLock::new(None)),
     };
 
+    // Report emails are sent from a background task, for the lifetime of the server.
+    tokio::spawn(schedule_reports(
+        Arc::clone(&app_state.repository),
+        Arc::clone(&app_state.mailer),
+    ));
+
     let app = Router::new()
         .route("/code", get(handle_pkce_code))
         .route("/killsession", get(handle_kill_session))
@@ -48,6 +56,8 @@ async fn main() {
             move || shell(leptos_options.clone())
         })
         .fallback(leptos_axum::file_and_error_handler::<AppState, _>(shell))
+        // Validates the auth token for protected pages and server functions.
+        .layer(from_fn(require_auth))
         .with_state(app_state);
 
     // run our app with hyper
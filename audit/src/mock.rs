@@ -39,6 +39,7 @@ use crate::model::RefCyl;
 use crate::model::RefCylPower;
 use crate::model::RefSph;
 use crate::model::Refraction;
+use crate::model::ReportFrequency;
 use crate::model::Sia;
 use crate::model::SiaPower;
 use crate::model::Side;
@@ -174,10 +175,7 @@ impl Mock for Biometry {
     fn mock() -> Self {
         Biometry {
             al: Al::mock(),
-            ks: Ks::new(
-                K::new(Kpower::mock(), Axis::mock()),
-                K::new(Kpower::mock(), Axis::mock()),
-            ),
+            ks: Ks::mock(),
             acd: Acd::mock(),
             lt: Lt::mock(),
             cct: Cct::mock_option(Prob::new(0.05).unwrap_or_default()),
@@ -191,6 +189,7 @@ impl Mock for Case {
         Self {
             side: Side::mock(),
             biometry: Biometry::mock(),
+            ks_after: Ks::mock_option(Prob::new(0.5).unwrap_or_default()),
             target: Target::mock(),
             main: Main::mock(),
             sia: Sia::new(SiaPower::mock(), Axis::mock()),
@@ -277,6 +276,15 @@ impl Mock for Iol {
     }
 }
 
+impl Mock for Ks {
+    fn mock() -> Self {
+        Ks::new(
+            K::new(Kpower::mock(), Axis::mock()),
+            K::new(Kpower::mock(), Axis::mock()),
+        )
+    }
+}
+
 impl Mock for OpIol {
     fn mock() -> Self {
         let iol = Iol::mock();
@@ -361,6 +369,17 @@ impl Mock for Sia {
         }
     }
 }
+impl Mock for ReportFrequency {
+    fn mock() -> Self {
+        let sample: bool = rng().sample(StandardUniform);
+
+        match sample {
+            true => ReportFrequency::Monthly,
+            false => ReportFrequency::Annual,
+        }
+    }
+}
+
 impl Mock for Side {
     fn mock() -> Self {
         let sample: bool = rng().sample(StandardUniform);
@@ -401,6 +420,7 @@ impl Mock for Surgeon {
                 .map(|name| name.into_inner()),
             defaults: SurgeonDefaults::mock_option(Prob::new(0.01).unwrap_or_default()),
             sia: SurgeonSia::mock(),
+            report_frequency: ReportFrequency::mock_option(Prob::new(0.5).unwrap_or_default()),
         }
     }
 }
@@ -1,7 +1,10 @@
 pub mod biometry;
 pub mod case;
+pub mod constant;
 pub mod cyl;
+pub mod filter;
 pub mod iol;
+pub mod power_vector;
 pub mod refraction;
 pub mod sca;
 pub mod sia;
@@ -13,9 +16,13 @@ use audit_macro::RangeBounded;
 pub use biometry::*;
 pub use case::*;
 use chrono::Datelike;
+use chrono::NaiveDate;
 use chrono::Utc;
+pub use constant::*;
 pub use cyl::*;
+pub use filter::*;
 pub use iol::*;
+pub use power_vector::*;
 pub use refraction::*;
 pub use sca::*;
 use serde::Deserialize;
@@ -26,6 +33,88 @@ pub use target::*;
 pub use va::*;
 
 use crate::bounded::Bounded;
+use crate::error::AppError;
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, RangeBounded, Serialize)]
 pub struct Year(#[bounded(range = 2025..=2100, default = Utc::now().year() as u32)] u32);
+
+/// The reference group for plot comparisons. For a [`Year`], this is either the full cohort of
+/// surgeons participating (same year), or the current surgeon (prior year). For a [`DateRange`],
+/// this is either the full cohort (same years as the range), or the current surgeon (all of their
+/// cases outside the range).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Cohort {
+    #[default]
+    Peers,
+    Surgeon,
+}
+
+/// An inclusive range of surgery dates, such as the period since a change in technique.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DateRange {
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl Default for DateRange {
+    /// The year to date.
+    fn default() -> Self {
+        let end = Utc::now().date_naive();
+        let start = end.with_ordinal(1).unwrap_or(end);
+
+        Self { start, end }
+    }
+}
+
+impl DateRange {
+    /// Construct a new [`DateRange`]. Returns an error if `start` is after `end`.
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Result<Self, AppError> {
+        if start > end {
+            return Err(AppError::Bounds(format!(
+                "the start date ({start}) should not be after the end date ({end})"
+            )));
+        }
+
+        Ok(Self { start, end })
+    }
+
+    pub fn start(&self) -> NaiveDate { self.start }
+
+    pub fn end(&self) -> NaiveDate { self.end }
+
+    /// Return whether the range includes the given date.
+    pub fn contains(&self, date: &NaiveDate) -> bool { (self.start..=self.end).contains(date) }
+}
+
+/// The parameters of a comparison: the surgeon's cases from a `period` (a [`Year`] or a
+/// [`DateRange`]), against the [`Cohort`], restricted to the subgroup in the [`CaseFilter`].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CompareParams<P> {
+    pub period: P,
+    pub cohort: Cohort,
+    pub filter: CaseFilter,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn makes_date_range() {
+        let range = DateRange::new(date(2025, 3, 1), date(2025, 9, 30)).unwrap();
+
+        assert!(range.contains(&date(2025, 3, 1)));
+        assert!(range.contains(&date(2025, 9, 30)));
+        assert!(!range.contains(&date(2025, 10, 1)));
+        assert!(DateRange::new(date(2025, 3, 1), date(2025, 3, 1)).is_ok());
+    }
+
+    #[test]
+    fn reversed_date_range_returns_err() {
+        assert!(DateRange::new(date(2025, 9, 30), date(2025, 3, 1)).is_err());
+    }
+}
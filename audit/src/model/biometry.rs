@@ -1,4 +1,10 @@
 use audit_macro::RangeBounded;
+#[cfg(feature = "ssr")] use gel_protocol::descriptors::TypePos;
+#[cfg(feature = "ssr")] use gel_protocol::errors::DecodeError;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::Decoder;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::DescriptorContext;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::DescriptorMismatch;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::Queryable;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -26,6 +32,7 @@ pub struct Lt(#[bounded(range = 200..=800, default = 450, mock_range = 350..=550
 pub struct Wtw(#[bounded(range = 800..=1400, default = 1200, mock_range = 1000..=1300)] u32);
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct K {
     pub power: Kpower,
     pub axis: Axis,
@@ -41,13 +48,40 @@ impl K {
     pub fn new(power: Kpower, axis: Axis) -> Self { Self { power, axis } }
 }
 
-// Safety: These fields are private to enforce the invariant that flat <= steep.
+// Safety: These fields are private to enforce the invariant that flat <= steep. Decoding from the
+// DB goes through `new`, so the invariant holds even for rows that weren't inserted by this app.
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct Ks {
     flat: K,
     steep: K,
 }
 
+/// The [`K`]s of a [`Ks`] as stored in the DB, before the invariant is enforced.
+#[cfg(feature = "ssr")]
+#[derive(gel_derive::Queryable)]
+struct DbKs {
+    flat: K,
+    steep: K,
+}
+
+#[cfg(feature = "ssr")]
+impl Queryable for Ks {
+    type Args = <DbKs as Queryable>::Args;
+
+    fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8]) -> Result<Self, DecodeError> {
+        let DbKs { flat, steep } = DbKs::decode(decoder, args, buf)?;
+
+        Ok(Ks::new(flat, steep))
+    }
+
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Self::Args, DescriptorMismatch> {
+        DbKs::check_descriptor(ctx, type_pos)
+    }
+}
+
 impl Ks {
     pub fn new(k1: K, k2: K) -> Self {
         if k1.power.inner() <= k2.power.inner() {
@@ -75,6 +109,7 @@ impl Ks {
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Biometry {
     pub al: Al,
     pub ks: Ks,
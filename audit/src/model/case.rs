@@ -9,15 +9,18 @@ use crate::bounded::Bounded;
 #[cfg(feature = "ssr")] use crate::error::AppError;
 use crate::model::Biometry;
 use crate::model::Formula;
+use crate::model::Ks;
 use crate::model::OpIol;
 use crate::model::OpRefraction;
 use crate::model::OpVa;
 use crate::model::Sia;
 use crate::model::Site;
 use crate::model::Target;
+#[cfg(feature = "ssr")] use crate::repository::Repository;
 
 /// The side of the patient's surgery.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub enum Side {
     #[default]
     Right,
@@ -34,15 +37,6 @@ impl Display for Side {
     }
 }
 
-impl Side {
-    pub fn to_db_side(&self) -> &str {
-        match self {
-            Self::Right => "Side.Right",
-            Self::Left => "Side.Left",
-        }
-    }
-}
-
 /// An adverse intraoperative event. Classification is at the surgeon's discretion, and only one
 /// option can be selected. For example, a wrap around split in the rhexis opens the PC, but in the
 /// surgeon's view it may be essentially a rhexis complication. For our purposes, we aren't
@@ -50,6 +44,7 @@ impl Side {
 /// vitrectomy was required). We are interested only in the relative outcomes of cases with adverse
 /// events versus those without.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub enum Adverse {
     Rhexis,
     Pc,
@@ -73,9 +68,12 @@ pub struct Main(#[bounded(range = 100..=600, default = 240, mock_range = 220..=2
 
 /// A single surgical case.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Case {
     pub side: Side,
     pub biometry: Biometry,
+    /// Postoperative keratometry, which allows the SIA to be measured rather than assumed.
+    pub ks_after: Option<Ks>,
     pub target: Target,
     pub main: Main,
     pub sia: Sia,
@@ -86,14 +84,14 @@ pub struct Case {
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct SurgeonCase {
     /// A unique value that allows (only) the surgeon to deanonymize the case. URNs and other
     /// unique identifiers are not permitted in the DB. We use `number` rather than `id` or
-    /// `identifier` because those terms are easily confused with the `id: UUID` field. It is
-    /// highly unlikely that we would ever require such a large number of cases, but using a
-    /// [`u64`] guarantees that the value, which on the DB side is an auto-incrementing int64
-    /// (sequence), can never be out of bounds during deserialization.
-    pub number: u64,
+    /// `identifier` because those terms are easily confused with the `id: UUID` field. The value
+    /// is an auto-incrementing int64 (sequence) on the DB side, so it is decoded as an [`i64`],
+    /// which can never be out of bounds.
+    pub number: i64,
     pub date: NaiveDate,
     pub site: Option<Site>,
     #[serde(alias = "cas")]
@@ -140,23 +138,28 @@ pub struct FormCase {
     pub ref_after_sph: f32,
     pub ref_after_cyl_power: Option<f32>,
     pub ref_after_cyl_axis: Option<u32>,
+    pub k1_after_power: Option<f32>,
+    pub k1_after_axis: Option<u32>,
+    pub k2_after_power: Option<f32>,
+    pub k2_after_axis: Option<u32>,
 }
 
 impl FormCase {
+    /// Validate the form values, looking up the IOL model in the given [`Repository`].
     #[cfg(feature = "ssr")]
-    pub async fn into_surgeon_case(self) -> Result<SurgeonCase, AppError> {
-        use crate::db::db;
+    pub async fn into_surgeon_case(
+        self,
+        repository: &dyn Repository,
+    ) -> Result<SurgeonCase, AppError> {
         use crate::model::Acd;
         use crate::model::AfterVa;
         use crate::model::Al;
         use crate::model::Axis;
         use crate::model::BeforeVa;
         use crate::model::Cct;
-        use crate::model::Iol;
         use crate::model::IolSe;
         use crate::model::K;
         use crate::model::Kpower;
-        use crate::model::Ks;
         use crate::model::Lt;
         use crate::model::RawCyl;
         use crate::model::RawSca;
@@ -209,6 +212,10 @@ impl FormCase {
             ref_after_sph,
             ref_after_cyl_power,
             ref_after_cyl_axis,
+            k1_after_power,
+            k1_after_axis,
+            k2_after_power,
+            k2_after_axis,
         } = self;
 
         let date = NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d")?;
@@ -228,6 +235,21 @@ impl FormCase {
             wtw: wtw.and_then(|wtw| Wtw::new((wtw * 100.0) as u32).ok()),
         };
 
+        let ks_after = match (k1_after_power, k1_after_axis, k2_after_power, k2_after_axis) {
+            (Some(k1_power), Some(k1_axis), Some(k2_power), Some(k2_axis)) => Some(Ks::new(
+                K::new(Kpower::new((k1_power * 100.0) as u32)?, Axis::new(k1_axis)?),
+                K::new(Kpower::new((k2_power * 100.0) as u32)?, Axis::new(k2_axis)?),
+            )),
+
+            (None, None, None, None) => None,
+
+            _ => {
+                return Err(AppError::Bounds(
+                    "postop Ks need both K powers and both axes, or none of them".to_string(),
+                ));
+            }
+        };
+
         let target_cyl = match (target_cyl_power, target_cyl_axis) {
             (Some(power), Some(axis)) => Some(TargetCyl::new(
                 TargetCylPower::new((power * 100.0) as u32)?,
@@ -253,22 +275,7 @@ impl FormCase {
 
         // NOTE: For now we are assuming the IOL model is in the DB. To start, offer an option in
         // the datalist that the IOL is not listed, and have a DB option for that.
-        let iol = if let Ok(Some(json)) = db()
-            .await?
-            .query_single_json(
-                format!(
-                    r#"
-select Iol {{
-    model, name, company, focus, toric
-}} filter .model = "{iol_model}";
-                    "#
-                ),
-                &(),
-            )
-            .await
-        {
-            let iol = serde_json::from_str::<Iol>(json.as_ref())?;
-
+        let iol = if let Ok(Some(iol)) = repository.iol(&iol_model).await {
             OpIol {
                 iol,
                 se: IolSe::new((iol_se * 100.0) as i32)?,
@@ -360,6 +367,7 @@ select Iol {{
         let case = Case {
             side,
             biometry,
+            ks_after,
             target,
             main,
             sia,
@@ -380,8 +388,14 @@ select Iol {{
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "ssr")] use crate::error::AppError;
+    #[cfg(feature = "ssr")] use crate::mock::Mock;
     use crate::model::Focus;
+    #[cfg(feature = "ssr")] use crate::model::FormCase;
+    #[cfg(feature = "ssr")] use crate::model::Formula;
     use crate::model::Iol;
+    #[cfg(feature = "ssr")] use crate::model::Side;
+    #[cfg(feature = "ssr")] use crate::repository::MemoryRepository;
 
     #[test]
     fn deserializes_iol() {
@@ -400,4 +414,77 @@ mod tests {
         let result = serde_json::from_str::<Iol>(json.as_str()).unwrap();
         assert_eq!(result, iol);
     }
+
+    #[cfg(feature = "ssr")]
+    #[tokio::test]
+    async fn rejects_partial_postop_ks() {
+        let iol = Iol::mock();
+        let repository = MemoryRepository::with_iols(vec![iol.clone()]);
+
+        let form = FormCase {
+            date: "2025-06-01".to_string(),
+            site: None,
+            side: Side::Right,
+            al: 23.5,
+            k1_power: 43.5,
+            k1_axis: 90,
+            k2_power: 44.0,
+            k2_axis: 0,
+            acd: 3.1,
+            lt: 4.5,
+            cct: None,
+            wtw: None,
+            formula: Formula::Barrett,
+            custom_constant: None,
+            target_se: -0.25,
+            target_cyl_power: None,
+            target_cyl_axis: None,
+            main: 2.2,
+            sia_power: 0.1,
+            sia_axis: 100,
+            iol_model: iol.model,
+            iol_se: 21.5,
+            iol_axis: None,
+            adverse: "none".to_string(),
+            va_best_before_num: 6,
+            va_best_before_den: 12.0,
+            va_raw_before_num: None,
+            va_raw_before_den: None,
+            va_best_after_num: None,
+            va_best_after_den: None,
+            va_raw_after_num: 6,
+            va_raw_after_den: 6.0,
+            ref_before_sph: -0.5,
+            ref_before_cyl_power: None,
+            ref_before_cyl_axis: None,
+            ref_after_sph: -0.25,
+            ref_after_cyl_power: None,
+            ref_after_cyl_axis: None,
+            k1_after_power: None,
+            k1_after_axis: None,
+            k2_after_power: None,
+            k2_after_axis: None,
+        };
+
+        assert_eq!(
+            form.clone()
+                .into_surgeon_case(&repository)
+                .await
+                .unwrap()
+                .case
+                .ks_after,
+            None
+        );
+
+        let partial = FormCase {
+            k1_after_power: Some(43.75),
+            k1_after_axis: Some(90),
+            ..form
+        };
+
+        assert!(matches!(
+            partial.into_surgeon_case(&repository).await,
+            Err(AppError::Bounds(_))
+        ));
+    }
 }
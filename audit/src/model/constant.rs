@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+#[cfg(feature = "ssr")] use gel_protocol::descriptors::TypePos;
+#[cfg(feature = "ssr")] use gel_protocol::errors::DecodeError;
+#[cfg(feature = "ssr")] use gel_protocol::errors::decode_error;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::Decoder;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::DescriptorContext;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::DescriptorMismatch;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::Queryable;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::formula::Outcome;
+use crate::formula::THIN_LENS_FORMULAS;
+use crate::model::Case;
+use crate::model::Formula;
+
+/// A surgeon's personalized lens constant for one IOL model and formula, back-calculated from
+/// their own outcomes so that it zeroes their mean prediction error. The constant is in the
+/// formula's own units (see [`ThinLens`](crate::formula::ThinLens)).
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SurgeonConstant {
+    /// The IOL model.
+    pub iol: String,
+    pub formula: Formula,
+    pub constant: f64,
+    /// The lower bound of the 95% confidence interval for the constant.
+    pub lower: f64,
+    /// The upper bound of the 95% confidence interval for the constant.
+    pub upper: f64,
+    /// The number of cases used to optimize the constant.
+    pub cases: u32,
+}
+
+/// A [`SurgeonConstant`] as stored in the DB, where `cases` is an `int32`.
+#[cfg(feature = "ssr")]
+#[derive(gel_derive::Queryable)]
+struct DbSurgeonConstant {
+    iol: String,
+    formula: Formula,
+    constant: f64,
+    lower: f64,
+    upper: f64,
+    cases: i32,
+}
+
+#[cfg(feature = "ssr")]
+impl Queryable for SurgeonConstant {
+    type Args = <DbSurgeonConstant as Queryable>::Args;
+
+    fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8]) -> Result<Self, DecodeError> {
+        let DbSurgeonConstant {
+            iol,
+            formula,
+            constant,
+            lower,
+            upper,
+            cases,
+        } = DbSurgeonConstant::decode(decoder, args, buf)?;
+
+        Ok(Self {
+            iol,
+            formula,
+            constant,
+            lower,
+            upper,
+            cases: u32::try_from(cases).map_err(decode_error)?,
+        })
+    }
+
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Self::Args, DescriptorMismatch> {
+        DbSurgeonConstant::check_descriptor(ctx, type_pos)
+    }
+}
+
+impl Display for SurgeonConstant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            iol,
+            formula,
+            constant,
+            lower,
+            upper,
+            cases,
+        } = self;
+
+        write!(
+            f,
+            "{iol} ({formula:?}): {constant:.3} (95% CI {lower:.3} to {upper:.3}, n = {cases})"
+        )
+    }
+}
+
+impl SurgeonConstant {
+    /// Optimize the constant for the given IOL model and [`Formula`], from cases that all used
+    /// that model. The confidence interval uses the spread of the constants back-calculated for
+    /// each case individually, and cases for which no plausible constant can be back-calculated
+    /// are left out. Returns [`None`] for formulas without a
+    /// [`ThinLens`](crate::formula::ThinLens) implementation, for fewer than 2 usable cases, or if
+    /// no plausible constant fits the outcomes.
+    pub fn optimize(iol: &str, formula: Formula, cases: &[&Case]) -> Option<Self> {
+        let thin_lens = formula.thin_lens(0.0)?;
+
+        let (outcomes, individual): (Vec<_>, Vec<_>) = cases
+            .iter()
+            .filter_map(|case| {
+                let outcome = Outcome::from(*case);
+                let constant = thin_lens.optimize(std::slice::from_ref(&outcome))?;
+
+                Some((outcome, constant))
+            })
+            .unzip();
+
+        // The sample variance of the individual constants is undefined for fewer than 2 cases.
+        if individual.len() < 2 {
+            return None;
+        }
+
+        let constant = thin_lens.optimize(&outcomes)?;
+
+        let n = individual.len() as f64;
+        let mean = individual.iter().sum::<f64>() / n;
+        let variance = individual
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        let margin = 1.96 * f64::sqrt(variance / n);
+
+        Some(Self {
+            iol: iol.to_string(),
+            formula,
+            constant,
+            lower: constant - margin,
+            upper: constant + margin,
+            cases: outcomes.len() as u32,
+        })
+    }
+
+    /// Optimize constants for each IOL model in the given cases, with each thin-lens formula,
+    /// ordered by IOL model.
+    pub fn from_cases<'a>(cases: impl IntoIterator<Item = &'a Case>) -> Vec<Self> {
+        let by_iol =
+            cases
+                .into_iter()
+                .fold(BTreeMap::<&str, Vec<&Case>>::new(), |mut by_iol, case| {
+                    by_iol
+                        .entry(case.iol.iol.model.as_str())
+                        .or_default()
+                        .push(case);
+
+                    by_iol
+                });
+
+        by_iol
+            .iter()
+            .flat_map(|(iol, cases)| {
+                THIN_LENS_FORMULAS
+                    .into_iter()
+                    .filter_map(|formula| Self::optimize(iol, formula, cases))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounded::Bounded;
+    use crate::formula::ThinLens;
+    use crate::model::Acd;
+    use crate::model::Al;
+    use crate::model::IolSe;
+    use crate::model::RefSph;
+
+    fn case(model: &str, al: u32, sph: i32) -> Case {
+        let mut case = Case::default();
+        case.iol.iol.model = model.to_string();
+        case.iol.se = IolSe::new(2000).unwrap();
+        case.biometry.al = Al::new(al).unwrap();
+        case.biometry.acd = Acd::new(320).unwrap();
+        case.refraction.after.sph = RefSph::new(sph).unwrap();
+
+        case
+    }
+
+    #[test]
+    fn optimizes_per_iol_model() {
+        let cases = [
+            case("SN60WF", 2350, 0),
+            case("SN60WF", 2380, -25),
+            case("SN60WF", 2320, 25),
+            case("ZCB00", 2400, -50),
+        ];
+
+        let constants = SurgeonConstant::from_cases(&cases);
+
+        // The single ZCB00 case is too few to optimize.
+        assert_eq!(constants.len(), THIN_LENS_FORMULAS.len());
+
+        for constant in &constants {
+            assert_eq!((constant.iol.as_str(), constant.cases), ("SN60WF", 3));
+            assert!(constant.lower < constant.constant && constant.constant < constant.upper);
+        }
+
+        let srk_t = constants
+            .iter()
+            .find(|constant| constant.formula == Formula::SrkT)
+            .unwrap();
+        let thin_lens = ThinLens::SrkT {
+            a_constant: srk_t.constant,
+        };
+
+        let mean_error = cases[..3]
+            .iter()
+            .map(|case| {
+                let Outcome { biometry, iol, se } = Outcome::from(case);
+                se - thin_lens.refraction(&biometry, iol).unwrap()
+            })
+            .sum::<f64>()
+            / 3.0;
+
+        assert!(mean_error.abs() < 1e-9);
+        assert!(
+            SurgeonConstant::optimize("ZCB00", Formula::Barrett, &[&cases[0], &cases[1]]).is_none()
+        );
+    }
+
+    #[test]
+    fn leaves_out_cases_that_fail_to_back_calculate() {
+        // No plausible constant explains a 15 D myopic surprise.
+        let outlier = case("SN60WF", 2350, -1500);
+        let cases = [
+            case("SN60WF", 2350, 0),
+            case("SN60WF", 2380, -25),
+            case("SN60WF", 2320, 25),
+        ];
+
+        let with_outlier = [&cases[0], &cases[1], &cases[2], &outlier];
+        let constant = SurgeonConstant::optimize("SN60WF", Formula::SrkT, &with_outlier).unwrap();
+        let without =
+            SurgeonConstant::optimize("SN60WF", Formula::SrkT, &[&cases[0], &cases[1], &cases[2]])
+                .unwrap();
+
+        assert_eq!(constant.cases, 3);
+        assert_eq!(constant, without);
+
+        // Only one usable case is too few for a confidence interval.
+        assert!(
+            SurgeonConstant::optimize("SN60WF", Formula::SrkT, &[&cases[0], &outlier]).is_none()
+        );
+    }
+}
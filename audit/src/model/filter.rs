@@ -0,0 +1,161 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bounded::Bounded;
+use crate::model::Al;
+use crate::model::Case;
+use crate::model::Focus;
+use crate::model::Formula;
+use crate::model::Side;
+
+/// A subgroup of cases for reports, such as "toric cases with the Barrett formula". Each field
+/// that is present narrows the selection, and the filter is applied to both the surgeon's cases and
+/// the cohort's cases, so that like is compared with like.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CaseFilter {
+    /// The IOL model (see [`Iol::model`](crate::model::Iol::model)).
+    pub iol: Option<String>,
+    pub focus: Option<Focus>,
+    /// Whether the IOL is toric.
+    pub toric: Option<bool>,
+    pub formula: Option<Formula>,
+    /// The site name. Sites are only recorded against the surgeon's own cases, so this filter
+    /// doesn't apply to the peer cohort.
+    pub site: Option<String>,
+    pub side: Option<Side>,
+    /// The minimum axial length (inclusive).
+    pub al_min: Option<Al>,
+    /// The maximum axial length (inclusive).
+    pub al_max: Option<Al>,
+}
+
+impl CaseFilter {
+    /// Parse a filter from string parameters (such as a URL query), where `param` returns the value
+    /// for a key. Empty or invalid values are ignored. Axial lengths are in mm.
+    pub fn from_params(param: impl Fn(&str) -> Option<String>) -> Self {
+        let param = |key: &str| param(key).filter(|value| !value.trim().is_empty());
+
+        let variant = |key: &str, variants: &[String]| {
+            param(key).and_then(|value| variants.iter().position(|variant| *variant == value))
+        };
+
+        let al = |key: &str| {
+            param(key)
+                .and_then(|value| value.trim().parse::<f32>().ok())
+                .and_then(|mm| Al::new((mm * 100.0).round() as u32).ok())
+        };
+
+        let focus = variant("focus", &Focus::ALL.map(|focus| focus.to_string()))
+            .map(|index| Focus::ALL[index].clone());
+
+        let formula = variant("formula", &Formula::ALL.map(|formula| formula.to_string()))
+            .map(|index| Formula::ALL[index]);
+
+        let side = variant(
+            "side",
+            &[Side::Right, Side::Left].map(|side| side.to_string()),
+        )
+        .map(|index| [Side::Right, Side::Left][index].clone());
+
+        Self {
+            iol: param("iol"),
+            focus,
+            toric: param("toric").and_then(|toric| toric.parse().ok()),
+            formula,
+            site: param("site"),
+            side,
+            al_min: al("al_min"),
+            al_max: al("al_max"),
+        }
+    }
+
+    /// Return whether the filter includes every case.
+    pub fn is_empty(&self) -> bool { *self == Self::default() }
+
+    /// Return whether a case falls within the filter. The filter is normally applied in the
+    /// database query (see [`query_select_compare`](crate::query::query_select_compare)), but this
+    /// is useful for cases that are already loaded. As in the query, the site is ignored.
+    pub fn matches(&self, case: &Case) -> bool {
+        let iol = &case.iol.iol;
+        let al = case.biometry.al.inner();
+
+        self.iol.as_ref().is_none_or(|model| *model == iol.model)
+            && self.focus.as_ref().is_none_or(|focus| *focus == iol.focus)
+            && self.toric.is_none_or(|toric| toric == iol.toric.is_some())
+            && self
+                .formula
+                .is_none_or(|formula| Some(formula) == case.target.formula)
+            && self.side.as_ref().is_none_or(|side| *side == case.side)
+            && self.al_min.is_none_or(|min| al >= min.inner())
+            && self.al_max.is_none_or(|max| al <= max.inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_params() {
+        let params = [
+            ("iol", "SN6AT3"),
+            ("focus", "Mono"),
+            ("toric", "true"),
+            ("formula", "Barrett"),
+            ("site", ""),
+            ("side", "Sideways"),
+            ("al_min", "22.5"),
+        ];
+
+        let filter = CaseFilter::from_params(|key| {
+            params
+                .iter()
+                .find(|(param, _)| *param == key)
+                .map(|(_, value)| value.to_string())
+        });
+
+        assert_eq!(
+            filter,
+            CaseFilter {
+                iol: Some(String::from("SN6AT3")),
+                focus: Some(Focus::Mono),
+                toric: Some(true),
+                formula: Some(Formula::Barrett),
+                al_min: Some(Al::new(2250).unwrap()),
+                ..Default::default()
+            }
+        );
+
+        assert!(CaseFilter::from_params(|_| None).is_empty());
+    }
+
+    #[test]
+    fn matches_cases() {
+        let mut case = Case::default();
+        case.biometry.al = Al::new(2250).unwrap();
+        case.target.formula = Some(Formula::Kane);
+
+        let filter = CaseFilter {
+            toric: Some(false),
+            formula: Some(Formula::Kane),
+            al_max: Some(Al::new(2300).unwrap()),
+            ..Default::default()
+        };
+
+        assert!(filter.matches(&case));
+        assert!(
+            !CaseFilter {
+                side: Some(Side::Left),
+                ..filter.clone()
+            }
+            .matches(&case)
+        );
+        assert!(
+            !CaseFilter {
+                al_min: Some(Al::new(2300).unwrap()),
+                ..filter
+            }
+            .matches(&case)
+        );
+    }
+}
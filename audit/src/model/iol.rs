@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use audit_macro::RangeBounded;
 use serde::Deserialize;
 use serde::Serialize;
@@ -7,6 +9,7 @@ use crate::model::Axis;
 
 /// The class of [`Iol`] (monofocal, EDOF, multifocal).
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub enum Focus {
     #[default]
     Mono,
@@ -14,6 +17,30 @@ pub enum Focus {
     Multi,
 }
 
+// Implementing Display is necessary for enums to impl Into<gel_protocol::Value>
+impl Display for Focus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mono => write!(f, "Mono"),
+            Self::Edof => write!(f, "Edof"),
+            Self::Multi => write!(f, "Multi"),
+        }
+    }
+}
+
+impl Focus {
+    pub const ALL: [Self; 3] = [Self::Mono, Self::Edof, Self::Multi];
+
+    /// A human-readable name, for labels in the UI.
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Mono => "Monofocal",
+            Self::Edof => "EDOF",
+            Self::Multi => "Multifocal",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, RangeBounded, Serialize)]
 pub struct IolSe(
     #[bounded(range = -2000..=6000, rem = 25, default = 2000, mock_range = -200..=3000)] i32,
@@ -26,6 +53,7 @@ pub struct ToricPower(
 
 /// A specific model of IOL.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Iol {
     pub model: String,
     pub name: Option<String>,
@@ -37,6 +65,7 @@ pub struct Iol {
 /// The IOL for a particular [`Case`](crate::case::Case). Includes both the model and the specific
 /// power chosen for this patient.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct OpIol {
     pub iol: Iol,
     pub se: IolSe,
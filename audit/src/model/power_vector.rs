@@ -0,0 +1,389 @@
+use std::f64::consts::PI;
+use std::fmt::Display;
+use std::iter::Sum;
+use std::ops::Add;
+use std::ops::Div;
+use std::ops::Sub;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bounded::Bounded;
+use crate::error::AppError;
+use crate::model::Axis;
+use crate::model::Cyl;
+use crate::model::CylPower;
+use crate::model::RawCyl;
+use crate::model::RawSca;
+use crate::model::RefCyl;
+use crate::model::RefSph;
+use crate::model::Refraction;
+use crate::model::Sca;
+use crate::model::Target;
+use crate::model::TargetCyl;
+use crate::model::TargetCylPower;
+use crate::model::TargetSe;
+
+/// A refraction in Thibos power-vector form, in diopters (not centidiopters, as the components are
+/// rarely multiples of 0.25 D). Unlike sphere, cylinder and axis, power vectors can be added,
+/// subtracted and averaged component-wise, so they are the accepted way to summarize refractions.
+///
+/// See Thibos, Wheeler and Horner, "Power vectors: an application of Fourier analysis to the
+/// description and statistical analysis of refractive error" (1997).
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PowerVector {
+    /// The spherical equivalent (S + C/2).
+    pub m: f64,
+    /// The Jackson cross-cylinder at 0° and 90°.
+    pub j0: f64,
+    /// The Jackson cross-cylinder at 45° and 135°.
+    pub j45: f64,
+}
+
+impl PowerVector {
+    pub fn new(m: f64, j0: f64, j45: f64) -> Self { Self { m, j0, j45 } }
+
+    /// Create a [`PowerVector`] from a spherical equivalent, and a cylinder power and axis, in
+    /// centidiopters. The cylinder can be in either plus or minus form.
+    fn from_se_cyl(se: f64, cyl: Option<(f64, Axis)>) -> Self {
+        let (j0, j45) = cyl.map_or((0.0, 0.0), |(power, axis)| {
+            let (sin, cos) = (2.0 * f64::from(axis.inner()) * PI / 180.0).sin_cos();
+            let half = power / 200.0;
+
+            (-half * cos, -half * sin)
+        });
+
+        Self {
+            m: se / 100.0,
+            j0,
+            j45,
+        }
+    }
+
+    /// Create a [`PowerVector`] from any [`Sca`] whose sphere is a true sphere (rather than a
+    /// spherical equivalent, as in [`Target`]).
+    fn from_sca<T, U>(sca: &U) -> Self
+    where
+        T: CylPower + Into<f64>,
+        U: Sca<T>,
+    {
+        let cyl = sca.cyl().map(|cyl| (cyl.power().into(), cyl.axis()));
+        let half_cyl = cyl.map_or(0.0, |(power, _)| power / 2.0);
+
+        Self::from_se_cyl(f64::from(sca.sph()) + half_cyl, cyl)
+    }
+
+    /// The magnitude of the astigmatic component, which is half the cylinder power.
+    pub fn j(&self) -> f64 { self.j0.hypot(self.j45) }
+
+    /// The blurring strength: the overall length of the power vector, combining spherical and
+    /// astigmatic error.
+    pub fn blur(&self) -> f64 {
+        f64::sqrt((self.m * self.m) + (self.j0 * self.j0) + (self.j45 * self.j45))
+    }
+
+    /// Convert to sphere, cylinder and axis in centidiopters, with the cylinder in minus form (or
+    /// plus form, if `plus_cyl` is true). Values are rounded to the nearest `step` centidiopters,
+    /// and the axis to the nearest degree. The cylinder is [`None`] if it rounds to zero.
+    fn sca(&self, plus_cyl: bool, step: f64) -> (i32, Option<(i32, Axis)>) {
+        let round = |value: f64| ((value * 100.0 / step).round() * step) as i32;
+
+        let cyl = if plus_cyl {
+            2.0 * self.j()
+        } else {
+            -2.0 * self.j()
+        };
+        let power = round(cyl);
+
+        // With J0 = -(C/2)cos(2α), the axis of a minus cylinder lies along the power vector, and
+        // the axis of a plus cylinder is perpendicular to it.
+        let direction = if plus_cyl {
+            f64::atan2(-self.j45, -self.j0)
+        } else {
+            f64::atan2(self.j45, self.j0)
+        };
+
+        let axis = (direction * 90.0 / PI).round().rem_euclid(180.0) as u32;
+        let axis = Axis::new(axis).expect("axis should be reduced to the range 0..180");
+
+        let sph = round(self.m - (f64::from(power) / 200.0));
+        let cyl = (power != 0).then_some((power, axis));
+
+        (sph, cyl)
+    }
+}
+
+impl Display for PowerVector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { m, j0, j45 } = self;
+
+        write!(f, "M {m:+.2} D, J0 {j0:+.2} D, J45 {j45:+.2} D")
+    }
+}
+
+impl Add for PowerVector {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            m: self.m + rhs.m,
+            j0: self.j0 + rhs.j0,
+            j45: self.j45 + rhs.j45,
+        }
+    }
+}
+
+impl Sub for PowerVector {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            m: self.m - rhs.m,
+            j0: self.j0 - rhs.j0,
+            j45: self.j45 - rhs.j45,
+        }
+    }
+}
+
+impl Div<f64> for PowerVector {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        Self {
+            m: self.m / rhs,
+            j0: self.j0 / rhs,
+            j45: self.j45 / rhs,
+        }
+    }
+}
+
+impl Sum for PowerVector {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self { iter.fold(Self::default(), Add::add) }
+}
+
+impl From<&Refraction> for PowerVector {
+    fn from(refraction: &Refraction) -> Self { Self::from_sca(refraction) }
+}
+
+impl From<&RawSca> for PowerVector {
+    fn from(sca: &RawSca) -> Self { Self::from_sca(sca) }
+}
+
+impl From<&Target> for PowerVector {
+    /// The [`Target`] sphere is already a spherical equivalent, so the cylinder only contributes
+    /// to J0 and J45.
+    fn from(target: &Target) -> Self {
+        Self::from_se_cyl(
+            f64::from(target.se.inner()),
+            target
+                .cyl
+                .map(|TargetCyl { power, axis }| (f64::from(power.inner()), axis)),
+        )
+    }
+}
+
+impl From<PowerVector> for RawSca {
+    /// Convert to minus cylinder form, rounded to the nearest centidiopter.
+    fn from(vector: PowerVector) -> Self {
+        let (sph, cyl) = vector.sca(false, 1.0);
+
+        RawSca::new(sph, cyl.map(|(power, axis)| RawCyl::new(power, axis)))
+    }
+}
+
+impl TryFrom<PowerVector> for Refraction {
+    type Error = AppError;
+
+    /// Convert to minus cylinder form, rounded to the nearest 0.25 D.
+    fn try_from(vector: PowerVector) -> Result<Self, Self::Error> {
+        let (sph, cyl) = vector.sca(false, 25.0);
+
+        Ok(Refraction {
+            sph: RefSph::new(sph)?,
+            cyl: cyl
+                .map(|(power, axis)| RefCyl::new(power, axis))
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<PowerVector> for Target {
+    type Error = AppError;
+
+    /// Convert to a [`Target`] without a formula, with the cylinder in plus form (as
+    /// [`TargetCylPower`] is nonnegative), rounded to the nearest centidiopter.
+    fn try_from(vector: PowerVector) -> Result<Self, Self::Error> {
+        let (_, cyl) = vector.sca(true, 1.0);
+        let se = (vector.m * 100.0).round() as i32;
+
+        Ok(Target {
+            formula: None,
+            custom_constant: false,
+            se: TargetSe::new(se)?,
+            cyl: cyl
+                .map(|(power, axis)| {
+                    Ok::<_, AppError>(TargetCyl::new(TargetCylPower::new(power as u32)?, axis))
+                })
+                .transpose()?,
+        })
+    }
+}
+
+/// A set of [`PowerVector`]s, with summary statistics.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PowerVectorData {
+    pub vectors: Vec<PowerVector>,
+}
+
+impl FromIterator<PowerVector> for PowerVectorData {
+    fn from_iter<T: IntoIterator<Item = PowerVector>>(iter: T) -> Self {
+        Self {
+            vectors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl PowerVectorData {
+    /// The component-wise mean, or [`None`] if there are no vectors.
+    pub fn mean(&self) -> Option<PowerVector> {
+        if self.vectors.is_empty() {
+            return None;
+        }
+
+        Some(self.vectors.iter().copied().sum::<PowerVector>() / self.vectors.len() as f64)
+    }
+
+    /// The component-wise sample standard deviation, or [`None`] for fewer than 2 vectors.
+    pub fn std_dev(&self) -> Option<PowerVector> {
+        if self.vectors.len() < 2 {
+            return None;
+        }
+
+        let mean = self.mean()?;
+        let squares = self
+            .vectors
+            .iter()
+            .map(|vector| {
+                let PowerVector { m, j0, j45 } = *vector - mean;
+
+                PowerVector::new(m * m, j0 * j0, j45 * j45)
+            })
+            .sum::<PowerVector>()
+            / (self.vectors.len() - 1) as f64;
+
+        Some(PowerVector::new(
+            squares.m.sqrt(),
+            squares.j0.sqrt(),
+            squares.j45.sqrt(),
+        ))
+    }
+
+    /// The mean blurring strength, or [`None`] if there are no vectors. Note that this is not the
+    /// blurring strength of the mean vector, which is smaller when errors cancel out.
+    pub fn mean_blur(&self) -> Option<f64> {
+        if self.vectors.is_empty() {
+            return None;
+        }
+
+        Some(self.vectors.iter().map(PowerVector::blur).sum::<f64>() / self.vectors.len() as f64)
+    }
+}
+
+impl Display for PowerVectorData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.mean(), self.std_dev(), self.mean_blur()) {
+            (Some(mean), Some(std_dev), Some(blur)) => write!(
+                f,
+                "mean {mean} (SD {:.2}, {:.2}, {:.2}), mean blur {blur:.2} D, n = {}",
+                std_dev.m,
+                std_dev.j0,
+                std_dev.j45,
+                self.vectors.len()
+            ),
+            (Some(mean), _, Some(blur)) => write!(f, "mean {mean}, mean blur {blur:.2} D, n = 1"),
+            _ => write!(f, "no cases"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refraction(sph: i32, cyl: i32, axis: u32) -> Refraction {
+        Refraction {
+            sph: RefSph::new(sph).unwrap(),
+            cyl: Some(RefCyl::new(cyl, Axis::new(axis).unwrap()).unwrap()),
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 1e-9 }
+
+    #[test]
+    fn converts_refractions() {
+        // -1.00 / -2.00 x 180: M = -2.00, J0 = +1.00, J45 = 0.
+        let vector = PowerVector::from(&refraction(-100, -200, 0));
+        assert!(close(vector.m, -2.0) && close(vector.j0, 1.0) && close(vector.j45, 0.0));
+
+        // The same lens in plus cylinder form has the same power vector.
+        let plus = PowerVector::from(&refraction(-300, 200, 90));
+        assert!(close(plus.m, vector.m) && close(plus.j0, vector.j0) && close(plus.j45, 0.0));
+
+        // +0.50 / -1.00 x 45: J45 = +0.50.
+        let oblique = PowerVector::from(&refraction(50, -100, 45));
+        assert!(close(oblique.m, 0.0) && close(oblique.j0, 0.0) && close(oblique.j45, 0.5));
+        assert!(close(oblique.blur(), 0.5));
+    }
+
+    #[test]
+    fn round_trips_refractions() {
+        for (sph, cyl, axis) in [
+            (-100, -200, 0),
+            (250, -75, 95),
+            (-425, -300, 135),
+            (0, -25, 10),
+        ] {
+            let original = refraction(sph, cyl, axis);
+            let round_trip = Refraction::try_from(PowerVector::from(&original)).unwrap();
+
+            assert_eq!(round_trip, original);
+        }
+
+        let target = Target {
+            se: TargetSe::new(-50).unwrap(),
+            cyl: Some(TargetCyl::new(
+                TargetCylPower::new(75).unwrap(),
+                Axis::new(170).unwrap(),
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Target::try_from(PowerVector::from(&target)).unwrap(),
+            target
+        );
+    }
+
+    #[test]
+    fn averages_crossed_cylinders() {
+        // Equal cylinders at perpendicular axes cancel out, leaving only the spherical equivalent,
+        // which naive averaging of sphere, cylinder and axis gets wrong.
+        let data = [refraction(0, -100, 0), refraction(0, -100, 90)]
+            .iter()
+            .map(PowerVector::from)
+            .collect::<PowerVectorData>();
+
+        let mean = data.mean().unwrap();
+        assert!(close(mean.m, -0.5) && close(mean.j(), 0.0));
+        assert!(close(data.mean_blur().unwrap(), f64::sqrt(0.5)));
+        assert!(close(data.std_dev().unwrap().j0, f64::sqrt(0.5)));
+        assert_eq!(
+            Refraction::try_from(mean).unwrap(),
+            Refraction {
+                sph: RefSph::new(-50).unwrap(),
+                cyl: None,
+            }
+        );
+        assert!(PowerVectorData::default().mean().is_none());
+    }
+}
@@ -15,6 +15,7 @@ pub struct RefCylPower(#[bounded(range = -1000..=1000, rem = 25, mock_range = -3
 pub struct RefSph(#[bounded(range = -2000..=2000, rem = 25, mock_range = -800..=400)] i32);
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct RefCyl {
     pub power: RefCylPower,
     pub axis: Axis,
@@ -37,6 +38,7 @@ impl RefCyl {
 
 /// A patient's subjective refraction.
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Refraction {
     pub sph: RefSph,
     pub cyl: Option<RefCyl>,
@@ -50,6 +52,7 @@ impl Sca<i32> for Refraction {
 
 /// The preoperative and postoperative refractions for a given [`Case`](crate::case::Case).
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct OpRefraction {
     pub before: Refraction,
     pub after: Refraction,
@@ -14,6 +14,7 @@ pub struct SiaPower(#[bounded(range = 0..=200, default = 10, mock_range = 5..=40
 /// decided that the cognitive overhead of using both terms in the code is higher than the cognitive
 /// overhead of knowing when `axis` actually refers to a meridian.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Sia {
     pub power: SiaPower,
     pub axis: Axis,
@@ -1,20 +1,28 @@
 use std::fmt::Display;
 
 use chrono::DateTime;
+use chrono::Datelike;
+use chrono::NaiveDate;
 use chrono::Utc;
 use garde::Validate;
-use leptos::prelude::ServerFnError;
+#[cfg(feature = "ssr")] use gel_protocol::descriptors::TypePos;
+#[cfg(feature = "ssr")] use gel_protocol::errors::DecodeError;
+#[cfg(feature = "ssr")] use gel_protocol::errors::decode_error;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::Decoder;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::DescriptorContext;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::DescriptorMismatch;
+#[cfg(feature = "ssr")] use gel_protocol::queryable::Queryable;
 use leptos::prelude::server;
-#[cfg(feature = "ssr")] use leptos::prelude::use_context;
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::error::AppError;
+use crate::model::DateRange;
 use crate::model::Formula;
 use crate::model::Iol;
 use crate::model::Main;
 use crate::model::Sia;
-#[cfg(feature = "ssr")] use crate::state::AppState;
+#[cfg(feature = "ssr")] use crate::repository::repository;
 
 /// A [`garde`]-checked valid email [`String`].
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, Validate)]
@@ -47,8 +55,28 @@ impl Email {
     pub fn into_inner(self) -> String { self.0 }
 }
 
+// Decoding validates the email in the same way as `new`.
+#[cfg(feature = "ssr")]
+impl Queryable for Email {
+    type Args = <String as Queryable>::Args;
+
+    fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8]) -> Result<Self, DecodeError> {
+        let email = String::decode(decoder, args, buf)?;
+
+        Email::new(&email).map_err(decode_error)
+    }
+
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Self::Args, DescriptorMismatch> {
+        String::check_descriptor(ctx, type_pos)
+    }
+}
+
 /// A surgeon's default [`Sia`] for right and left eyes
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct SurgeonSia {
     pub right: Sia,
     pub left: Sia,
@@ -71,12 +99,14 @@ pub struct FormSurgeon {
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Site {
     pub name: String,
 }
 
 /// A unique surgeon
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Surgeon {
     /// A unique, valid email.
     pub email: Email,
@@ -85,9 +115,13 @@ pub struct Surgeon {
     pub preferred_name: Option<String>,
     pub defaults: Option<SurgeonDefaults>,
     pub sia: SurgeonSia,
+    /// How often the surgeon receives an outcome report by email, or [`None`] if they have opted
+    /// out.
+    pub report_frequency: Option<ReportFrequency>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct SurgeonDefaults {
     pub site: Option<Site>,
     pub iol: Option<Iol>,
@@ -96,31 +130,128 @@ pub struct SurgeonDefaults {
     pub main: Main,
 }
 
-/// Return the current [`Surgeon`] from global server context by cloning its value. In practice,
-/// this function should rarely be needed, as accessing a protected route will call
-/// [`get_authorized_surgeon`](crate::auth::get_authorized_surgeon), which is then provided as
-/// client-side context.
-#[server]
-pub async fn get_current_surgeon() -> Result<Option<Surgeon>, AppError> {
-    let surgeon = use_context::<AppState>()
-        .ok_or_else(|| AppError::State("AppState not present in context".to_string()))?
-        .surgeon
-        .get_cloned()?;
+/// How often a surgeon receives an outcome report by email. Each report covers the most recent
+/// complete period (calendar month or calendar year).
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
+pub enum ReportFrequency {
+    Monthly,
+    Annual,
+}
 
-    Ok(surgeon)
+// Implementing Display is necessary for enums to impl Into<gel_protocol::Value>
+impl Display for ReportFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Monthly => write!(f, "Monthly"),
+            Self::Annual => write!(f, "Annual"),
+        }
+    }
 }
 
-/// Set the value of the current [`Surgeon`] in global server context. using `Option<Surgeon>` as
-/// the input parameter allows clearing the value by setting [`None`].
-#[server]
-pub async fn set_current_surgeon(surgeon: Option<Surgeon>) -> Result<(), ServerFnError> {
-    use_context::<AppState>()
-        .ok_or_else(|| AppError::State("AppState not present in context".to_string()))?
-        .surgeon
-        .set(surgeon)?;
+impl ReportFrequency {
+    pub const ALL: [Self; 2] = [Self::Monthly, Self::Annual];
+
+    /// A human-readable name, for labels in the UI.
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Monthly => "Monthly",
+            Self::Annual => "Annual",
+        }
+    }
+
+    /// Parse a frequency from its [`Display`] representation. Any other value (such as "Off")
+    /// opts out of reports.
+    pub fn from_param(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|frequency| frequency.to_string() == value)
+    }
+
+    /// Return the first day of the period containing `date`.
+    fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Monthly => date.with_day(1),
+            Self::Annual => date.with_ordinal(1),
+        }
+        .expect("expected the first day of the month or year to be valid")
+    }
 
-    Ok(())
+    /// Return the most recent complete period before `today`, which is the period that a report
+    /// sent today should cover.
+    pub fn period(&self, today: NaiveDate) -> DateRange {
+        let end = self.period_start(today).pred_opt().unwrap_or(today);
+        let start = self.period_start(end);
+
+        DateRange::new(start, end).expect("expected the period to start before it ends")
+    }
+
+    /// Return whether a report is due, given when the last one was sent. A report is due once per
+    /// period, so one sent at any time during the current period covers it.
+    pub fn is_due(&self, last_sent: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        let current = self.period_start(now.date_naive());
+
+        last_sent.is_none_or(|sent| sent.date_naive() < current)
+    }
+}
+
+/// Return the current [`Surgeon`], identified by the auth token of the current request. In
+/// practice, this function should rarely be needed, as accessing a protected route will call
+/// [`get_authorized_surgeon`](crate::routes::get_authorized_surgeon), which is then provided as
+/// client-side context.
+#[server]
+pub async fn get_current_surgeon() -> Result<Option<Surgeon>, AppError> {
+    repository()?.current_surgeon().await
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn reports_on_the_previous_period() {
+        let monthly = ReportFrequency::Monthly.period(date(2026, 3, 14));
+        assert_eq!(
+            (monthly.start(), monthly.end()),
+            (date(2026, 2, 1), date(2026, 2, 28))
+        );
+
+        let annual = ReportFrequency::Annual.period(date(2026, 1, 1));
+        assert_eq!(
+            (annual.start(), annual.end()),
+            (date(2025, 1, 1), date(2025, 12, 31))
+        );
+    }
+
+    #[test]
+    fn reports_are_due_once_per_period() {
+        let at = |year, month, day| {
+            date(year, month, day)
+                .and_hms_opt(9, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+
+        let monthly = ReportFrequency::Monthly;
+        assert!(monthly.is_due(None, at(2026, 3, 14)));
+        assert!(monthly.is_due(Some(at(2026, 2, 1)), at(2026, 3, 1)));
+        assert!(!monthly.is_due(Some(at(2026, 3, 1)), at(2026, 3, 31)));
+
+        let annual = ReportFrequency::Annual;
+        assert!(!annual.is_due(Some(at(2026, 1, 1)), at(2026, 12, 31)));
+        assert!(annual.is_due(Some(at(2025, 12, 31)), at(2026, 1, 1)));
+    }
+
+    #[test]
+    fn parses_frequency_params() {
+        assert_eq!(
+            ReportFrequency::from_param("Monthly"),
+            Some(ReportFrequency::Monthly)
+        );
+        assert_eq!(ReportFrequency::from_param("Off"), None);
+    }
+}
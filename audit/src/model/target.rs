@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use audit_macro::RangeBounded;
 use serde::Deserialize;
 use serde::Serialize;
@@ -9,6 +11,7 @@ use crate::model::Sca;
 
 /// A formula for calculating IOL power from biometry.
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub enum Formula {
     AscrsKrs,
     Barrett,
@@ -28,7 +31,51 @@ pub enum Formula {
     Other,
 }
 
+// Implementing Display is necessary for enums to impl Into<gel_protocol::Value>
+impl Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{self:?}") }
+}
+
 impl Formula {
+    pub const ALL: [Self; 15] = [
+        Self::AscrsKrs,
+        Self::Barrett,
+        Self::BarrettTrueK,
+        Self::Evo,
+        Self::Haigis,
+        Self::HaigisL,
+        Self::HillRbf,
+        Self::HofferQ,
+        Self::Holladay1,
+        Self::Holladay2,
+        Self::Kane,
+        Self::Okulix,
+        Self::Olsen,
+        Self::SrkT,
+        Self::Other,
+    ];
+
+    /// A human-readable name, for labels in the UI.
+    pub fn label(&self) -> &str {
+        match self {
+            Self::AscrsKrs => "ASCRS KRS",
+            Self::Barrett => "Barrett Universal II",
+            Self::BarrettTrueK => "Barrett True-K",
+            Self::Evo => "EVO",
+            Self::Haigis => "Haigis",
+            Self::HaigisL => "Haigis-L",
+            Self::HillRbf => "Hill-RBF",
+            Self::HofferQ => "Hoffer Q",
+            Self::Holladay1 => "Holladay 1",
+            Self::Holladay2 => "Holladay 2",
+            Self::Kane => "Kane",
+            Self::Okulix => "Okulix",
+            Self::Olsen => "Olsen",
+            Self::SrkT => "SRK/T",
+            Self::Other => "Other",
+        }
+    }
+
     pub fn is_thick(&self) -> bool {
         matches!(
             self,
@@ -54,6 +101,7 @@ pub struct TargetCylPower(#[bounded(range = 0..=600, mock_range = 0..=75)] u32);
 pub struct TargetSe(#[bounded(range = -600..=200, mock_range = -200..=20)] i32);
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct TargetCyl {
     pub power: TargetCylPower,
     pub axis: Axis,
@@ -71,6 +119,7 @@ impl TargetCyl {
 
 /// The residual postop refraction for a case, assuming the provided formula and IOL constant.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Target {
     pub formula: Option<Formula>,
     pub custom_constant: bool,
@@ -3,6 +3,7 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::bounded::Bounded;
+use crate::error::AppError;
 
 // Choosing not to use NonZeroU32 for VaDen, because it has a slightly different interface than all
 // our other bounded types.
@@ -23,6 +24,7 @@ pub struct VaNum(#[bounded(range = 0..=2000, default = 600, mock_range = 600..=6
 /// [`Iol`](crate::iol::Iol), [`Refraction`](crate::refraction::Refraction), and
 /// [`Target`](crate::target::Target).
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct Va {
     pub num: VaNum,
     pub den: VaDen,
@@ -35,11 +37,68 @@ impl Va {
     pub fn num(&self) -> u32 { self.num.inner() }
 
     pub fn den(&self) -> u32 { self.den.inner() }
+
+    /// Create a [`Va`] from a logMAR value, expressed as a 6 m Snellen fraction. Returns an error
+    /// if the value is not finite, or if the resulting denominator is out of bounds.
+    pub fn from_logmar(logmar: f64) -> Result<Self, AppError> {
+        if !logmar.is_finite() {
+            return Err(AppError::Bounds(format!(
+                "invalid logMAR value: {logmar:?}"
+            )));
+        }
+
+        let num = VaNum::default();
+        let den = (f64::from(num.inner()) * 10_f64.powf(logmar)).round();
+
+        // `as` saturates, so an out-of-range float will fail the bounds check on `VaDen` rather
+        // than wrapping.
+        Ok(Self::new(num, VaDen::new(den as u32)?))
+    }
+
+    /// Create a [`Va`] from a decimal acuity (e.g. 0.5 for 6/12), expressed as a 6 m Snellen
+    /// fraction.
+    pub fn from_decimal(decimal: f64) -> Result<Self, AppError> {
+        if decimal <= 0.0 {
+            return Err(AppError::Bounds(format!(
+                "invalid decimal acuity: {decimal:?}"
+            )));
+        }
+
+        Self::from_logmar(-decimal.log10())
+    }
+
+    /// Create a [`Va`] from an ETDRS letter score, expressed as a 6 m Snellen fraction.
+    pub fn from_letters(letters: u32) -> Result<Self, AppError> {
+        Self::from_logmar((85.0 - f64::from(letters)) / 50.0)
+    }
+
+    /// Return the logMAR equivalent of the acuity, or [`None`] if the numerator is zero (the
+    /// minimum angle of resolution is undefined).
+    pub fn logmar(&self) -> Option<f64> {
+        (self.num() > 0).then(|| f64::log10(f64::from(self.den()) / f64::from(self.num())))
+    }
+
+    /// Return the decimal equivalent of the acuity (e.g. 0.5 for 6/12).
+    pub fn decimal(&self) -> f64 { f64::from(self.num()) / f64::from(self.den()) }
+
+    /// Return the ETDRS letter score equivalent of the acuity, using the usual convention that
+    /// 85 letters is equivalent to logMAR 0.0, and each letter is worth 0.02 logMAR. Scores are
+    /// rounded to the nearest letter and floored at zero. Returns [`None`] if the logMAR value is
+    /// undefined.
+    pub fn letters(&self) -> Option<u32> {
+        self.logmar()
+            .map(|logmar| (85.0 - 50.0 * logmar).round().max(0.0) as u32)
+    }
+
+    /// Return the nearest logMAR line to the acuity, in tenths of logMAR (e.g. 0 for 6/6, 3 for
+    /// 6/12). Returns [`None`] if the logMAR value is undefined.
+    pub fn line(&self) -> Option<i32> { self.logmar().map(|logmar| (logmar * 10.0).round() as i32) }
 }
 
 /// A collection of visual acuities from before surgery. We use separate structs for [`BeforeVa`]
 /// and [`AfterVa`], because we enforce different mandatory fields for the two situations.
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct BeforeVa {
     pub best: Va,
     pub raw: Option<Va>,
@@ -48,6 +107,7 @@ pub struct BeforeVa {
 /// A collection of visual acuities from after surgery. We use separate structs for [`BeforeVa`]
 /// and [`AfterVa`], because we enforce different mandatory fields for the two situations.
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct AfterVa {
     pub best: Option<Va>,
     pub raw: Va,
@@ -55,11 +115,39 @@ pub struct AfterVa {
 
 /// The visual acuity sets from before and after a particular [`Case`](crate::case::Case).
 #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "ssr", derive(gel_derive::Queryable))]
 pub struct OpVa {
     pub before: BeforeVa,
     pub after: AfterVa,
 }
 
+impl OpVa {
+    /// Return the number of logMAR lines gained from the preoperative best-corrected acuity to the
+    /// postoperative best-corrected acuity. A negative value represents lines lost. Returns
+    /// [`None`] if there is no postoperative best-corrected acuity, or if either logMAR value is
+    /// undefined.
+    pub fn lines_gained(&self) -> Option<i32> {
+        let before = self.before.best.logmar()?;
+        let after = self.after.best?.logmar()?;
+
+        Some(((before - after) * 10.0).round() as i32)
+    }
+}
+
+/// Calculate the mean logMAR value of a set of acuities. Acuities are averaged on the logMAR
+/// scale rather than as Snellen fractions, because the Snellen scale is not linear. Acuities with
+/// an undefined logMAR value are omitted, and [`None`] is returned if no values remain.
+pub fn mean_logmar<'a>(vas: impl IntoIterator<Item = &'a Va>) -> Option<f64> {
+    let (sum, count) = vas
+        .into_iter()
+        .filter_map(Va::logmar)
+        .fold((0.0, 0_u32), |(sum, count), logmar| {
+            (sum + logmar, count + 1)
+        });
+
+    (count > 0).then(|| sum / f64::from(count))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +167,109 @@ mod tests {
     fn zero_va_denominator_returns_err() {
         assert!(VaDen::new(0).is_err());
     }
+
+    fn va(num: u32, den: u32) -> Va { Va::new(VaNum::new(num).unwrap(), VaDen::new(den).unwrap()) }
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 0.005 }
+
+    // Equivalents from the standard Snellen/decimal/logMAR/ETDRS conversion table (e.g. Holladay,
+    // J Refract Surg 1997; 13:388-391).
+    const TABLE: [(u32, u32, f64, f64, u32); 7] = [
+        (600, 300, 2.0, -0.30, 100),
+        (600, 600, 1.0, 0.0, 85),
+        (600, 750, 0.8, 0.10, 80),
+        (600, 1200, 0.5, 0.30, 70),
+        (600, 2400, 0.25, 0.60, 55),
+        (600, 6000, 0.1, 1.0, 35),
+        (2000, 20000, 0.1, 1.0, 35),
+    ];
+
+    #[test]
+    fn converts_va_to_table_equivalents() {
+        for (num, den, decimal, logmar, letters) in TABLE {
+            let va = va(num, den);
+
+            assert!(close(va.decimal(), decimal), "{num}/{den} decimal");
+            assert!(close(va.logmar().unwrap(), logmar), "{num}/{den} logMAR");
+            assert_eq!(va.letters(), Some(letters), "{num}/{den} letters");
+        }
+    }
+
+    #[test]
+    fn converts_table_equivalents_to_va() {
+        for (num, den, decimal, logmar, letters) in TABLE {
+            let expected = va(num, den).logmar().unwrap();
+
+            assert!(close(
+                Va::from_decimal(decimal).unwrap().logmar().unwrap(),
+                expected
+            ));
+            assert!(close(
+                Va::from_logmar(logmar).unwrap().logmar().unwrap(),
+                expected
+            ));
+            assert!(close(
+                Va::from_letters(letters).unwrap().logmar().unwrap(),
+                expected
+            ));
+        }
+    }
+
+    #[test]
+    fn zero_va_numerator_has_no_logmar() {
+        let va = va(0, 600);
+
+        assert_eq!(va.logmar(), None);
+        assert_eq!(va.letters(), None);
+        assert_eq!(va.line(), None);
+    }
+
+    #[test]
+    fn invalid_conversions_return_err() {
+        assert!(Va::from_decimal(0.0).is_err());
+        assert!(Va::from_logmar(f64::NAN).is_err());
+        assert!(Va::from_logmar(-10.0).is_err());
+    }
+
+    #[test]
+    fn rounds_to_nearest_line() {
+        // 6/9 is logMAR 0.18, which is nearest to the 6/9.5 line.
+        assert_eq!(va(600, 900).line(), Some(2));
+        assert_eq!(va(600, 1200).line(), Some(3));
+    }
+
+    #[test]
+    fn counts_lines_gained() {
+        let op_va = OpVa {
+            before: BeforeVa {
+                best: va(600, 2400),
+                raw: None,
+            },
+            after: AfterVa {
+                best: Some(va(600, 600)),
+                raw: va(600, 750),
+            },
+        };
+
+        assert_eq!(op_va.lines_gained(), Some(6));
+        assert_eq!(
+            OpVa {
+                after: AfterVa {
+                    best: None,
+                    ..op_va.after
+                },
+                ..op_va
+            }
+            .lines_gained(),
+            None
+        );
+    }
+
+    #[test]
+    fn calculates_mean_logmar() {
+        let vas = [va(600, 600), va(600, 1200), va(0, 600)];
+
+        assert!(close(mean_logmar(&vas).unwrap(), 0.15));
+        assert_eq!(mean_logmar(&[]), None);
+    }
 }
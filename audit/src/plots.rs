@@ -9,27 +9,56 @@
 //
 // This needs to be created as a method on RefSph and RefCylPower, or a trait they share.
 
+mod acuity;
+mod adverse;
+mod alpins;
+mod bootstrap;
 mod cartesian;
 mod case;
+mod constant;
 mod polar;
+mod power_vector;
+mod prediction;
+mod sia;
+mod significance;
+mod svg;
+mod trend;
 mod vertex;
 
 use std::f64::consts::PI;
 
+pub use acuity::*;
+pub use adverse::*;
+pub use alpins::*;
 use audit_macro::RangeBounded;
+pub use bootstrap::*;
 pub use cartesian::*;
 pub use case::*;
+pub use constant::*;
 use plotly::Plot;
 pub use polar::*;
+pub use power_vector::*;
+pub use prediction::*;
 use serde::Deserialize;
 use serde::Serialize;
+pub use sia::*;
+pub use significance::*;
+pub use trend::*;
 pub use vertex::*;
 
 use crate::bounded::Bounded;
+use crate::theme::PlotTheme;
 
-/// Generate a Plotly [`Plot`] from the given type.
+/// Generate a Plotly [`Plot`] from the given type, with the colours of the given [`PlotTheme`].
 pub trait AsPlot {
-    fn plot(&self) -> Plot;
+    fn plot(&self, theme: &PlotTheme) -> Plot;
+}
+
+/// Render the given type as a standalone SVG document, with the colours of the given
+/// [`PlotTheme`]. Unlike [`AsPlot`], the output needs no JavaScript, so it can be used offline and
+/// in email or PDF reports.
+pub trait AsSvg {
+    fn svg(&self, theme: &PlotTheme) -> String;
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, RangeBounded)]
@@ -116,6 +145,24 @@ pub fn radians_to_degrees(radians: f64) -> f64 { radians * 180.0 / PI }
 /// Calculate the average value of a 1-dimensional dataset.
 pub fn mean(data: &[f64]) -> f64 { data.iter().sum::<f64>() / data.len() as f64 }
 
+/// Calculate the median value of a 1-dimensional dataset. Returns [`f64::NAN`] for an empty
+/// dataset, consistent with [`mean`].
+pub fn median(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return f64::NAN;
+    }
+
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
 /// Convert a single, cartesian, XY pair to its corresponding polar angle in radians, taking into
 /// account the adjustments needed for each quadrant.
 pub fn theta_radians(x: f64, y: f64) -> f64 {
@@ -0,0 +1,250 @@
+use plotly::Bar;
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::common::Anchor;
+use plotly::common::Font;
+use plotly::common::HoverInfo;
+use plotly::common::LegendGroupTitle;
+use plotly::common::Marker;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::Axis;
+use plotly::layout::BarMode;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use plotly::layout::TraceOrder;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::model::Case;
+use crate::plots::AsPlot;
+use crate::theme::PlotTheme;
+
+/// The Snellen lines used as thresholds for cumulative visual acuity plots, as pairs of the
+/// line's logMAR value (in tenths) and its 6 m Snellen label.
+pub const SNELLEN_LINES: [(i32, &str); 10] = [
+    (-1, "6/4.8"),
+    (0, "6/6"),
+    (1, "6/7.5"),
+    (2, "6/9.5"),
+    (3, "6/12"),
+    (4, "6/15"),
+    (5, "6/19"),
+    (6, "6/24"),
+    (7, "6/30"),
+    (10, "6/60"),
+];
+
+/// A pair of visual acuity datasets, representing the surgeon of interest and a comparison
+/// cohort of peers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AcuityCompare {
+    pub surgeon: AcuityData,
+    pub cohort: AcuityData,
+}
+
+impl AsPlot for AcuityCompare {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        /// Create the category labels for each Snellen line.
+        fn categories() -> Vec<String> {
+            SNELLEN_LINES
+                .iter()
+                .map(|(_, label)| label.to_string())
+                .collect()
+        }
+
+        /// Create custom hover labels for the plot.
+        fn labels(percentages: &[Option<f64>]) -> Vec<String> {
+            SNELLEN_LINES
+                .iter()
+                .zip(percentages)
+                .map(|((_, label), percent)| {
+                    percent.map_or_else(
+                        || String::from("no cases"),
+                        |percent| format!("{percent:.0}% {label} or better"),
+                    )
+                })
+                .collect()
+        }
+
+        let Self { surgeon, cohort } = self;
+
+        let (surgeon_after, surgeon_before, cohort_after, cohort_before) = (
+            cumulative_percent(&surgeon.after_raw),
+            cumulative_percent(&surgeon.before_best),
+            cumulative_percent(&cohort.after_raw),
+            cumulative_percent(&cohort.before_best),
+        );
+
+        let surgeon_after = Bar::new(categories(), surgeon_after.clone())
+            .name("postop uncorrected")
+            .legend_group("surgeon")
+            .legend_group_title(
+                LegendGroupTitle::new()
+                    .text("Surgeon")
+                    .font(Font::new().color(theme.label)),
+            )
+            .marker(Marker::new().color(theme.surgeon))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&surgeon_after));
+
+        let surgeon_before = Bar::new(categories(), surgeon_before.clone())
+            .name("preop corrected")
+            .legend_group("surgeon")
+            .marker(Marker::new().color(theme.surgeon_muted))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&surgeon_before));
+
+        let cohort_after = Bar::new(categories(), cohort_after.clone())
+            .name("postop uncorrected")
+            .legend_group("cohort")
+            .legend_group_title(
+                LegendGroupTitle::new()
+                    .text("Peer cohort")
+                    .font(Font::new().color(theme.label)),
+            )
+            .marker(Marker::new().color(theme.cohort))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&cohort_after));
+
+        let cohort_before = Bar::new(categories(), cohort_before.clone())
+            .name("preop corrected")
+            .legend_group("cohort")
+            .marker(Marker::new().color(theme.cohort_muted))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&cohort_before));
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                    ModeBarButtonName::Zoom2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        plot.add_traces(vec![
+            surgeon_after,
+            surgeon_before,
+            cohort_after,
+            cohort_before,
+        ]);
+
+        let x_axis = Axis::new()
+            .title("Cumulative Snellen visual acuity (or better)")
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let y_axis = Axis::new()
+            .title("Cumulative eyes (%)")
+            .range(vec![0.0, 100.0])
+            .color(theme.label)
+            .show_line(false)
+            .zero_line(false)
+            .grid_color(theme.grid);
+
+        let layout = Layout::new()
+            .bar_mode(BarMode::Group)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .trace_order(TraceOrder::Grouped)
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+/// A visual acuity dataset, with logMAR values for preoperative corrected acuity
+/// ([`before_best`](Self::before_best)) and postoperative uncorrected acuity
+/// ([`after_raw`](Self::after_raw)).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AcuityData {
+    pub before_best: Vec<f64>,
+    pub after_raw: Vec<f64>,
+}
+
+impl<'a> FromIterator<&'a Case> for AcuityData {
+    fn from_iter<T: IntoIterator<Item = &'a Case>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Self::default(), |mut data, Case { va, .. }| {
+                data.before_best.extend(va.before.best.logmar());
+                data.after_raw.extend(va.after.raw.logmar());
+
+                data
+            })
+    }
+}
+
+/// For each of the [`SNELLEN_LINES`], return the percentage of logMAR values (0.0 to 100.0) that
+/// are on that line or better, or [`None`] if there are no values. Values are rounded to the
+/// nearest line first, because charts differ in their exact denominators (a 6/9 reading should
+/// count towards 6/9.5 or better).
+pub fn cumulative_percent(logmar: &[f64]) -> Vec<Option<f64>> {
+    if logmar.is_empty() {
+        return vec![None; SNELLEN_LINES.len()];
+    }
+
+    let lines = logmar
+        .iter()
+        .map(|value| (value * 10.0).round() as i32)
+        .collect::<Vec<_>>();
+
+    SNELLEN_LINES
+        .iter()
+        .map(|(threshold, _)| {
+            let count = lines.iter().filter(|line| *line <= threshold).count();
+
+            Some(count as f64 * 100.0 / lines.len() as f64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_to_nearest_line() {
+        // 6/9 (0.176) rounds to the 6/9.5 line, and 6/7.5 (0.097) rounds to the 6/7.5 line.
+        let percentages = cumulative_percent(&[0.0, 0.097, 0.176, 0.301]);
+
+        assert_eq!(percentages[0], Some(0.0));
+        assert_eq!(percentages[1], Some(25.0));
+        assert_eq!(percentages[2], Some(50.0));
+        assert_eq!(percentages[3], Some(75.0));
+        assert_eq!(percentages[4], Some(100.0));
+    }
+
+    #[test]
+    fn has_no_percentages_without_cases() {
+        let percentages = cumulative_percent(&[]);
+
+        assert_eq!(percentages.len(), SNELLEN_LINES.len());
+        assert!(percentages.iter().all(Option::is_none));
+    }
+}
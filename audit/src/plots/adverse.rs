@@ -0,0 +1,624 @@
+use std::fmt::Display;
+
+use plotly::Bar;
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::Scatter;
+use plotly::common::Anchor;
+use plotly::common::DashType;
+use plotly::common::ErrorData;
+use plotly::common::ErrorType;
+use plotly::common::Font;
+use plotly::common::HoverInfo;
+use plotly::common::Line;
+use plotly::common::Marker;
+use plotly::common::Mode;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::Axis;
+use plotly::layout::BarMode;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::model::Adverse;
+use crate::plots::AsPlot;
+use crate::theme::PlotTheme;
+
+/// The standard normal quantile for a two-sided 95% interval.
+pub const Z_95: f64 = 1.959964;
+
+/// The standard normal quantile for a two-sided 99.8% interval.
+pub const Z_998: f64 = 3.090232;
+
+/// The one-sided tail probability of 95% control limits.
+pub const TAIL_95: f64 = 0.025;
+
+/// The one-sided tail probability of 99.8% control limits.
+pub const TAIL_998: f64 = 0.001;
+
+/// The adverse event categories reported, where [`None`] represents any adverse event.
+const CATEGORIES: [Option<Adverse>; 5] = [
+    None,
+    Some(Adverse::Rhexis),
+    Some(Adverse::Pc),
+    Some(Adverse::Zonule),
+    Some(Adverse::Other),
+];
+
+/// Return a human-readable label for an adverse event category.
+fn category_label(category: &Option<Adverse>) -> &'static str {
+    match category {
+        None => "Any",
+        Some(Adverse::Rhexis) => "Rhexis",
+        Some(Adverse::Pc) => "PCR",
+        Some(Adverse::Zonule) => "Zonule",
+        Some(Adverse::Other) => "Other",
+    }
+}
+
+/// Calculate the Wilson score interval for a binomial proportion, returning the lower and upper
+/// bounds as proportions (0.0 to 1.0). Unlike the normal approximation (Wald interval), the Wilson
+/// interval remains within 0.0 to 1.0 and behaves well for the small counts and rare events typical
+/// of complication rates. Returns `(0.0, 1.0)` if there are no trials.
+pub fn wilson_interval(events: usize, trials: usize, z: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+
+    let n = trials as f64;
+    let p = events as f64 / n;
+    let z2 = z * z;
+
+    let centre = p + z2 / (2.0 * n);
+    let margin = z * f64::sqrt((p * (1.0 - p) / n) + (z2 / (4.0 * n * n)));
+    let denominator = 1.0 + z2 / n;
+
+    (
+        ((centre - margin) / denominator).max(0.0),
+        ((centre + margin) / denominator).min(1.0),
+    )
+}
+
+/// Return the exact binomial control limit (as a proportion) at the cumulative probability `q`,
+/// for `trials` cases with a target proportion of `p`. The discrete binomial quantile is
+/// interpolated so that the limits form a smooth funnel, as in Spiegelhalter, Stat Med 2005;
+/// 24:1185-1202 (Appendix A.1.1). Unlike the normal approximation, the limits are asymmetric and
+/// remain valid for the small volumes and rare events typical of complication rates.
+pub fn binomial_limit(trials: usize, p: f64, q: f64) -> f64 {
+    if trials == 0 || p <= 0.0 || p >= 1.0 {
+        return p.clamp(0.0, 1.0);
+    }
+
+    let n = trials as f64;
+    let log_odds = f64::ln(p / (1.0 - p));
+
+    // Accumulate the probability mass function in log space, so that it doesn't underflow for a
+    // large number of trials.
+    let mut log_pmf = n * f64::ln_1p(-p);
+    let mut cdf = 0.0;
+
+    for r in 0..=trials {
+        if r > 0 {
+            log_pmf += f64::ln((n - (r - 1) as f64) / r as f64) + log_odds;
+        }
+
+        let pmf = log_pmf.exp();
+        cdf += pmf;
+
+        if cdf >= q {
+            let alpha = (cdf - q) / pmf;
+
+            return ((r as f64 - alpha) / n).clamp(0.0, 1.0);
+        }
+    }
+
+    1.0
+}
+
+/// The rate of an adverse event category, with a 95% Wilson confidence interval. Rates and
+/// bounds are percentages (0.0 to 100.0).
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AdverseRate {
+    /// The adverse event category, where [`None`] represents any adverse event.
+    pub category: Option<Adverse>,
+    pub events: usize,
+    pub cases: usize,
+    pub rate: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Display for AdverseRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            category,
+            events,
+            cases,
+            rate,
+            lower,
+            upper,
+        } = self;
+
+        let label = category_label(category);
+
+        if *cases == 0 {
+            return write!(f, "{label}: no cases");
+        }
+
+        write!(
+            f,
+            "{label} {events}/{cases} ({rate:.2}%, 95% CI {lower:.2}–{upper:.2}%)"
+        )
+    }
+}
+
+/// A dataset of adverse events, with one entry per case ([`None`] for an uncomplicated case).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AdverseData {
+    pub cases: Vec<Option<Adverse>>,
+}
+
+impl FromIterator<Option<Adverse>> for AdverseData {
+    fn from_iter<T: IntoIterator<Item = Option<Adverse>>>(iter: T) -> Self {
+        Self {
+            cases: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl AdverseData {
+    /// Return the number of cases with an event in the given category, where [`None`] counts any
+    /// adverse event.
+    pub fn events(&self, category: &Option<Adverse>) -> usize {
+        self.cases
+            .iter()
+            .filter(|adverse| match category {
+                None => adverse.is_some(),
+                Some(_) => *adverse == category,
+            })
+            .count()
+    }
+
+    /// Calculate the rate of the given category, with a 95% Wilson confidence interval. The rate
+    /// is zero (with an interval of 0 to 100%) if there are no cases.
+    pub fn rate(&self, category: Option<Adverse>) -> AdverseRate {
+        let (events, cases) = (self.events(&category), self.cases.len());
+        let (lower, upper) = wilson_interval(events, cases, Z_95);
+
+        AdverseRate {
+            category,
+            events,
+            cases,
+            rate: if cases == 0 {
+                0.0
+            } else {
+                events as f64 * 100.0 / cases as f64
+            },
+            lower: lower * 100.0,
+            upper: upper * 100.0,
+        }
+    }
+
+    /// Calculate the rate of each adverse event category, starting with any adverse event.
+    pub fn rates(&self) -> Vec<AdverseRate> {
+        CATEGORIES
+            .iter()
+            .map(|category| self.rate(category.clone()))
+            .collect()
+    }
+}
+
+/// A pair of adverse event datasets, representing the surgeon of interest and a comparison
+/// cohort of peers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AdverseCompare {
+    pub surgeon: AdverseData,
+    pub cohort: AdverseData,
+}
+
+impl AdverseCompare {
+    /// Create a funnel plot of the surgeon's PCR rate against the cohort.
+    pub fn pcr_funnel(&self) -> Funnel {
+        let Self { surgeon, cohort } = self;
+        let category = Some(Adverse::Pc);
+
+        Funnel {
+            label: category_label(&category).to_string(),
+            events: surgeon.events(&category),
+            cases: surgeon.cases.len(),
+            cohort_rate: (!cohort.cases.is_empty())
+                .then(|| cohort.events(&category) as f64 / cohort.cases.len() as f64),
+        }
+    }
+}
+
+impl AsPlot for AdverseCompare {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        /// Create the error bars for a set of rates.
+        fn error_bars(rates: &[AdverseRate], color: &str) -> ErrorData {
+            let (plus, minus) = rates
+                .iter()
+                .map(
+                    |AdverseRate {
+                         rate, lower, upper, ..
+                     }| (upper - rate, rate - lower),
+                )
+                .unzip();
+
+            ErrorData::new(ErrorType::Data)
+                .symmetric(false)
+                .array(plus)
+                .array_minus(minus)
+                .color(color)
+        }
+
+        /// Create custom hover labels for the plot.
+        fn labels(rates: &[AdverseRate]) -> Vec<String> {
+            rates.iter().map(|rate| rate.to_string()).collect()
+        }
+
+        let categories = CATEGORIES
+            .iter()
+            .map(|category| category_label(category).to_string())
+            .collect::<Vec<_>>();
+
+        let Self { surgeon, cohort } = self;
+        let (surgeon, cohort) = (surgeon.rates(), cohort.rates());
+
+        let surgeon_trace = Bar::new(
+            categories.clone(),
+            surgeon.iter().map(|rate| rate.rate).collect(),
+        )
+        .name("Surgeon")
+        .marker(Marker::new().color(theme.surgeon))
+        .error_y(error_bars(&surgeon, theme.label))
+        .hover_info(HoverInfo::Text)
+        .hover_text_array(labels(&surgeon));
+
+        let cohort_trace = Bar::new(categories, cohort.iter().map(|rate| rate.rate).collect())
+            .name("Peer cohort")
+            .marker(Marker::new().color(theme.cohort))
+            .error_y(error_bars(&cohort, theme.label))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&cohort));
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                    ModeBarButtonName::Zoom2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        plot.add_traces(vec![surgeon_trace, cohort_trace]);
+
+        let x_axis = Axis::new()
+            .title("Adverse event")
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let y_axis = Axis::new()
+            .title("Rate (%, with 95% CI)")
+            .color(theme.label)
+            .show_line(false)
+            .zero_line(false)
+            .grid_color(theme.grid);
+
+        let layout = Layout::new()
+            .bar_mode(BarMode::Group)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+/// A funnel plot of a surgeon's adverse event rate against their case volume, with control limits
+/// around the cohort rate. Peer cases are anonymous, so individual peers can't be plotted; the
+/// cohort contributes only the target rate.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Funnel {
+    pub label: String,
+    pub events: usize,
+    pub cases: usize,
+    /// The cohort rate, as a proportion (0.0 to 1.0), or [`None`] if the cohort has no cases.
+    pub cohort_rate: Option<f64>,
+}
+
+impl Funnel {
+    /// Return the surgeon's rate, as a proportion, or [`None`] if they have no cases.
+    pub fn rate(&self) -> Option<f64> {
+        (self.cases > 0).then(|| self.events as f64 / self.cases as f64)
+    }
+
+    /// Return the lower and upper control limits (as proportions) for the given number of cases,
+    /// using exact binomial limits around the cohort rate with the given one-sided tail
+    /// probability. Returns [`None`] if the cohort has no cases.
+    pub fn limits(&self, cases: usize, tail: f64) -> Option<(f64, f64)> {
+        let p = self.cohort_rate?;
+
+        Some((
+            binomial_limit(cases, p, tail),
+            binomial_limit(cases, p, 1.0 - tail),
+        ))
+    }
+
+    /// Return whether the surgeon's rate lies above the upper control limit for the given tail
+    /// probability.
+    pub fn is_outlier(&self, tail: f64) -> bool {
+        match (self.rate(), self.limits(self.cases, tail)) {
+            (Some(rate), Some((_, upper))) => rate > upper,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Funnel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            label,
+            events,
+            cases,
+            cohort_rate,
+        } = self;
+
+        let position = if self.is_outlier(TAIL_998) {
+            "above the 99.8% limit"
+        } else if self.is_outlier(TAIL_95) {
+            "above the 95% limit"
+        } else {
+            "within the control limits"
+        };
+
+        match (self.rate(), cohort_rate) {
+            (None, _) => write!(f, "{label}: no cases"),
+
+            (Some(rate), None) => write!(
+                f,
+                "{label} {events}/{cases} ({:.2}%), no cohort cases",
+                rate * 100.0
+            ),
+
+            (Some(rate), Some(cohort_rate)) => write!(
+                f,
+                "{label} {events}/{cases} ({:.2}%), cohort rate {:.2}%: {position}",
+                rate * 100.0,
+                cohort_rate * 100.0
+            ),
+        }
+    }
+}
+
+impl AsPlot for Funnel {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        // Extend the funnel beyond the surgeon's volume, so that their point is never at the edge.
+        let max_cases = (self.cases * 3 / 2).max(100);
+        let x = (1..=max_cases).collect::<Vec<_>>();
+
+        let limit = |tail: f64, upper: bool| {
+            x.iter()
+                .filter_map(|cases| {
+                    let (lower, higher) = self.limits(*cases, tail)?;
+                    Some((if upper { higher } else { lower }) * 100.0)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Without cohort cases, there is no target rate for the funnel.
+        let cohort = self.cohort_rate.map(|cohort_rate| {
+            Scatter::new(x.clone(), vec![cohort_rate * 100.0; x.len()])
+                .name("cohort rate")
+                .mode(Mode::Lines)
+                .line(Line::new().color(theme.cohort))
+                .hover_info(HoverInfo::Skip)
+        });
+
+        let limits = [
+            (TAIL_95, "95% limits", theme.legend, DashType::Dash),
+            (
+                TAIL_998,
+                "99.8% limits",
+                theme.surgeon_confidence,
+                DashType::Dot,
+            ),
+        ]
+        .into_iter()
+        .filter(|_| self.cohort_rate.is_some())
+        .flat_map(|(tail, name, color, dash)| {
+            [true, false].map(|upper| {
+                Scatter::new(x.clone(), limit(tail, upper))
+                    .name(name)
+                    .legend_group(name)
+                    .show_legend(upper)
+                    .mode(Mode::Lines)
+                    .line(Line::new().color(color).dash(dash.clone()))
+                    .hover_info(HoverInfo::Skip)
+            })
+        })
+        .collect::<Vec<_>>();
+
+        // Without cases, the surgeon has no rate to plot.
+        let surgeon = self.rate().map(|rate| {
+            Scatter::new(vec![self.cases], vec![rate * 100.0])
+                .name("Surgeon")
+                .mode(Mode::Markers)
+                .marker(Marker::new().color(theme.surgeon).size(12))
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(vec![self.to_string()])
+        });
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        if let Some(cohort) = cohort {
+            plot.add_trace(cohort);
+        }
+        for limit in limits {
+            plot.add_trace(limit);
+        }
+        if let Some(surgeon) = surgeon {
+            plot.add_trace(surgeon);
+        }
+
+        let x_axis = Axis::new()
+            .title("Number of cases")
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let y_axis = Axis::new()
+            .title(format!("{} rate (%)", self.label))
+            .color(theme.label)
+            .show_line(false)
+            .zero_line(false)
+            .grid_color(theme.grid);
+
+        let layout = Layout::new()
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 1e-4 }
+
+    #[test]
+    fn calculates_wilson_interval() {
+        // Reference values from Newcombe, Stat Med 1998; 17:857-872 (Table I, method 3).
+        let (lower, upper) = wilson_interval(81, 263, Z_95);
+        assert!(
+            close(lower, 0.2553) && close(upper, 0.3662),
+            "{lower} {upper}"
+        );
+
+        let (lower, upper) = wilson_interval(0, 20, Z_95);
+        assert!(close(lower, 0.0) && close(upper, 0.1611), "{lower} {upper}");
+    }
+
+    #[test]
+    fn calculates_rates_by_category() {
+        let data = [None, None, Some(Adverse::Pc), Some(Adverse::Rhexis)]
+            .into_iter()
+            .collect::<AdverseData>();
+
+        let any = data.rate(None);
+        let pc = data.rate(Some(Adverse::Pc));
+
+        assert_eq!((any.events, any.cases), (2, 4));
+        assert!(close(any.rate, 50.0));
+        assert_eq!(pc.events, 1);
+        assert!(pc.lower < pc.rate && pc.rate < pc.upper);
+        assert_eq!(data.rates().len(), 5);
+    }
+
+    #[test]
+    fn funnel_limits_narrow_with_volume() {
+        let funnel = Funnel {
+            label: "PCR".to_string(),
+            events: 10,
+            cases: 200,
+            cohort_rate: Some(0.02),
+        };
+
+        let (_, upper_small) = funnel.limits(50, TAIL_95).unwrap();
+        let (_, upper_large) = funnel.limits(500, TAIL_95).unwrap();
+
+        assert!(upper_large < upper_small);
+        assert!(funnel.is_outlier(TAIL_95));
+    }
+
+    #[test]
+    fn calculates_exact_binomial_limits() {
+        // Hand-computed from the binomial CDF: for n = 100 and p = 0.02, P(X ≤ 4) = 0.94917 and
+        // P(X ≤ 5) = 0.98452, so the interpolated 97.5% limit is (5 - 0.26923) / 100.
+        assert!(close(binomial_limit(100, 0.02, 0.975), 0.047308));
+        assert!(close(binomial_limit(100, 0.02, 0.999), 0.069783));
+        assert!(close(binomial_limit(500, 0.02, 0.975), 0.032120));
+        assert!(close(binomial_limit(50, 0.1, 0.025), 0.013863));
+
+        // The lower limit can't fall below zero for rare events.
+        assert!(close(binomial_limit(100, 0.02, 0.025), 0.0));
+    }
+
+    #[test]
+    fn describes_no_cases_without_nan() {
+        let empty = AdverseData::default();
+        let rate = empty.rate(None);
+
+        assert!(close(rate.rate, 0.0));
+        assert_eq!(rate.to_string(), "Any: no cases");
+
+        let funnel = AdverseCompare {
+            surgeon: empty.clone(),
+            cohort: empty,
+        }
+        .pcr_funnel();
+
+        assert_eq!(funnel.rate(), None);
+        assert_eq!(funnel.cohort_rate, None);
+        assert!(!funnel.is_outlier(TAIL_95));
+        assert_eq!(funnel.to_string(), "PCR: no cases");
+    }
+}
@@ -0,0 +1,437 @@
+use std::fmt::Display;
+
+use plotly::Configuration;
+use plotly::Histogram;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::common::Anchor;
+use plotly::common::Font;
+use plotly::common::Marker;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::histogram::Bins;
+use plotly::histogram::HistNorm;
+use plotly::layout::Axis;
+use plotly::layout::BarMode;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::plots::AsPlot;
+use crate::plots::Cartesian;
+use crate::plots::CartesianPoint;
+use crate::plots::Polar;
+use crate::plots::PolarPoint;
+use crate::plots::mean;
+use crate::theme::PlotTheme;
+
+/// The results of an Alpins vector analysis for a single case.
+///
+/// The vectors ([`tia`](Self::tia), [`sia`](Self::sia) and [`dv`](Self::dv)) are in diopters at
+/// the corneal plane, and are expressed as double-angle [`PolarPoint`]s, so that they can be
+/// plotted alongside the other double-angle plots. Halve [`theta`](PolarPoint::theta) to recover
+/// the meridian.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Alpins {
+    /// Target induced astigmatism: the change in astigmatism the surgery was intended to induce
+    /// (target minus preop).
+    pub tia: PolarPoint,
+    /// Surgically induced astigmatism: the change in astigmatism the surgery actually induced
+    /// (postop minus preop).
+    pub sia: PolarPoint,
+    /// Difference vector: the additional change needed to reach the target from the postop
+    /// astigmatism (target minus postop).
+    pub dv: PolarPoint,
+    /// Correction index: the ratio of SIA to TIA. Values above 1.0 indicate overcorrection, and
+    /// values below 1.0 indicate undercorrection.
+    pub ci: f64,
+    /// Magnitude of error: the arithmetic difference between the magnitudes of the SIA and TIA.
+    pub me: f64,
+    /// Angle of error, in degrees (-90.0 to 90.0): the angle between the SIA and TIA, with
+    /// positive values when the SIA is counterclockwise to the TIA. This is [`f64::NAN`] when no
+    /// astigmatism was induced, as the angle is undefined.
+    pub ae: f64,
+    /// Index of success: the ratio of DV to TIA. A value of 0.0 indicates that the target was
+    /// reached.
+    pub ios: f64,
+}
+
+impl Alpins {
+    /// Perform an Alpins vector analysis from the preoperative, target, and postoperative
+    /// astigmatism, each provided as a double-angle [`PolarPoint`] in diopters at the corneal
+    /// plane. Returns [`None`] if the TIA is zero, because the indices are undefined.
+    pub fn new(preop: &PolarPoint, target: &PolarPoint, postop: &PolarPoint) -> Option<Self> {
+        /// Subtract the second vector from the first.
+        fn difference(a: &PolarPoint, b: &PolarPoint) -> PolarPoint {
+            let (a, b) = (a.cartesian(), b.cartesian());
+
+            CartesianPoint {
+                x: a.x - b.x,
+                y: a.y - b.y,
+            }
+            .polar()
+        }
+
+        let tia = difference(target, preop);
+        let sia = difference(postop, preop);
+        let dv = difference(target, postop);
+
+        if tia.r < f64::EPSILON {
+            return None;
+        }
+
+        let ae = if sia.r < f64::EPSILON {
+            f64::NAN
+        } else {
+            // Normalize the double-angle difference to (-180, 180], then halve it to return to
+            // single angles.
+            let double_angle = (sia.theta - tia.theta).rem_euclid(360.0);
+
+            if double_angle > 180.0 {
+                (double_angle - 360.0) / 2.0
+            } else {
+                double_angle / 2.0
+            }
+        };
+
+        Some(Self {
+            ci: sia.r / tia.r,
+            me: sia.r - tia.r,
+            ae,
+            ios: dv.r / tia.r,
+            tia,
+            sia,
+            dv,
+        })
+    }
+}
+
+/// A pair of Alpins datasets, representing the surgeon of interest and a comparison cohort of
+/// peers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AlpinsCompare {
+    pub surgeon: AlpinsData,
+    pub cohort: AlpinsData,
+}
+
+impl AlpinsCompare {
+    /// Create a histogram comparing the distribution of the given [`AlpinsMetric`].
+    pub fn histogram(&self, metric: AlpinsMetric) -> AlpinsHistogram {
+        AlpinsHistogram {
+            metric,
+            surgeon: self.surgeon.values(metric),
+            cohort: self.cohort.values(metric),
+        }
+    }
+}
+
+/// A dataset of Alpins vector analyses, one per case.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AlpinsData {
+    pub cases: Vec<Alpins>,
+}
+
+impl FromIterator<Alpins> for AlpinsData {
+    fn from_iter<T: IntoIterator<Item = Alpins>>(iter: T) -> Self {
+        Self {
+            cases: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl AlpinsData {
+    /// Return the value of the given [`AlpinsMetric`] for each case, omitting undefined values.
+    pub fn values(&self, metric: AlpinsMetric) -> Vec<f64> {
+        self.cases
+            .iter()
+            .map(|alpins| match metric {
+                AlpinsMetric::AngleOfError => alpins.ae,
+                AlpinsMetric::CorrectionIndex => alpins.ci,
+            })
+            .filter(|value| value.is_finite())
+            .collect()
+    }
+
+    /// Calculate aggregate statistics for the dataset. Alpins recommends the geometric mean for
+    /// the correction index, because it is a ratio (an overcorrection of 2.0 and an
+    /// undercorrection of 0.5 should average to 1.0). Returns [`None`] if there are no cases.
+    pub fn stats(&self) -> Option<AlpinsStats> {
+        if self.cases.is_empty() {
+            return None;
+        }
+
+        let magnitudes = |f: fn(&Alpins) -> f64| self.cases.iter().map(f).collect::<Vec<_>>();
+        let ae = self.values(AlpinsMetric::AngleOfError);
+        let ae_abs = ae.iter().map(|ae| ae.abs()).collect::<Vec<_>>();
+        let ci_ln = self
+            .values(AlpinsMetric::CorrectionIndex)
+            .iter()
+            .filter(|ci| **ci > 0.0)
+            .map(|ci| ci.ln())
+            .collect::<Vec<_>>();
+
+        // The angle of error and correction index are undefined for some cases, so there may be
+        // none to average.
+        let defined_mean = |values: &[f64]| (!values.is_empty()).then(|| mean(values));
+
+        Some(AlpinsStats {
+            count: self.cases.len(),
+            tia: mean(&magnitudes(|alpins| alpins.tia.r)),
+            sia: mean(&magnitudes(|alpins| alpins.sia.r)),
+            dv: mean(&magnitudes(|alpins| alpins.dv.r)),
+            ci: defined_mean(&ci_ln).map(f64::exp),
+            me: mean(&magnitudes(|alpins| alpins.me)),
+            ae: defined_mean(&ae),
+            ae_abs: defined_mean(&ae_abs),
+            ios: mean(&magnitudes(|alpins| alpins.ios)),
+        })
+    }
+}
+
+/// Aggregate statistics for an [`AlpinsData`]. Vector magnitudes are arithmetic means in
+/// diopters, [`ci`](Self::ci) is a geometric mean, and angles are in degrees. The angle of error
+/// and correction index are [`None`] if they are undefined for every case.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AlpinsStats {
+    pub count: usize,
+    pub tia: f64,
+    pub sia: f64,
+    pub dv: f64,
+    pub ci: Option<f64>,
+    pub me: f64,
+    pub ae: Option<f64>,
+    pub ae_abs: Option<f64>,
+    pub ios: f64,
+}
+
+impl Display for AlpinsStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            count,
+            tia,
+            sia,
+            dv,
+            ci,
+            me,
+            ae,
+            ae_abs,
+            ios,
+        } = self;
+
+        let ci = ci.map_or_else(String::new, |ci| format!(", CI {ci:.2}"));
+
+        let ae = ae.zip(*ae_abs).map_or_else(String::new, |(ae, ae_abs)| {
+            format!(", AE {ae:+.1}° (absolute {ae_abs:.1}°)")
+        });
+
+        write!(
+            f,
+            "n = {count}, TIA {tia:.2} D, SIA {sia:.2} D, DV {dv:.2} D{ci}, ME {me:+.2} D{ae}, IOS \
+             {ios:.2}"
+        )
+    }
+}
+
+impl Display for AlpinsData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.stats() {
+            Some(stats) => write!(f, "{stats}"),
+            None => write!(f, "no cases"),
+        }
+    }
+}
+
+/// The per-case Alpins indices that can be plotted as a distribution.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AlpinsMetric {
+    AngleOfError,
+    CorrectionIndex,
+}
+
+/// The distribution of a single [`AlpinsMetric`] for the surgeon of interest and a comparison
+/// cohort of peers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AlpinsHistogram {
+    pub metric: AlpinsMetric,
+    pub surgeon: Vec<f64>,
+    pub cohort: Vec<f64>,
+}
+
+impl AsPlot for AlpinsHistogram {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        let Self {
+            metric,
+            surgeon,
+            cohort,
+        } = self;
+
+        let title = match metric {
+            AlpinsMetric::AngleOfError => "Angle of error (°)",
+            AlpinsMetric::CorrectionIndex => "Correction index",
+        };
+
+        let bins = || match metric {
+            AlpinsMetric::AngleOfError => Bins::new(-90.0, 90.0, 5.0),
+            AlpinsMetric::CorrectionIndex => Bins::new(0.0, 3.0, 0.1),
+        };
+
+        let surgeon = Histogram::new(surgeon.clone())
+            .name("Surgeon")
+            .hist_norm(HistNorm::Percent)
+            .x_bins(bins())
+            .marker(Marker::new().color(theme.surgeon))
+            .opacity(0.75);
+
+        let cohort = Histogram::new(cohort.clone())
+            .name("Peer cohort")
+            .hist_norm(HistNorm::Percent)
+            .x_bins(bins())
+            .marker(Marker::new().color(theme.cohort))
+            .opacity(0.6);
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                    ModeBarButtonName::Zoom2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        plot.add_traces(vec![cohort, surgeon]);
+
+        let x_axis = Axis::new()
+            .title(title)
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let y_axis = Axis::new()
+            .title("Eyes (%)")
+            .color(theme.label)
+            .show_line(false)
+            .zero_line(false)
+            .grid_color(theme.grid);
+
+        let layout = Layout::new()
+            .bar_mode(BarMode::Overlay)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 1e-9 }
+
+    // 1.00 D of corneal astigmatism, steep at 90°.
+    fn preop() -> PolarPoint {
+        PolarPoint {
+            r: 1.0,
+            theta: 180.0,
+        }
+    }
+
+    fn zero() -> PolarPoint { PolarPoint { r: 0.0, theta: 0.0 } }
+
+    #[test]
+    fn full_correction_is_ideal() {
+        let alpins = Alpins::new(&preop(), &zero(), &zero()).unwrap();
+
+        assert!(close(alpins.tia.r, 1.0));
+        assert!(close(alpins.sia.r, 1.0));
+        assert!(close(alpins.dv.r, 0.0));
+        assert!(close(alpins.ci, 1.0));
+        assert!(close(alpins.me, 0.0));
+        assert!(close(alpins.ae, 0.0));
+        assert!(close(alpins.ios, 0.0));
+    }
+
+    #[test]
+    fn half_correction_is_undercorrected() {
+        let postop = PolarPoint {
+            r: 0.5,
+            theta: 180.0,
+        };
+        let alpins = Alpins::new(&preop(), &zero(), &postop).unwrap();
+
+        assert!(close(alpins.ci, 0.5));
+        assert!(close(alpins.me, -0.5));
+        assert!(close(alpins.ae, 0.0));
+        assert!(close(alpins.ios, 0.5));
+    }
+
+    #[test]
+    fn rotated_correction_has_angle_of_error() {
+        // An SIA of 1.00 D rotated 30° counterclockwise from the TIA leaves 1.00 D of residual
+        // astigmatism at 60°.
+        let postop = PolarPoint {
+            r: 1.0,
+            theta: 120.0,
+        };
+        let alpins = Alpins::new(&preop(), &zero(), &postop).unwrap();
+
+        assert!(close(alpins.ci, 1.0));
+        assert!(close(alpins.ae, 30.0));
+        assert!(close(alpins.ios, 1.0));
+    }
+
+    #[test]
+    fn zero_tia_returns_none() {
+        assert!(Alpins::new(&preop(), &preop(), &zero()).is_none());
+    }
+
+    #[test]
+    fn correction_index_uses_geometric_mean() {
+        let data = [0.5_f64, 2.0]
+            .into_iter()
+            .filter_map(|r| {
+                let postop = PolarPoint {
+                    r: (1.0 - r).abs(),
+                    theta: if r < 1.0 { 180.0 } else { 0.0 },
+                };
+
+                Alpins::new(&preop(), &zero(), &postop)
+            })
+            .collect::<AlpinsData>();
+
+        assert!(close(data.stats().unwrap().ci.unwrap(), 1.0));
+    }
+
+    #[test]
+    fn describes_empty_data_without_nan() {
+        let empty = AlpinsData { cases: Vec::new() };
+
+        assert_eq!(empty.stats(), None);
+        assert_eq!(empty.to_string(), "no cases");
+    }
+}
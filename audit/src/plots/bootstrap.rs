@@ -0,0 +1,303 @@
+use std::fmt::Display;
+
+use audit_macro::RangeBounded;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bounded::Bounded;
+use crate::plots::Cartesian;
+use crate::plots::CartesianData;
+use crate::plots::ConfidenceParams;
+use crate::plots::Polar;
+use crate::plots::PolarData;
+use crate::plots::PredictionData;
+use crate::plots::StdDev;
+use crate::plots::Variance;
+use crate::plots::mean;
+
+/// The number of resamples drawn for a bootstrap estimate.
+#[derive(Clone, Copy, Debug, PartialEq, RangeBounded)]
+pub struct Iterations(#[bounded(range = 100..=100000, default = 2000)] u32);
+
+/// The confidence level of a bootstrap interval, as a proportion.
+#[derive(Clone, Copy, Debug, PartialEq, RangeBounded)]
+pub struct Level(#[bounded(range = 0.5..=0.999, default = 0.95)] f64);
+
+/// The characteristics of a bootstrap estimate. The random number generator is seeded, so that a
+/// given dataset always produces the same interval (and the same plot).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BootstrapParams {
+    /// The number of resamples.
+    iterations: Iterations,
+    /// The seed for the random number generator.
+    seed: u64,
+    /// The confidence level of the interval.
+    level: Level,
+}
+
+impl BootstrapParams {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    pub fn iterations(mut self, iterations: Iterations) -> Self {
+        self.iterations = iterations;
+
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+
+        self
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+
+        self
+    }
+
+    /// Resample the data with replacement, and return the statistic calculated for each
+    /// resample.
+    pub fn replicates<D, S>(&self, data: &D, statistic: impl Fn(&D) -> S) -> Vec<S>
+    where D: Resample {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        (0..self.iterations.inner())
+            .map(|_| statistic(&data.resample(&mut rng)))
+            .collect()
+    }
+
+    /// Estimate a statistic, with a bootstrap percentile interval. Returns [`None`] if the data
+    /// are empty, or the statistic is undefined for the data.
+    pub fn interval<D>(&self, data: &D, statistic: impl Fn(&D) -> f64) -> Option<Interval>
+    where D: Resample {
+        if data.is_empty() {
+            return None;
+        }
+
+        let estimate = statistic(data);
+
+        let mut replicates = self
+            .replicates(data, statistic)
+            .into_iter()
+            .filter(|value| value.is_finite())
+            .collect::<Vec<_>>();
+
+        if !estimate.is_finite() || replicates.is_empty() {
+            return None;
+        }
+
+        replicates.sort_by(f64::total_cmp);
+        let tail = (1.0 - self.level.inner()) / 2.0;
+
+        Some(Interval {
+            estimate,
+            lower: quantile(&replicates, tail),
+            upper: quantile(&replicates, 1.0 - tail),
+        })
+    }
+}
+
+/// Return the quantile `p` (0.0 to 1.0) of sorted data, interpolating linearly between ranks.
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+
+    sorted[below] + ((sorted[above] - sorted[below]) * (rank - below as f64))
+}
+
+/// A point estimate of a statistic, with the bounds of its bootstrap interval.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Interval {
+    pub estimate: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            estimate,
+            lower,
+            upper,
+        } = self;
+
+        write!(f, "{estimate:.2} ({lower:.2} to {upper:.2})")
+    }
+}
+
+/// A dataset that can be resampled with replacement, for bootstrap estimates.
+pub trait Resample {
+    /// Draw a resample of the same size as the dataset, with replacement.
+    fn resample(&self, rng: &mut StdRng) -> Self;
+
+    /// Return whether the dataset is empty (and so can't be resampled).
+    fn is_empty(&self) -> bool;
+}
+
+/// Draw a resample of the same size as `values`, with replacement.
+fn resample_values<T: Clone>(values: &[T], rng: &mut StdRng) -> Vec<T> {
+    (0..values.len())
+        .map(|_| values[rng.random_range(0..values.len())].clone())
+        .collect()
+}
+
+impl Resample for CartesianData {
+    fn resample(&self, rng: &mut StdRng) -> Self {
+        Self {
+            points: resample_values(&self.points, rng),
+        }
+    }
+
+    fn is_empty(&self) -> bool { self.points.is_empty() }
+}
+
+impl Resample for PolarData {
+    fn resample(&self, rng: &mut StdRng) -> Self {
+        Self {
+            points: resample_values(&self.points, rng),
+        }
+    }
+
+    fn is_empty(&self) -> bool { self.points.is_empty() }
+}
+
+impl Resample for PredictionData {
+    fn resample(&self, rng: &mut StdRng) -> Self {
+        Self {
+            errors: resample_values(&self.errors, rng),
+        }
+    }
+
+    fn is_empty(&self) -> bool { self.errors.is_empty() }
+}
+
+impl PolarData {
+    /// Generate an ellipse covering the bootstrap distribution of the centroid, at the given
+    /// confidence level. Unlike the tolerance ellipse from [`PolarData::confidence`], which covers
+    /// the cases themselves, this ellipse shrinks as the number of cases grows. Returns [`None`]
+    /// for fewer than 3 points.
+    pub fn mean_confidence(&self, params: &BootstrapParams) -> Option<PolarData> {
+        if self.points.len() < 3 {
+            return None;
+        }
+
+        let centroids = params
+            .replicates(self, |data| data.centroid().points[0].cartesian())
+            .into_iter()
+            .collect::<CartesianData>();
+
+        // A bivariate normal region at level p spans sqrt(chi-squared(2 df, p)) = sqrt(-2 ln(1 -
+        // p)) standard deviations.
+        let std_dev = StdDev::new(f64::sqrt(-2.0 * f64::ln(1.0 - params.level.inner()))).ok()?;
+        let confidence = ConfidenceParams::new()
+            .variance(Variance::Sample)
+            .std_dev(std_dev);
+
+        Some(centroids.confidence(Some(confidence)).polar())
+    }
+
+    /// Estimate the magnitude of the centroid (the vector mean), with a bootstrap interval.
+    pub fn centroid_interval(&self, params: &BootstrapParams) -> Option<Interval> {
+        params.interval(self, |data| data.centroid().points[0].r)
+    }
+
+    /// Estimate the mean magnitude (the mean of [`r`](crate::plots::PolarPoint::r), ignoring
+    /// direction), with a bootstrap interval.
+    pub fn mean_magnitude_interval(&self, params: &BootstrapParams) -> Option<Interval> {
+        params.interval(self, |data| {
+            mean(&data.points.iter().map(|point| point.r).collect::<Vec<_>>())
+        })
+    }
+}
+
+impl PredictionData {
+    /// Estimate the mean prediction error, with a bootstrap interval.
+    pub fn mean_error_interval(&self, params: &BootstrapParams) -> Option<Interval> {
+        params.interval(self, |data| mean(&data.errors))
+    }
+
+    /// Estimate the mean absolute prediction error, with a bootstrap interval.
+    pub fn mean_absolute_error_interval(&self, params: &BootstrapParams) -> Option<Interval> {
+        params.interval(self, |data| mean(&data.absolute_errors()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plots::PolarPoint;
+
+    fn polar_data(count: usize) -> PolarData {
+        (0..count)
+            .map(|i| PolarPoint {
+                r: 0.5 + (i % 5) as f64 * 0.25,
+                theta: (i * 37 % 360) as f64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seeded_replicates_are_reproducible() {
+        let data = polar_data(20);
+        let params = BootstrapParams::new().seed(42);
+
+        assert_eq!(
+            params.centroid_interval(&data),
+            params.centroid_interval(&data)
+        );
+        assert_ne!(
+            params.centroid_interval(&data),
+            params.clone().seed(43).centroid_interval(&data)
+        );
+    }
+
+    #[test]
+    fn interval_contains_estimate() {
+        let data = (0..50)
+            .map(|i| f64::from(i % 7) * 0.25 - 0.75)
+            .collect::<PredictionData>();
+
+        let Interval {
+            estimate,
+            lower,
+            upper,
+        } = data.mean_error_interval(&BootstrapParams::new()).unwrap();
+
+        assert!(lower < estimate && estimate < upper);
+        assert!(
+            PredictionData { errors: vec![] }
+                .mean_error_interval(&BootstrapParams::new())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn mean_confidence_shrinks_with_more_cases() {
+        let params = BootstrapParams::new().iterations(Iterations::new(500).unwrap());
+
+        let spread = |data: &PolarData| {
+            let (x, _) = data.cartesian().split_axes();
+            let (min, max) = x.iter().fold((f64::MAX, f64::MIN), |(min, max), x| {
+                (min.min(*x), max.max(*x))
+            });
+
+            max - min
+        };
+
+        let small = polar_data(12).mean_confidence(&params).unwrap();
+        let large = polar_data(600).mean_confidence(&params).unwrap();
+        let tolerance = polar_data(600).confidence(None);
+
+        assert!(spread(&large) < spread(&small));
+        assert!(spread(&large) < spread(&tolerance));
+        assert!(polar_data(2).mean_confidence(&params).is_none());
+    }
+}
@@ -1,11 +1,18 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
 use plotly::Configuration;
 use plotly::Layout;
 use plotly::Plot;
 use plotly::Scatter;
 use plotly::common::Anchor;
+use plotly::common::DashType;
+use plotly::common::ErrorData;
+use plotly::common::ErrorType;
 use plotly::common::Font;
 use plotly::common::HoverInfo;
 use plotly::common::LegendGroupTitle;
+use plotly::common::Line;
 use plotly::common::Marker;
 use plotly::common::Mode;
 use plotly::common::Orientation;
@@ -19,13 +26,18 @@ use plotly::layout::TraceOrder;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::bounded::Bounded;
 use crate::plots::AsPlot;
+use crate::plots::ConfidenceParams;
 use crate::plots::Polar;
 use crate::plots::PolarData;
 use crate::plots::PolarPoint;
+use crate::plots::Variance;
 use crate::plots::mean;
 use crate::plots::radians_to_degrees;
 use crate::plots::theta_radians;
+use crate::plots::variance;
+use crate::theme::PlotTheme;
 
 /// Convert a polar dataset to a cartesian dataset.
 pub trait Cartesian {
@@ -43,14 +55,7 @@ pub struct CartesianCompare {
 }
 
 impl AsPlot for CartesianCompare {
-    fn plot(&self) -> Plot {
-        #[allow(clippy::empty_line_after_doc_comments)]
-        /// Place values into bins of 0.25 D, and return a vec of bin/mean pairs, where x is the
-        /// middle of the bin.
-        // fn bins(data: &CartesianData) -> CartesianData {
-        //     todo!()
-        // }
-
+    fn plot(&self, theme: &PlotTheme) -> Plot {
         /// Create custom hover labels for the plot.
         fn labels(data: &CartesianData) -> Vec<String> {
             data.points
@@ -59,44 +64,93 @@ impl AsPlot for CartesianCompare {
                 .collect()
         }
 
-        // TODO: set these as constants app-wide, adapt for light mode, and use in all plots
-        // (see plots/polar.rs as well)
-        #[allow(unused_variables)]
-        let cohort_centroid_marker_color = "#f5f5f6";
-        #[allow(unused_variables)]
-        let cohort_confidence_color = "#848998";
-        let cohort_marker_color = "#848998";
-        let grid_color = "#363a48";
-        let label_color = "#eaebed";
-        let legend_font_color = "#caccd1";
-        let legend_group_font_color = "#eaebed";
-        let paper_background_color = "#252833";
-        #[allow(unused_variables)]
-        let surgeon_centroid_marker_color = "#00f115";
-        #[allow(unused_variables)]
-        let surgeon_confidence_color = "#f100dc";
-        let surgeon_marker_color = "#ff7b00";
-        #[allow(unused_variables)]
-        let tick_color = "#acafb9";
+        /// Create a marker trace for the mean of each 0.25 D bin, with error bars of ±1 SD.
+        fn bin_trace(data: &CartesianData, color: &str) -> Box<Scatter<f64, f64>> {
+            let bins = data.bins(0.25);
+
+            let labels = bins
+                .iter()
+                .map(
+                    |Bin {
+                         x,
+                         mean,
+                         std_dev,
+                         count,
+                     }| {
+                        format!("Pre: {x:.2} D, Post: {mean:.2} ± {std_dev:.2} D (n = {count})")
+                    },
+                )
+                .collect();
+
+            let (x, (y, std_dev)) = bins
+                .into_iter()
+                .map(
+                    |Bin {
+                         x, mean, std_dev, ..
+                     }| (x, (mean, std_dev)),
+                )
+                .unzip();
+
+            Scatter::new(x, y)
+                .mode(Mode::Markers)
+                .marker(Marker::new().color(color).size(10))
+                .error_y(ErrorData::new(ErrorType::Data).array(std_dev).color(color))
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(labels)
+        }
+
+        /// Create a line trace for the least-squares regression, across the range of the data.
+        fn regression_trace(data: &CartesianData, color: &str) -> Option<Box<Scatter<f64, f64>>> {
+            let regression = data.regression()?;
+            let (x, _) = data.split_axes();
+            let (min, max) = x.iter().fold((f64::MAX, f64::MIN), |(min, max), x| {
+                (min.min(*x), max.max(*x))
+            });
+
+            let trace = Scatter::new(vec![min, max], vec![regression.y(min), regression.y(max)])
+                .mode(Mode::Lines)
+                .line(Line::new().color(color).width(1.5).dash(DashType::Dash))
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(vec![regression.to_string(); 2]);
+
+            Some(trace)
+        }
+
+        /// Create a line trace for the confidence ellipse, if there are enough points to estimate
+        /// one.
+        fn ellipse_trace(data: &CartesianData, color: &str) -> Option<Box<Scatter<f64, f64>>> {
+            if data.points.len() < 3 {
+                return None;
+            }
+
+            let trace = data
+                .confidence(Some(ConfidenceParams::new().variance(Variance::Sample)))
+                .scatter()
+                .mode(Mode::Lines)
+                .line(Line::new().color(color).width(1.5))
+                .hover_info(HoverInfo::Skip);
+
+            Some(trace)
+        }
 
         let Self { surgeon, cohort } = self;
 
-        let surgeon = surgeon
+        let surgeon_cases = surgeon
             .scatter()
             .name("cases")
             .legend_group("surgeon")
             .legend_group_title(
                 LegendGroupTitle::new()
                     .text("Surgeon")
-                    .font(Font::new().color(legend_group_font_color)),
+                    .font(Font::new().color(theme.label)),
             )
             .mode(Mode::Markers)
-            .marker(Marker::new().color(surgeon_marker_color))
+            .marker(Marker::new().color(theme.surgeon))
             // .hover_template(hover_template);
             .hover_info(HoverInfo::Text)
             .hover_text_array(labels(surgeon));
 
-        let cohort = cohort
+        let cohort_cases = cohort
             .scatter()
             .name("cases")
             .legend_group("cohort")
@@ -105,14 +159,45 @@ impl AsPlot for CartesianCompare {
                     // Hack: adding spaces to the name because Plotly doesn't have horizontal group
                     // spacing.
                     .text("Peer cohort    ")
-                    .font(Font::new().color(legend_group_font_color)),
+                    .font(Font::new().color(theme.label)),
             )
             .mode(Mode::Markers)
-            .marker(Marker::new().color(cohort_marker_color))
+            .marker(Marker::new().color(theme.cohort))
             .opacity(0.4)
             .hover_info(HoverInfo::Skip);
 
-        // TODO: add plots for 0.25 D bins using the centroid colors
+        let surgeon_bins = bin_trace(surgeon, theme.surgeon_centroid)
+            .name("0.25 D bins")
+            .legend_group("surgeon");
+
+        let cohort_bins = bin_trace(cohort, theme.cohort_centroid)
+            .name("0.25 D bins")
+            .legend_group("cohort");
+
+        let fitted = [
+            (
+                regression_trace(surgeon, theme.surgeon_centroid),
+                "regression",
+                "surgeon",
+            ),
+            (
+                ellipse_trace(surgeon, theme.surgeon_confidence),
+                "confidence",
+                "surgeon",
+            ),
+            (
+                regression_trace(cohort, theme.cohort_centroid),
+                "regression",
+                "cohort",
+            ),
+            (
+                ellipse_trace(cohort, theme.cohort),
+                "confidence    ",
+                "cohort",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(trace, name, group)| Some(trace?.name(name).legend_group(group)));
 
         let mut plot = Plot::new();
 
@@ -133,7 +218,11 @@ impl AsPlot for CartesianCompare {
                 ),
         );
 
-        plot.add_traces(vec![cohort, surgeon]);
+        plot.add_traces(vec![cohort_cases, surgeon_cases, cohort_bins, surgeon_bins]);
+
+        for trace in fitted {
+            plot.add_trace(trace);
+        }
 
         let x_axis = Axis::new()
             .title(
@@ -145,29 +234,29 @@ impl AsPlot for CartesianCompare {
                     .y_anchor(Anchor::Bottom)
                     .y(0.0),
             )
-            .color(label_color)
+            .color(theme.label)
             .show_line(false)
             .zero_line(false)
-            .grid_color(grid_color);
+            .grid_color(theme.grid);
 
         let y_axis = Axis::new()
             .title("Postop refractive astigmatism (vertexed D)")
-            .color(label_color)
+            .color(theme.label)
             .show_line(false)
             .zero_line(false)
-            .grid_color(grid_color);
+            .grid_color(theme.grid);
 
         let layout = Layout::new()
             // .height(600)
             // .width(600)
             .x_axis(x_axis)
             .y_axis(y_axis)
-            .paper_background_color(paper_background_color)
-            .plot_background_color(paper_background_color)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
             .margin(Margin::new().top(30).right(5).bottom(0).left(50))
             .legend(
                 Legend::new()
-                    .font(Font::new().color(legend_font_color))
+                    .font(Font::new().color(theme.legend))
                     .trace_order(TraceOrder::Grouped)
                     .orientation(Orientation::Horizontal)
                     .x_anchor(Anchor::Center)
@@ -182,9 +271,6 @@ impl AsPlot for CartesianCompare {
     }
 }
 
-// TODO: consider creating a cartesian confidence and associated method for the ellipse, like you
-// did with the polar plot.
-
 /// A cartesian dataset, with values along the [`x`](CartesianPoint::x) and [`y`](CartesianPoint::y)
 /// axes.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -225,6 +311,187 @@ impl CartesianData {
     #[expect(unused)]
     fn new() -> Self { Self { points: Vec::new() } }
 
+    // This function uses the method
+    // [described by Carsten Schelp](https://carstenschelp.github.io/2018/09/14/Plot_Confidence_Ellipse_001.html),
+    // but requires extra steps, as we rotate the ellipse in polar coordinates, and we need to
+    // manually generate points for our ellipse (Carsten's
+    // method relies on built-in Matplotlib functionality to draw the ellipse).
+    //
+    // Python implementations of Carsten's method can be found at:
+    //
+    // https://matplotlib.org/stable/gallery/statistics/confidence_ellipse.html#sphx-glr-gallery-statistics-confidence-ellipse-py
+    //
+    // and
+    //
+    // https://gist.github.com/CarstenSchelp/b992645537660bda692f218b562d0712
+    //
+    /// Generate an ellipse encompassing the points within a given number of standard deviations.
+    /// This function calculates a confidence ellipse, which is more closely related to a tolerance
+    /// interval (a range where most values fall) than a confidence interval (a range likely to
+    /// contain the mean).
+    pub fn confidence(&self, params: Option<ConfidenceParams>) -> CartesianData {
+        let ConfidenceParams {
+            variance: params_variance,
+            std_dev,
+            step,
+        } = params.unwrap_or_default();
+
+        let (x, y) = self.split_axes();
+        let (mean_x, mean_y) = (mean(&x), mean(&y));
+
+        let (variance_x, variance_y) = (
+            variance(&x, params_variance.clone()),
+            variance(&y, params_variance.clone()),
+        );
+
+        // Rounding can push a perfect correlation just past ±1, where the radii are undefined.
+        let pearson = self.correlation(params_variance).clamp(-1.0, 1.0);
+        let radius_x = f64::sqrt(1.0 + pearson);
+        let radius_y = f64::sqrt(1.0 - pearson);
+        let (lower_x, upper_x) = (0.0 - radius_x, 0.0 + radius_x);
+        let mut points = Vec::<CartesianPoint>::new();
+        points.push(CartesianPoint { x: lower_x, y: 0.0 });
+        points.push(CartesianPoint { x: upper_x, y: 0.0 });
+        // Changing the size of the step will adjust the smoothness of the ellipse.
+        let mut current_x = lower_x + step.inner();
+
+        // In cartesian coordinates, solving for y, our ellipse equation is:
+        //
+        // y = +/- (b / a)(sqrt(a^2 - x^2))
+        //
+        // For each value of x, generate a positive and a negative value for y:
+        while current_x < upper_x {
+            let positive_y = (radius_y / radius_x)
+                * (f64::sqrt((radius_x * radius_x) - (current_x * current_x)));
+
+            let negative_y = -positive_y;
+
+            points.push(CartesianPoint {
+                x: current_x,
+                y: positive_y,
+            });
+
+            points.push(CartesianPoint {
+                x: current_x,
+                y: negative_y,
+            });
+
+            current_x += step.inner();
+        }
+
+        // It's essential to sort the ellipse in theta order before transforming it, while it still
+        // surrounds the origin. That way the lines connecting each point will still draw the
+        // outside of the ellipse, rather than crossing it.
+        let mut ellipse = CartesianData { points }.polar().theta_sort();
+        // We duplicate the first point in the ellipse at the end, to ensure that our ellipse
+        // is fully closed.
+        ellipse.points.push(ellipse.points[0].clone());
+
+        // The standard deviation is the square root of the variance, and we multiply that by the
+        // number of standard deviations we want our ellipse to cover.
+        let scale = Scale {
+            x: f64::sqrt(variance_x) * std_dev.inner(),
+            y: f64::sqrt(variance_y) * std_dev.inner(),
+        };
+
+        let translate = Translate {
+            x: mean_x,
+            y: mean_y,
+        };
+
+        ellipse
+            .rotate(45.0)
+            .cartesian()
+            .scale(&scale)
+            .translate(&translate)
+    }
+
+    /// Return the Pearson correlation coefficient of the points, with the covariance scaled to
+    /// match the given [`Variance`].
+    fn correlation(&self, params_variance: Variance) -> f64 {
+        let (x, y) = self.split_axes();
+
+        // The covariance is always a sample covariance, so it must be rescaled to match a
+        // population variance, or the correlation coefficient can exceed 1 for small datasets.
+        let covariance_xy = match params_variance {
+            Variance::Population => {
+                let n = self.points.len() as f64;
+
+                self.covariance() * (n - 1.0) / n
+            }
+            Variance::Sample => self.covariance(),
+        };
+
+        covariance_xy
+            / f64::sqrt(variance(&x, params_variance.clone()) * variance(&y, params_variance))
+    }
+
+    /// Place the points into bins of the given width along the [`x`](CartesianPoint::x) axis, and
+    /// return the mean and sample standard deviation of [`y`](CartesianPoint::y) for each
+    /// nonempty bin, in order of x. The x value of each [`Bin`] is the middle of the bin.
+    pub fn bins(&self, width: f64) -> Vec<Bin> {
+        let bins = self.points.iter().fold(
+            BTreeMap::<i64, Vec<f64>>::new(),
+            |mut bins, CartesianPoint { x, y }| {
+                bins.entry((x / width).floor() as i64).or_default().push(*y);
+
+                bins
+            },
+        );
+
+        bins.into_iter()
+            .map(|(index, y)| Bin {
+                x: (index as f64 + 0.5) * width,
+                mean: mean(&y),
+                std_dev: if y.len() < 2 {
+                    0.0
+                } else {
+                    f64::sqrt(variance(&y, Variance::Sample))
+                },
+                count: y.len(),
+            })
+            .collect()
+    }
+
+    /// Fit a least-squares regression line of [`y`](CartesianPoint::y) on
+    /// [`x`](CartesianPoint::x). Returns [`None`] if there are fewer than 2 points, or if all
+    /// points have the same x value.
+    pub fn regression(&self) -> Option<Regression> {
+        if self.points.len() < 2 {
+            return None;
+        }
+
+        let (x, y) = self.split_axes();
+        let (mean_x, mean_y) = (mean(&x), mean(&y));
+
+        let (sum_xy, sum_xx, sum_yy) = self.points.iter().fold(
+            (0.0, 0.0, 0.0),
+            |(sum_xy, sum_xx, sum_yy), CartesianPoint { x, y }| {
+                let (dx, dy) = (x - mean_x, y - mean_y);
+
+                (sum_xy + (dx * dy), sum_xx + (dx * dx), sum_yy + (dy * dy))
+            },
+        );
+
+        if sum_xx == 0.0 {
+            return None;
+        }
+
+        let slope = sum_xy / sum_xx;
+
+        let r_squared = if sum_yy == 0.0 {
+            1.0
+        } else {
+            (sum_xy * sum_xy) / (sum_xx * sum_yy)
+        };
+
+        Some(Regression {
+            slope,
+            intercept: mean_y - (slope * mean_x),
+            r_squared,
+        })
+    }
+
     // Eventually I think you separate this out into a trait Covariance: Variance: Mean (or some
     // arrangement like this), and then functions like centroid could be generic over that.
     //
@@ -306,6 +573,43 @@ impl Polar for CartesianPoint {
     }
 }
 
+/// The [`y`](CartesianPoint::y) values of the points within a single bin of
+/// [`x`](CartesianPoint::x) values, summarized by their mean and sample standard deviation.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Bin {
+    /// The middle of the bin.
+    pub x: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub count: usize,
+}
+
+/// A least-squares regression line, where y = (slope)(x) + intercept.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Regression {
+    pub slope: f64,
+    pub intercept: f64,
+    /// The coefficient of determination.
+    pub r_squared: f64,
+}
+
+impl Display for Regression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            slope,
+            intercept,
+            r_squared,
+        } = self;
+
+        write!(f, "y = {slope:.2}x {intercept:+.2} (R² = {r_squared:.2})")
+    }
+}
+
+impl Regression {
+    /// Return the fitted value of y for the given x.
+    pub fn y(&self, x: f64) -> f64 { (self.slope * x) + self.intercept }
+}
+
 /// A set of scale factors for growing or shrinking a cartesian plot along its axes.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Scale {
@@ -319,3 +623,96 @@ pub struct Translate {
     pub x: f64,
     pub y: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(points: &[(f64, f64)]) -> CartesianData {
+        points
+            .iter()
+            .map(|(x, y)| CartesianPoint { x: *x, y: *y })
+            .collect()
+    }
+
+    #[test]
+    fn bins_by_quarter_diopter() {
+        let bins = data(&[(0.1, 1.0), (0.2, 3.0), (0.3, 0.5), (1.1, 0.0)]).bins(0.25);
+
+        assert_eq!(bins.len(), 3);
+        assert_eq!((bins[0].x, bins[0].mean, bins[0].count), (0.125, 2.0, 2));
+        assert!((bins[0].std_dev - f64::sqrt(2.0)).abs() < 1e-9);
+        assert_eq!((bins[1].x, bins[1].std_dev), (0.375, 0.0));
+        assert_eq!(bins[2].x, 1.125);
+    }
+
+    #[test]
+    fn fits_regression_line() {
+        let regression = data(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)])
+            .regression()
+            .unwrap();
+
+        assert!((regression.slope - 2.0).abs() < 1e-9);
+        assert!((regression.intercept - 1.0).abs() < 1e-9);
+        assert!((regression.r_squared - 1.0).abs() < 1e-9);
+        assert!(data(&[(1.0, 0.0), (1.0, 2.0)]).regression().is_none());
+    }
+
+    #[test]
+    fn confidence_ellipse_surrounds_mean() {
+        let data = data(&[(0.0, 0.0), (1.0, 0.5), (2.0, 2.0), (3.0, 2.5)]);
+        let ellipse = data.confidence(None);
+        let (x, y) = ellipse.split_axes();
+
+        assert!((mean(&x) - 1.5).abs() < 0.1);
+        assert!((mean(&y) - 1.25).abs() < 0.1);
+        assert_eq!(ellipse.points.first(), ellipse.points.last());
+    }
+
+    #[test]
+    fn keeps_correlation_within_bounds_for_small_datasets() {
+        // Perfectly correlated, so a sample covariance over population variances would give
+        // n / (n - 1) = 1.5.
+        let collinear = data(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+
+        for params_variance in [Variance::Population, Variance::Sample] {
+            let correlation = collinear.correlation(params_variance);
+            assert!((correlation - 1.0).abs() < 1e-9, "{correlation}");
+        }
+
+        let anticorrelated = data(&[(0.0, 2.0), (1.0, 1.0), (2.0, 0.0)]);
+        let correlation = anticorrelated.correlation(Variance::Population);
+        assert!((correlation + 1.0).abs() < 1e-9, "{correlation}");
+
+        let ellipse = collinear.confidence(None);
+        assert!(
+            ellipse
+                .points
+                .iter()
+                .all(|point| point.x.is_finite() && point.y.is_finite())
+        );
+    }
+
+    #[test]
+    fn scales_ellipse_to_population_covariance() {
+        let data = data(&[(0.0, 0.0), (1.0, 0.5), (2.0, 2.0), (3.0, 2.5)]);
+
+        // Hand-computed population (co)variances, about the mean of (1.5, 1.25):
+        // var(x) = 5 / 4, var(y) = 4.25 / 4, and cov(x, y) = 4.5 / 4.
+        let (var_x, var_y, cov_xy) = (1.25, 1.0625, 1.125);
+        let det = (var_x * var_y) - (cov_xy * cov_xy);
+
+        // Every point on a 2 SD ellipse has a Mahalanobis distance of 2 from the mean.
+        let ellipse = data.confidence(Some(ConfidenceParams::new().variance(Variance::Population)));
+
+        for CartesianPoint { x, y } in &ellipse.points {
+            let (dx, dy) = (x - 1.5, y - 1.25);
+            let distance = ((var_y * dx * dx) - (2.0 * cov_xy * dx * dy) + (var_x * dy * dy)) / det;
+
+            assert!(
+                (distance.sqrt() - 2.0).abs() < 1e-6,
+                "({x}, {y}): {distance}"
+            );
+        }
+    }
+}
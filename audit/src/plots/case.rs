@@ -1,43 +1,84 @@
+use gel_derive::Queryable;
 use gel_tokio::Client;
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::bounded::Bounded;
-use crate::db::db;
 use crate::error::AppError;
 use crate::model::Case;
+use crate::model::Cohort;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::Ks;
+use crate::model::OpIol;
+use crate::model::PowerVector;
 use crate::model::RefCyl;
 use crate::model::Refraction;
+use crate::model::Side;
 use crate::model::SurgeonCase;
+use crate::model::SurgeonSia;
 use crate::model::Target;
 use crate::model::TargetCyl;
 use crate::model::Year;
+use crate::plots::AcuityCompare;
+use crate::plots::AdverseCompare;
+use crate::plots::Alpins;
+use crate::plots::AlpinsCompare;
 use crate::plots::Cartesian;
 use crate::plots::CartesianCompare;
 use crate::plots::CartesianPoint;
+use crate::plots::IolToCorneal;
 use crate::plots::Polar;
 use crate::plots::PolarCompare;
+use crate::plots::PolarData;
 use crate::plots::PolarPoint;
+use crate::plots::PowerVectorCompare;
+use crate::plots::PredictionCompare;
+use crate::plots::SiaCompare;
+use crate::plots::SiaData;
+use crate::plots::ToricConversion;
 use crate::plots::VertexK;
+use crate::query::QuerySurgeon;
 use crate::query::query_select_compare;
+use crate::query::query_select_range_compare;
 use crate::query::query_select_self_compare;
-
-/// The reference group for plot comparisons. This is either the full cohort of surgeons
-/// participating (same year), or the current surgeon (prior year).
-#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
-pub enum Cohort {
-    #[default]
-    Peers,
-    Surgeon,
-}
+use crate::repository::repository;
 
 /// A pair of case datasets, representing the surgeon of interest and a comparison cohort of peers.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize, Deserialize)]
 pub struct CaseCompare {
     surgeon: Vec<SurgeonCase>,
     cohort: Vec<Case>,
 }
 
+/// Return the corneal cylinder for a set of [`Ks`] as a double-angle [`PolarPoint`], at the steep
+/// meridian.
+pub fn ks_double_angle(ks: &Ks) -> PolarPoint {
+    PolarPoint {
+        r: f64::from(ks.cyl()) / 100.0,
+        // We double the axis to create a double-angle plot.
+        theta: f64::from(ks.steep_axis()) * 2.0,
+    }
+}
+
+/// Calculate the measured SIA for a case, as the double-angle vector difference between the
+/// postoperative and preoperative keratometric cylinder. Returns [`None`] if there is no
+/// postoperative keratometry.
+pub fn measured_sia(case: &Case) -> Option<PolarPoint> {
+    let (before, after) = (
+        ks_double_angle(&case.biometry.ks).cartesian(),
+        ks_double_angle(case.ks_after.as_ref()?).cartesian(),
+    );
+
+    Some(
+        CartesianPoint {
+            x: after.x - before.x,
+            y: after.y - before.y,
+        }
+        .polar(),
+    )
+}
+
 pub fn ref_cyl_double_angle(case: &Case) -> PolarPoint {
     match case.refraction.after.cyl {
         None => PolarPoint { r: 0.0, theta: 0.0 },
@@ -78,18 +119,61 @@ pub fn ref_cyl_double_angle(case: &Case) -> PolarPoint {
     }
 }
 
+/// Calculate the spherical equivalent prediction error for a case, in diopters. Following the
+/// usual convention, this is the achieved postoperative spherical equivalent minus the predicted
+/// (target) spherical equivalent, so a positive value represents a hyperopic surprise.
+pub fn se_prediction_error(case: &Case) -> f64 {
+    let Refraction { sph, cyl } = case.refraction.after;
+    let cyl = cyl.map_or(0.0, |RefCyl { power, .. }| f64::from(power.inner()));
+    let achieved = (f64::from(sph.inner()) + (cyl / 2.0)) / 100.0;
+    let predicted = f64::from(case.target.se.inner()) / 100.0;
+
+    achieved - predicted
+}
+
+/// Return the astigmatic correction of a toric IOL at the corneal plane, as a double-angle
+/// [`PolarPoint`] in the same (steep meridian) convention as the preoperative corneal cylinder.
+/// Returns [`None`] if the IOL is nontoric, or no axis was recorded.
+pub fn toric_double_angle(case: &Case, conversion: &ToricConversion) -> Option<PolarPoint> {
+    let OpIol { iol, axis, .. } = &case.iol;
+    let (power, axis) = (iol.toric?, (*axis)?);
+
+    // The IOL axis is aligned with the steep corneal meridian, and the IOL adds power in the
+    // perpendicular meridian. When converting to the corneal steep meridian, we would normally
+    // add 90° % 180, but since we are working with doubled angles, we add 180° % 360.
+    Some(PolarPoint {
+        r: power.corneal(&case.biometry, conversion),
+        theta: ((f64::from(axis.inner()) * 2.0) + 180.0) % 360.0,
+    })
+}
+
+/// Perform an Alpins vector analysis for a case, using the preoperative corneal astigmatism, the
+/// target refractive astigmatism, and the postoperative refractive astigmatism, with refractive
+/// values vertexed to the corneal plane. A missing target cylinder is treated as a target of zero
+/// astigmatism. Returns [`None`] if the target induced astigmatism is zero.
+pub fn alpins(case: &Case) -> Option<Alpins> {
+    let preop = ks_double_angle(&case.biometry.ks);
+
+    // TargetCylPower is nonnegative, so the target is already in plus cyl notation.
+    let target = case.target.cyl.map_or(
+        PolarPoint { r: 0.0, theta: 0.0 },
+        |TargetCyl { power, axis }| PolarPoint {
+            r: power.vertex(),
+            theta: f64::from(axis.inner()) * 2.0,
+        },
+    );
+
+    let postop = ref_cyl_double_angle(case);
+
+    Alpins::new(&preop, &target, &postop)
+}
+
 impl CaseCompare {
+    pub fn new(surgeon: Vec<SurgeonCase>, cohort: Vec<Case>) -> Self { Self { surgeon, cohort } }
+
     /// Compare preoperative corneal cylinder values.
     pub fn polar_cyl_before(&self) -> PolarCompare {
-        fn k_cyl_double_angle(case: &Case) -> PolarPoint {
-            let ks = case.biometry.ks;
-
-            PolarPoint {
-                r: f64::from(ks.cyl()) / 100.0,
-                // We double the axis to create a double-angle plot.
-                theta: f64::from(ks.steep_axis()) * 2.0,
-            }
-        }
+        fn k_cyl_double_angle(case: &Case) -> PolarPoint { ks_double_angle(&case.biometry.ks) }
 
         let surgeon = self
             .surgeon
@@ -99,7 +183,25 @@ impl CaseCompare {
 
         let cohort = self.cohort.iter().map(k_cyl_double_angle).collect();
 
-        PolarCompare { surgeon, cohort }
+        PolarCompare::new(surgeon, cohort)
+    }
+
+    /// Compare toric IOL cylinder values, converted to the corneal plane with the given
+    /// [`ToricConversion`]. Nontoric cases are omitted.
+    pub fn polar_toric(&self, conversion: &ToricConversion) -> PolarCompare {
+        let surgeon = self
+            .surgeon
+            .iter()
+            .filter_map(|sc| toric_double_angle(&sc.case, conversion))
+            .collect();
+
+        let cohort = self
+            .cohort
+            .iter()
+            .filter_map(|case| toric_double_angle(case, conversion))
+            .collect();
+
+        PolarCompare::new(surgeon, cohort)
     }
 
     /// Compare postoperative refractive cylinder values, vertexed to the corneal plane.
@@ -112,7 +214,7 @@ impl CaseCompare {
 
         let cohort = self.cohort.iter().map(ref_cyl_double_angle).collect();
 
-        PolarCompare { surgeon, cohort }
+        PolarCompare::new(surgeon, cohort)
     }
 
     // TODO: Do we need an equivalent for SE or sph?
@@ -161,7 +263,7 @@ impl CaseCompare {
         let surgeon = surgeon.iter().map(|sc| delta_target(&sc.case)).collect();
         let cohort = cohort.iter().map(delta_target).collect();
 
-        PolarCompare { surgeon, cohort }
+        PolarCompare::new(surgeon, cohort)
     }
 
     /// Compare preoperative corneal cylinder and postoperative refractive cylinder (vertexed to the
@@ -198,35 +300,153 @@ impl CaseCompare {
 
         CartesianCompare { surgeon, cohort }
     }
-}
 
-// TODO: in future, you may want the ability to compare a specific date range for the Surgeon,
-// against either the cohort, or against the surgeon's own baseline (all other dates outside the
-// range).
-/// Query the database for cases from the given year.
-pub async fn get_compare(year: Year, cohort: Cohort) -> Result<CaseCompare, AppError> {
-    let client = db().await?;
+    /// Compare spherical equivalent prediction errors (achieved minus target).
+    pub fn prediction_error(&self) -> PredictionCompare {
+        let surgeon = self
+            .surgeon
+            .iter()
+            .map(|sc| se_prediction_error(&sc.case))
+            .collect();
+
+        let cohort = self.cohort.iter().map(se_prediction_error).collect();
+
+        PredictionCompare { surgeon, cohort }
+    }
+
+    /// Compare postoperative refractions as power vectors.
+    pub fn power_vectors(&self) -> PowerVectorCompare {
+        let surgeon = self
+            .surgeon
+            .iter()
+            .map(|sc| PowerVector::from(&sc.case.refraction.after))
+            .collect();
+
+        let cohort = self
+            .cohort
+            .iter()
+            .map(|case| PowerVector::from(&case.refraction.after))
+            .collect();
+
+        PowerVectorCompare { surgeon, cohort }
+    }
+
+    /// Compare the surgeon's measured SIA against their assumed SIA, split by eye side. Only the
+    /// surgeon's own cases with postoperative keratometry are included, so the cohort is unused.
+    pub fn measured_sia(&self, assumed: &SurgeonSia) -> SiaCompare {
+        let side = |side: Side| {
+            self.surgeon
+                .iter()
+                .filter(|sc| sc.case.side == side)
+                .filter_map(|sc| measured_sia(&sc.case))
+                .collect::<PolarData>()
+        };
+
+        SiaCompare {
+            right: SiaData::new(side(Side::Right), &assumed.right),
+            left: SiaData::new(side(Side::Left), &assumed.left),
+        }
+    }
+
+    /// Compare Alpins vector analyses. Cases with a target induced astigmatism of zero are
+    /// omitted.
+    pub fn alpins(&self) -> AlpinsCompare {
+        let surgeon = self
+            .surgeon
+            .iter()
+            .filter_map(|sc| alpins(&sc.case))
+            .collect();
+
+        let cohort = self.cohort.iter().filter_map(alpins).collect();
+
+        AlpinsCompare { surgeon, cohort }
+    }
+
+    /// Compare postoperative uncorrected visual acuity, and preoperative corrected visual acuity,
+    /// as logMAR values for cumulative plotting. Acuities with an undefined logMAR value (a zero
+    /// numerator) are omitted.
+    pub fn cumulative_va(&self) -> AcuityCompare {
+        let surgeon = self.surgeon.iter().map(|sc| &sc.case).collect();
+        let cohort = self.cohort.iter().collect();
+
+        AcuityCompare { surgeon, cohort }
+    }
+
+    /// Compare adverse event rates, by category.
+    pub fn adverse(&self) -> AdverseCompare {
+        let surgeon = self
+            .surgeon
+            .iter()
+            .map(|sc| sc.case.adverse.clone())
+            .collect();
+        let cohort = self
+            .cohort
+            .iter()
+            .map(|case| case.adverse.clone())
+            .collect();
+
+        AdverseCompare { surgeon, cohort }
+    }
+}
 
-    get_compare_with_client(&client, year, cohort).await
+/// Query the repository for cases from the given year, restricted to the subgroup in the
+/// [`CaseFilter`](crate::model::CaseFilter).
+pub async fn get_compare(params: &CompareParams<Year>) -> Result<CaseCompare, AppError> {
+    repository()?.compare(params).await
 }
 
 // Query the database for cases from the given year, using a custom [`gel_tokio::Client`]. Factoring
-// out this function provides a way to supply our own Client in tests. In prod, it is assumed that
-// our main function will run and the Client will be retrieved from the [`AppState`] in context.
+// out this function provides a way to supply our own Client in tests. In prod, it is called by the
+// [`GelRepository`](crate::repository::GelRepository) retrieved from the `AppState` in context.
 #[doc(hidden)]
 pub(crate) async fn get_compare_with_client(
     client: &Client,
-    year: Year,
-    cohort: Cohort,
+    params: &CompareParams<Year>,
 ) -> Result<CaseCompare, AppError> {
-    let query = match cohort {
-        Cohort::Peers => query_select_compare(year),
-        Cohort::Surgeon => query_select_self_compare(year),
+    let CompareParams {
+        period,
+        cohort,
+        filter,
+    } = params;
+
+    let (query, args) = match cohort {
+        Cohort::Peers => query_select_compare(*period, filter),
+        Cohort::Surgeon => query_select_self_compare(*period, filter),
     };
 
-    if let Some(query_result) = client.query_single_json(query, &()).await? {
-        let compare = serde_json::from_str::<CaseCompare>(query_result.as_ref())?;
+    if let Some(compare) = client.query_single(query, &args).await? {
+        Ok(compare)
+    } else {
+        Err(AppError::Db(
+            "the query for Compare was not successful".to_string(),
+        ))
+    }
+}
+
+/// Query the database for the surgeon's cases from the given [`DateRange`], compared with either
+/// the peer cohort from the same years, or the surgeon's own cases outside the range, restricted to
+/// the subgroup in the [`CaseFilter`](crate::model::CaseFilter).
+pub async fn get_range_compare(params: &CompareParams<DateRange>) -> Result<CaseCompare, AppError> {
+    repository()?.range_compare(params).await
+}
 
+// Query the database for the surgeon's cases from the given date range, using a custom
+// [`gel_tokio::Client`]. See [`get_compare_with_client`].
+#[doc(hidden)]
+pub(crate) async fn get_range_compare_with_client(
+    client: &Client,
+    params: &CompareParams<DateRange>,
+    surgeon: QuerySurgeon<'_>,
+) -> Result<CaseCompare, AppError> {
+    // The range may have been deserialized from a request, bypassing the check in `new()`.
+    let params = CompareParams {
+        period: DateRange::new(params.period.start(), params.period.end())?,
+        ..params.clone()
+    };
+
+    let (query, args) = query_select_range_compare(&params, surgeon);
+
+    if let Some(compare) = client.query_single(query, &args).await? {
         Ok(compare)
     } else {
         Err(AppError::Db(
@@ -234,3 +454,33 @@ pub(crate) async fn get_compare_with_client(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Axis;
+    use crate::model::K;
+    use crate::model::Kpower;
+
+    fn ks(flat: u32, steep: u32) -> Ks {
+        Ks::new(
+            K::new(Kpower::new(flat).unwrap(), Axis::new(0).unwrap()),
+            K::new(Kpower::new(steep).unwrap(), Axis::new(90).unwrap()),
+        )
+    }
+
+    #[test]
+    fn measures_sia_from_keratometric_change() {
+        let mut case = Case::default();
+        case.biometry.ks = ks(4300, 4400);
+
+        assert_eq!(measured_sia(&case), None);
+
+        // Reducing with-the-rule cylinder from 1.00 D to 0.50 D is a 0.50 D steepening at 0°.
+        case.ks_after = Some(ks(4350, 4400));
+        let PolarPoint { r, theta } = measured_sia(&case).unwrap();
+
+        assert!((r - 0.5).abs() < 1e-9);
+        assert!(theta.rem_euclid(360.0).min(360.0 - theta.rem_euclid(360.0)) < 1e-6);
+    }
+}
@@ -0,0 +1,138 @@
+use gel_tokio::Client;
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::Table;
+use plotly::common::Font;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::Margin;
+use plotly::traces::table::Cells;
+use plotly::traces::table::Fill;
+use plotly::traces::table::Header;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::model::SurgeonConstant;
+use crate::plots::AsPlot;
+use crate::query::query_select_surgeon_constants;
+use crate::query::query_upsert_surgeon_constant;
+use crate::repository::Repository;
+use crate::repository::repository;
+use crate::theme::PlotTheme;
+
+/// A table of the surgeon's optimized lens constants.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ConstantTable {
+    pub constants: Vec<SurgeonConstant>,
+}
+
+impl AsPlot for ConstantTable {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        let columns = self.constants.iter().fold(
+            vec![Vec::new(); 5],
+            |mut columns,
+             SurgeonConstant {
+                 iol,
+                 formula,
+                 constant,
+                 lower,
+                 upper,
+                 cases,
+             }| {
+                columns[0].push(iol.clone());
+                columns[1].push(format!("{formula:?}"));
+                columns[2].push(format!("{constant:.3}"));
+                columns[3].push(format!("{lower:.3} to {upper:.3}"));
+                columns[4].push(cases.to_string());
+
+                columns
+            },
+        );
+
+        let header = Header::new(vec!["IOL", "Formula", "Constant", "95% CI", "Cases"])
+            .fill(Fill::new().color(theme.grid))
+            .font(Font::new().color(theme.label));
+
+        let cells = Cells::new(columns)
+            .fill(Fill::new().color(theme.background))
+            .font(Font::new().color(theme.label));
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        plot.add_trace(Table::new(header, cells));
+
+        let layout = Layout::new()
+            .paper_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(5));
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+/// Query the repository for the current surgeon's stored lens constants.
+pub async fn get_surgeon_constants() -> Result<ConstantTable, AppError> {
+    let constants = repository()?.constants().await?;
+
+    Ok(ConstantTable { constants })
+}
+
+/// Optimize the current surgeon's lens constants from all of their cases, and store them in the
+/// repository, replacing any previous values. This is called whenever the surgeon adds a case, so
+/// that viewing the constants never writes to the repository. Returns all of the surgeon's stored
+/// constants.
+pub async fn update_surgeon_constants(
+    repository: &dyn Repository,
+) -> Result<Vec<SurgeonConstant>, AppError> {
+    let cases = repository.trend_cases().await?.surgeon;
+    let constants = SurgeonConstant::from_cases(cases.iter().map(|sc| &sc.case));
+
+    repository.upsert_constants(constants).await
+}
+
+// Query the database for the current surgeon's stored constants, using a custom
+// [`gel_tokio::Client`]. See [`get_compare_with_client`](crate::plots::get_compare_with_client).
+#[doc(hidden)]
+pub(crate) async fn get_surgeon_constants_with_client(
+    client: &Client,
+) -> Result<Vec<SurgeonConstant>, AppError> {
+    let constants = client.query(query_select_surgeon_constants(), &()).await?;
+
+    Ok(constants)
+}
+
+// Store constants, replacing any previous values for the same IOL and formula, using a custom
+// [`gel_tokio::Client`]. See [`get_compare_with_client`](crate::plots::get_compare_with_client).
+// The constants are stored in a single transaction, so the surgeon never sees a partial update.
+#[doc(hidden)]
+pub(crate) async fn upsert_surgeon_constants_with_client(
+    client: &Client,
+    constants: &[SurgeonConstant],
+) -> Result<Vec<SurgeonConstant>, AppError> {
+    client
+        .transaction(|mut tx| async move {
+            for constant in constants {
+                let (query, args) = query_upsert_surgeon_constant(constant);
+
+                tx.execute(query, &args).await?;
+            }
+
+            Ok(())
+        })
+        .await?;
+
+    get_surgeon_constants_with_client(client).await
+}
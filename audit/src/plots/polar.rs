@@ -5,6 +5,7 @@ use plotly::Layout;
 use plotly::Plot;
 use plotly::ScatterPolar;
 use plotly::common::Anchor;
+use plotly::common::DashType;
 use plotly::common::Font;
 use plotly::common::HoverInfo;
 use plotly::common::LegendGroupTitle;
@@ -26,19 +27,17 @@ use plotly::layout::TraceOrder;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::bounded::Bounded;
 use crate::plots::AsPlot;
+use crate::plots::BootstrapParams;
 use crate::plots::Cartesian;
 use crate::plots::CartesianData;
 use crate::plots::CartesianPoint;
 use crate::plots::ConfidenceParams;
-use crate::plots::Scale;
-use crate::plots::Translate;
 use crate::plots::degrees_to_radians;
 use crate::plots::mean;
 use crate::plots::radians_to_degrees;
 use crate::plots::theta_radians;
-use crate::plots::variance;
+use crate::theme::PlotTheme;
 
 /// Convert a cartesian dataset to a polar dataset.
 pub trait Polar {
@@ -53,10 +52,30 @@ pub trait Polar {
 pub struct PolarCompare {
     pub surgeon: PolarData,
     pub cohort: PolarData,
+    /// The parameters for bootstrapping a confidence ellipse around each centroid. Bootstrapping
+    /// resamples each dataset many times, so the ellipses are only drawn when this is set.
+    #[serde(skip)]
+    pub bootstrap: Option<BootstrapParams>,
+}
+
+impl PolarCompare {
+    pub fn new(surgeon: PolarData, cohort: PolarData) -> Self {
+        Self {
+            surgeon,
+            cohort,
+            bootstrap: None,
+        }
+    }
+
+    pub fn bootstrap(mut self, bootstrap: BootstrapParams) -> Self {
+        self.bootstrap = Some(bootstrap);
+
+        self
+    }
 }
 
 impl AsPlot for PolarCompare {
-    fn plot(&self) -> Plot {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
         /// Create custom hover labels for double-angle plots.
         fn labels(data: &PolarData) -> Vec<String> {
             data.points
@@ -65,7 +84,11 @@ impl AsPlot for PolarCompare {
                 .collect()
         }
 
-        let Self { surgeon, cohort } = self;
+        let Self {
+            surgeon,
+            cohort,
+            bootstrap,
+        } = self;
         let (surgeon_centroid, cohort_centroid) = (surgeon.centroid(), cohort.centroid());
 
         let (surgeon_labels, surgeon_centroid_labels, cohort_centroid_labels) = (
@@ -77,19 +100,13 @@ impl AsPlot for PolarCompare {
         let surgeon_ellipse = surgeon.confidence(None);
         let cohort_ellipse = cohort.confidence(None);
 
-        // TODO: set these as constants app-wide, adapt for light mode, and use in all plots
-        let cohort_centroid_marker_color = "#f5f5f6";
-        let cohort_confidence_color = "#848998";
-        let cohort_marker_color = "#848998";
-        let grid_color = "#363a48";
-        let label_color = "#eaebed";
-        let legend_font_color = "#caccd1";
-        let legend_group_font_color = "#eaebed";
-        let paper_background_color = "#252833";
-        let surgeon_centroid_marker_color = "#00f115";
-        let surgeon_confidence_color = "#f100dc";
-        let surgeon_marker_color = "#ff7b00";
-        let tick_color = "#acafb9";
+        let (surgeon_mean_ellipse, cohort_mean_ellipse) = match bootstrap {
+            Some(bootstrap) => (
+                surgeon.mean_confidence(bootstrap),
+                cohort.mean_confidence(bootstrap),
+            ),
+            None => (None, None),
+        };
 
         // These format strings use d3 format syntax:
         // https://d3js.org/d3-format
@@ -104,10 +121,10 @@ impl AsPlot for PolarCompare {
             .legend_group_title(
                 LegendGroupTitle::new()
                     .text("Surgeon")
-                    .font(Font::new().color(legend_group_font_color)),
+                    .font(Font::new().color(theme.label)),
             )
             .mode(Mode::Markers)
-            .marker(Marker::new().color(surgeon_marker_color))
+            .marker(Marker::new().color(theme.surgeon))
             // .hover_template(hover_template);
             .hover_info(HoverInfo::Text)
             .hover_text_array(surgeon_labels);
@@ -119,10 +136,10 @@ impl AsPlot for PolarCompare {
             .legend_group_title(
                 LegendGroupTitle::new()
                     .text("Peer cohort")
-                    .font(Font::new().color(legend_group_font_color)),
+                    .font(Font::new().color(theme.label)),
             )
             .mode(Mode::Markers)
-            .marker(Marker::new().color(cohort_marker_color))
+            .marker(Marker::new().color(theme.cohort))
             .opacity(0.4)
             .hover_info(HoverInfo::Skip);
 
@@ -131,7 +148,7 @@ impl AsPlot for PolarCompare {
             .name("centroid")
             .legend_group("surgeon")
             .mode(Mode::Markers)
-            .marker(Marker::new().color(surgeon_centroid_marker_color).size(10))
+            .marker(Marker::new().color(theme.surgeon_centroid).size(10))
             .hover_info(HoverInfo::Text)
             .hover_text_array(surgeon_centroid_labels);
 
@@ -140,7 +157,7 @@ impl AsPlot for PolarCompare {
             .name("centroid")
             .legend_group("cohort")
             .mode(Mode::Markers)
-            .marker(Marker::new().color(cohort_centroid_marker_color).size(10))
+            .marker(Marker::new().color(theme.cohort_centroid).size(10))
             .hover_info(HoverInfo::Text)
             .hover_text_array(cohort_centroid_labels);
 
@@ -149,7 +166,7 @@ impl AsPlot for PolarCompare {
             .name("confidence")
             .legend_group("surgeon")
             .mode(Mode::Lines)
-            .line(Line::new().color(surgeon_confidence_color).width(1.5))
+            .line(Line::new().color(theme.surgeon_confidence).width(1.5))
             .hover_info(HoverInfo::Skip);
 
         let cohort_ellipse = cohort_ellipse
@@ -158,7 +175,7 @@ impl AsPlot for PolarCompare {
             .name("confidence    ")
             .legend_group("cohort")
             .mode(Mode::Lines)
-            .line(Line::new().color(cohort_confidence_color).width(1.5))
+            .line(Line::new().color(theme.cohort).width(1.5))
             .opacity(0.7)
             .hover_info(HoverInfo::Skip);
 
@@ -190,7 +207,30 @@ impl AsPlot for PolarCompare {
             surgeon_ellipse,
         ]);
 
-        let radial_ticks = PolarAxisTicks::new().tick_color(tick_color);
+        // The centroid confidence ellipses are only drawn when requested, and when there are enough
+        // cases to resample.
+        let mean_ellipses = [
+            (surgeon_mean_ellipse, "surgeon", theme.surgeon_centroid),
+            (cohort_mean_ellipse, "cohort", theme.cohort_centroid),
+        ]
+        .into_iter()
+        .filter_map(|(ellipse, group, color)| {
+            let trace = ellipse?
+                .scatter_polar()
+                .name("centroid 95% CI")
+                .legend_group(group)
+                .mode(Mode::Lines)
+                .line(Line::new().color(color).width(1.5).dash(DashType::Dot))
+                .hover_info(HoverInfo::Skip);
+
+            Some(trace)
+        });
+
+        for trace in mean_ellipses {
+            plot.add_trace(trace);
+        }
+
+        let radial_ticks = PolarAxisTicks::new().tick_color(theme.tick);
 
         let angular_ticks = PolarAxisTicks::new()
             .tick_mode(PolarTickMode::Array {
@@ -207,33 +247,31 @@ impl AsPlot for PolarCompare {
                     "".to_string(),
                 ]),
             })
-            .tick_color(tick_color);
+            .tick_color(theme.tick);
 
         let axis_attributes = PolarAxisAttributes::new()
-            .color(label_color)
+            .color(theme.label)
             .show_line(false)
-            .grid_color(grid_color);
+            .grid_color(theme.grid);
 
         let radial_axis_attributes = axis_attributes.clone().ticks(radial_ticks);
         let angular_axis_attributes = axis_attributes.ticks(angular_ticks);
         let radial_axis = RadialAxis::new().axis_attributes(radial_axis_attributes.clone());
         let angular_axis = AngularAxis::new().axis_attributes(angular_axis_attributes);
 
-        // TODO: use a signal on the plot container to set the colors for light/dark mode.
-        // Find a way to do this with CSS variables (not natively available in Plotly).
         let polar_layout = LayoutPolar::new()
-            .bg_color("#252833")
+            .bg_color(theme.background)
             .radial_axis(radial_axis)
             .angular_axis(angular_axis);
 
         let layout = Layout::new()
             .auto_size(true)
-            .paper_background_color(paper_background_color)
+            .paper_background_color(theme.background)
             .polar(polar_layout)
             .margin(Margin::new().top(30).right(30).bottom(0).left(30))
             .legend(
                 Legend::new()
-                    .font(Font::new().color(legend_font_color))
+                    .font(Font::new().color(theme.legend))
                     .trace_order(TraceOrder::Grouped)
                     .orientation(Orientation::Horizontal)
                     .x_anchor(Anchor::Center)
@@ -350,101 +388,11 @@ impl PolarData {
         self
     }
 
-    // This function uses the method
-    // [described by Carsten Schelp](https://carstenschelp.github.io/2018/09/14/Plot_Confidence_Ellipse_001.html),
-    // but requires extra steps, as we need to convert between polar and cartesian coordinates at
-    // the appropriate steps, and we need to manually generate points for our ellipse (Carsten's
-    // method relies on built-in Matplotlib functionality to draw the ellipse).
-    //
-    // Python implementations of Carsten's method can be found at:
-    //
-    // https://matplotlib.org/stable/gallery/statistics/confidence_ellipse.html#sphx-glr-gallery-statistics-confidence-ellipse-py
-    //
-    // and
-    //
-    // https://gist.github.com/CarstenSchelp/b992645537660bda692f218b562d0712
-    //
     /// Generate an ellipse encompassing the points within a given number of standard deviations.
-    /// This function calculates a confidence ellipse, which is more closely related to a tolerance
-    /// interval (a range where most values fall) than a confidence interval (a range likely to
-    /// contain the mean).
+    /// The ellipse is calculated in cartesian coordinates (see [`CartesianData::confidence`]) and
+    /// converted back to polar coordinates.
     pub fn confidence(&self, params: Option<ConfidenceParams>) -> PolarData {
-        let ConfidenceParams {
-            variance: params_variance,
-            std_dev,
-            step,
-        } = params.unwrap_or_default();
-
-        let cartesian_data = self.cartesian();
-        let covariance_xy = cartesian_data.covariance();
-        let (x, y) = cartesian_data.split_axes();
-        let (mean_x, mean_y) = (mean(&x), mean(&y));
-
-        let (variance_x, variance_y) = (
-            variance(&x, params_variance.clone()),
-            variance(&y, params_variance.clone()),
-        );
-
-        let pearson = covariance_xy / f64::sqrt(variance_x * variance_y);
-        let radius_x = f64::sqrt(1.0 + pearson);
-        let radius_y = f64::sqrt(1.0 - pearson);
-        let (lower_x, upper_x) = (0.0 - radius_x, 0.0 + radius_x);
-        let mut points = Vec::<CartesianPoint>::new();
-        points.push(CartesianPoint { x: lower_x, y: 0.0 });
-        points.push(CartesianPoint { x: upper_x, y: 0.0 });
-        // Changing the size of the step will adjust the smoothness of the ellipse.
-        let mut current_x = lower_x + step.inner();
-
-        // In cartesian coordinates, solving for y, our ellipse equation is:
-        //
-        // y = +/- (b / a)(sqrt(a^2 - x^2))
-        //
-        // For each value of x, generate a positive and a negative value for y:
-        while current_x < upper_x {
-            let positive_y = (radius_y / radius_x)
-                * (f64::sqrt((radius_x * radius_x) - (current_x * current_x)));
-
-            let negative_y = -positive_y;
-
-            points.push(CartesianPoint {
-                x: current_x,
-                y: positive_y,
-            });
-
-            points.push(CartesianPoint {
-                x: current_x,
-                y: negative_y,
-            });
-
-            current_x += step.inner();
-        }
-
-        // It's essential to sort the ellipse in theta order before transforming it, while it still
-        // surrounds the origin. That way the lines connecting each point will still draw the
-        // outside of the ellipse, rather than crossing it.
-        let mut ellipse = CartesianData { points }.polar().theta_sort();
-        // We duplicate the first point in the ellipse at the end, to ensure that our ellipse
-        // is fully closed.
-        ellipse.points.push(ellipse.points[0].clone());
-
-        // The standard deviation is the square root of the variance, and we multiply that by the
-        // number of standard deviations we want our ellipse to cover.
-        let scale = Scale {
-            x: f64::sqrt(variance_x) * std_dev.inner(),
-            y: f64::sqrt(variance_y) * std_dev.inner(),
-        };
-
-        let translate = Translate {
-            x: mean_x,
-            y: mean_y,
-        };
-
-        ellipse
-            .rotate(45.0)
-            .cartesian()
-            .scale(&scale)
-            .translate(&translate)
-            .polar()
+        self.cartesian().confidence(params).polar()
     }
 }
 
@@ -0,0 +1,149 @@
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::Scatter;
+use plotly::common::Anchor;
+use plotly::common::Font;
+use plotly::common::HoverInfo;
+use plotly::common::LegendGroupTitle;
+use plotly::common::Marker;
+use plotly::common::MarkerSymbol;
+use plotly::common::Mode;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::Axis;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use plotly::layout::TraceOrder;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::model::PowerVector;
+use crate::model::PowerVectorData;
+use crate::plots::AsPlot;
+use crate::theme::PlotTheme;
+
+/// A pair of power vector datasets, representing the surgeon of interest and a comparison cohort
+/// of peers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PowerVectorCompare {
+    pub surgeon: PowerVectorData,
+    pub cohort: PowerVectorData,
+}
+
+impl AsPlot for PowerVectorCompare {
+    /// Plot the astigmatic components (J0 against J45) of each dataset, with their means.
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        /// Create a trace of J0 against J45 for the given vectors.
+        fn scatter(vectors: &[PowerVector]) -> Box<Scatter<f64, f64>> {
+            let labels = vectors.iter().map(PowerVector::to_string).collect();
+            let (j0, j45) = vectors.iter().map(|vector| (vector.j0, vector.j45)).unzip();
+
+            Scatter::new(j0, j45)
+                .mode(Mode::Markers)
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(labels)
+        }
+
+        let Self { surgeon, cohort } = self;
+
+        let surgeon_cases = scatter(&surgeon.vectors)
+            .name("cases")
+            .legend_group("surgeon")
+            .legend_group_title(
+                LegendGroupTitle::new()
+                    .text("Surgeon")
+                    .font(Font::new().color(theme.label)),
+            )
+            .marker(Marker::new().color(theme.surgeon));
+
+        let cohort_cases = scatter(&cohort.vectors)
+            .name("cases")
+            .legend_group("cohort")
+            .legend_group_title(
+                LegendGroupTitle::new()
+                    // Hack: adding spaces to the name because Plotly doesn't have horizontal group
+                    // spacing.
+                    .text("Peer cohort    ")
+                    .font(Font::new().color(theme.label)),
+            )
+            .marker(Marker::new().color(theme.cohort))
+            .opacity(0.4)
+            .hover_info(HoverInfo::Skip);
+
+        let means = [
+            (surgeon, theme.surgeon_centroid, "surgeon"),
+            (cohort, theme.cohort_centroid, "cohort"),
+        ]
+        .into_iter()
+        .filter_map(|(data, color, group)| {
+            let trace = scatter(&[data.mean()?])
+                .name("mean")
+                .legend_group(group)
+                .marker(
+                    Marker::new()
+                        .color(color)
+                        .size(12)
+                        .symbol(MarkerSymbol::Cross),
+                );
+
+            Some(trace)
+        });
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                    ModeBarButtonName::Zoom2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        plot.add_traces(vec![cohort_cases, surgeon_cases]);
+
+        for trace in means {
+            plot.add_trace(trace);
+        }
+
+        let axis = |title: &str| {
+            Axis::new()
+                .title(title)
+                .color(theme.label)
+                .show_line(false)
+                .zero_line(true)
+                .zero_line_color(theme.grid)
+                .grid_color(theme.grid)
+        };
+
+        let layout = Layout::new()
+            .x_axis(axis("J0 (D)"))
+            .y_axis(axis("J45 (D)"))
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .trace_order(TraceOrder::Grouped)
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
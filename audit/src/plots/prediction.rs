@@ -0,0 +1,329 @@
+use std::fmt::Display;
+
+use plotly::Bar;
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::common::Anchor;
+use plotly::common::Font;
+use plotly::common::HoverInfo;
+use plotly::common::Marker;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::Axis;
+use plotly::layout::BarMode;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::plots::AsPlot;
+use crate::plots::Variance;
+use crate::plots::mean;
+use crate::plots::median;
+use crate::plots::variance;
+use crate::theme::PlotTheme;
+
+/// The absolute prediction error thresholds (in diopters) reported by the Hoffer/Savini
+/// guidelines.
+pub const PREDICTION_THRESHOLDS: [f64; 4] = [0.25, 0.5, 1.0, 2.0];
+
+/// A pair of prediction error datasets, representing the surgeon of interest and a comparison
+/// cohort of peers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PredictionCompare {
+    pub surgeon: PredictionData,
+    pub cohort: PredictionData,
+}
+
+impl AsPlot for PredictionCompare {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        /// Create the category labels for each threshold.
+        fn categories() -> Vec<String> {
+            PREDICTION_THRESHOLDS
+                .iter()
+                .map(|threshold| format!("±{threshold:.2} D"))
+                .collect()
+        }
+
+        /// Create custom hover labels for the plot.
+        fn labels(percentages: &[Option<f64>]) -> Vec<String> {
+            percentages
+                .iter()
+                .map(|percent| {
+                    percent.map_or_else(
+                        || String::from("no cases"),
+                        |percent| format!("{percent:.0}%"),
+                    )
+                })
+                .collect()
+        }
+
+        let Self { surgeon, cohort } = self;
+        let (surgeon, cohort) = (
+            surgeon.percent_within_thresholds(),
+            cohort.percent_within_thresholds(),
+        );
+
+        let surgeon = Bar::new(categories(), surgeon.clone())
+            .name("Surgeon")
+            .marker(Marker::new().color(theme.surgeon))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&surgeon));
+
+        let cohort = Bar::new(categories(), cohort.clone())
+            .name("Peer cohort")
+            .marker(Marker::new().color(theme.cohort))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels(&cohort));
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                    ModeBarButtonName::Zoom2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        plot.add_traces(vec![surgeon, cohort]);
+
+        let x_axis = Axis::new()
+            .title("Absolute spherical equivalent prediction error")
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let y_axis = Axis::new()
+            .title("Eyes within range (%)")
+            .range(vec![0.0, 100.0])
+            .color(theme.label)
+            .show_line(false)
+            .zero_line(false)
+            .grid_color(theme.grid);
+
+        let layout = Layout::new()
+            .bar_mode(BarMode::Group)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+impl PredictionCompare {
+    /// Zero the mean error of both datasets. See [`PredictionData::zeroed`].
+    pub fn zeroed(self) -> Self {
+        Self {
+            surgeon: self.surgeon.zeroed(),
+            cohort: self.cohort.zeroed(),
+        }
+    }
+}
+
+/// A dataset of spherical equivalent prediction errors, in diopters. Each error is the achieved
+/// postoperative spherical equivalent minus the target spherical equivalent.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PredictionData {
+    pub errors: Vec<f64>,
+}
+
+impl FromIterator<f64> for PredictionData {
+    fn from_iter<T: IntoIterator<Item = f64>>(iter: T) -> Self {
+        Self {
+            errors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl PredictionData {
+    /// Return the absolute value of each prediction error.
+    pub fn absolute_errors(&self) -> Vec<f64> {
+        self.errors.iter().map(|error| error.abs()).collect()
+    }
+
+    /// Return the percentage of eyes (0.0 to 100.0) with an absolute prediction error less than
+    /// or equal to the given number of diopters, or [`None`] if there are no eyes.
+    pub fn percent_within(&self, diopters: f64) -> Option<f64> {
+        if self.errors.is_empty() {
+            return None;
+        }
+
+        let within = self
+            .errors
+            .iter()
+            .filter(|error| error.abs() <= diopters)
+            .count();
+
+        Some(within as f64 * 100.0 / self.errors.len() as f64)
+    }
+
+    /// Return the percentage of eyes within each of the [`PREDICTION_THRESHOLDS`].
+    pub fn percent_within_thresholds(&self) -> Vec<Option<f64>> {
+        PREDICTION_THRESHOLDS
+            .iter()
+            .map(|threshold| self.percent_within(*threshold))
+            .collect()
+    }
+
+    /// Calculate the summary statistics recommended by the Hoffer/Savini guidelines, or [`None`]
+    /// if there are no errors (such as for a new surgeon, or a narrow filter).
+    pub fn stats(&self) -> Option<PredictionStats> {
+        if self.errors.is_empty() {
+            return None;
+        }
+
+        let absolute_errors = self.absolute_errors();
+
+        // The sample variance is undefined for fewer than 2 values.
+        let std_dev = if self.errors.len() > 1 {
+            Some(f64::sqrt(variance(&self.errors, Variance::Sample)))
+        } else {
+            None
+        };
+
+        let within = |threshold: f64| self.percent_within(threshold).unwrap_or_default();
+
+        Some(PredictionStats {
+            count: self.errors.len(),
+            mean_error: mean(&self.errors),
+            mean_absolute_error: mean(&absolute_errors),
+            median_absolute_error: median(&absolute_errors),
+            std_dev,
+            within_quarter: within(PREDICTION_THRESHOLDS[0]),
+            within_half: within(PREDICTION_THRESHOLDS[1]),
+            within_one: within(PREDICTION_THRESHOLDS[2]),
+            within_two: within(PREDICTION_THRESHOLDS[3]),
+        })
+    }
+
+    /// Subtract the mean error from each prediction error, so that the mean error of the
+    /// dataset becomes zero. The Hoffer/Savini guidelines recommend reporting zeroed statistics
+    /// when comparing formulas, because a systematic offset can be removed by optimizing the
+    /// IOL constant, and would otherwise dominate the comparison.
+    pub fn zeroed(mut self) -> Self {
+        let mean_error = mean(&self.errors);
+        self.errors
+            .iter_mut()
+            .for_each(|error| *error -= mean_error);
+
+        self
+    }
+}
+
+/// Summary statistics for a [`PredictionData`]. All errors are in diopters, and all `within_*`
+/// values are percentages of eyes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PredictionStats {
+    pub count: usize,
+    pub mean_error: f64,
+    pub mean_absolute_error: f64,
+    pub median_absolute_error: f64,
+    /// The sample standard deviation, or [`None`] for a single error.
+    pub std_dev: Option<f64>,
+    pub within_quarter: f64,
+    pub within_half: f64,
+    pub within_one: f64,
+    pub within_two: f64,
+}
+
+impl Display for PredictionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            count,
+            mean_error,
+            mean_absolute_error,
+            median_absolute_error,
+            std_dev,
+            within_quarter,
+            within_half,
+            within_one,
+            within_two,
+        } = self;
+
+        let std_dev = std_dev.map_or_else(String::new, |std_dev| format!(", SD {std_dev:.2} D"));
+
+        write!(
+            f,
+            "n = {count}, ME {mean_error:+.2} D, MAE {mean_absolute_error:.2} D, MedAE \
+             {median_absolute_error:.2} D{std_dev}, within ±0.25 D {within_quarter:.0}%, ±0.50 D \
+             {within_half:.0}%, ±1.00 D {within_one:.0}%, ±2.00 D {within_two:.0}%"
+        )
+    }
+}
+
+impl Display for PredictionData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.stats() {
+            Some(stats) => write!(f, "{stats}"),
+            None => write!(f, "no cases"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> PredictionData { [-0.5, -0.25, 0.0, 0.25, 1.5].into_iter().collect() }
+
+    #[test]
+    fn calculates_prediction_stats() {
+        let stats = data().stats().unwrap();
+
+        assert_eq!(stats.count, 5);
+        assert!((stats.mean_error - 0.2).abs() < 1e-9);
+        assert!((stats.mean_absolute_error - 0.5).abs() < 1e-9);
+        assert!((stats.median_absolute_error - 0.25).abs() < 1e-9);
+        assert!((stats.within_quarter - 60.0).abs() < 1e-9);
+        assert!((stats.within_half - 80.0).abs() < 1e-9);
+        assert!((stats.within_one - 80.0).abs() < 1e-9);
+        assert!((stats.within_two - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zeroes_mean_error() {
+        let stats = data().zeroed().stats().unwrap();
+
+        assert!(stats.mean_error.abs() < 1e-9);
+        assert!((stats.std_dev.unwrap() - data().stats().unwrap().std_dev.unwrap()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn describes_empty_data_without_nan() {
+        let empty = PredictionData { errors: Vec::new() };
+
+        assert_eq!(empty.stats(), None);
+        assert_eq!(empty.percent_within(0.5), None);
+        assert_eq!(empty.to_string(), "no cases");
+
+        let single = PredictionData { errors: vec![0.3] };
+
+        assert_eq!(single.stats().unwrap().std_dev, None);
+        assert!(!single.to_string().contains("NaN"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt::Display;
+
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::common::Anchor;
+use plotly::common::Font;
+use plotly::common::HoverInfo;
+use plotly::common::LegendGroupTitle;
+use plotly::common::Marker;
+use plotly::common::MarkerSymbol;
+use plotly::common::Mode;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::AngularAxis;
+use plotly::layout::LayoutPolar;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use plotly::layout::PolarAxisAttributes;
+use plotly::layout::PolarAxisTicks;
+use plotly::layout::PolarTickMode;
+use plotly::layout::RadialAxis;
+use plotly::layout::TraceOrder;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bounded::Bounded;
+use crate::model::Sia;
+use crate::plots::AsPlot;
+use crate::plots::PolarData;
+use crate::plots::PolarPoint;
+use crate::theme::PlotTheme;
+
+/// A surgeon's measured SIA, split by eye side, for comparison with their assumed SIA.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SiaCompare {
+    pub right: SiaData,
+    pub left: SiaData,
+}
+
+impl AsPlot for SiaCompare {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        /// Create custom hover labels for double-angle plots.
+        fn labels(data: &PolarData) -> Vec<String> {
+            data.points
+                .iter()
+                .map(|PolarPoint { r, theta }| format!("{:.2} D × {:.0}°", r, *theta / 2.0))
+                .collect()
+        }
+
+        let traces = [
+            ("right", "Right eyes", &self.right, theme.surgeon),
+            ("left", "Left eyes", &self.left, theme.surgeon_confidence),
+        ]
+        .into_iter()
+        .flat_map(|(group, title, data, color)| {
+            let SiaData { measured, assumed } = data;
+            let centroid = measured.centroid();
+            let assumed = PolarData {
+                points: vec![assumed.clone()],
+            };
+
+            let cases = measured
+                .scatter_polar()
+                .name("cases")
+                .legend_group(group)
+                .legend_group_title(
+                    LegendGroupTitle::new()
+                        .text(title)
+                        .font(Font::new().color(theme.label)),
+                )
+                .mode(Mode::Markers)
+                .marker(Marker::new().color(color))
+                .opacity(0.6)
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(labels(measured));
+
+            let centroid = centroid
+                .scatter_polar()
+                .name("measured")
+                .legend_group(group)
+                .mode(Mode::Markers)
+                .marker(Marker::new().color(color).size(12))
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(labels(&centroid));
+
+            let assumed = assumed
+                .scatter_polar()
+                .name("assumed")
+                .legend_group(group)
+                .mode(Mode::Markers)
+                .marker(
+                    Marker::new()
+                        .color(theme.cohort_centroid)
+                        .symbol(MarkerSymbol::Diamond)
+                        .size(10),
+                )
+                .hover_info(HoverInfo::Text)
+                .hover_text_array(labels(&assumed));
+
+            [cases, centroid, assumed]
+        })
+        .collect::<Vec<_>>();
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Zoom2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        for trace in traces {
+            plot.add_trace(trace);
+        }
+
+        let radial_ticks = PolarAxisTicks::new().tick_color(theme.tick);
+
+        let angular_ticks = PolarAxisTicks::new()
+            .tick_mode(PolarTickMode::Array {
+                tick_values: Some(vec![0.0, 45.0, 90.0, 135.0, 180.0, 225.0, 270.0, 315.0]),
+
+                tick_text: Some(vec![
+                    "0°".to_string(),
+                    "".to_string(),
+                    "45°".to_string(),
+                    "".to_string(),
+                    "90°".to_string(),
+                    "".to_string(),
+                    "135°".to_string(),
+                    "".to_string(),
+                ]),
+            })
+            .tick_color(theme.tick);
+
+        let axis_attributes = PolarAxisAttributes::new()
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let radial_axis_attributes = axis_attributes.clone().ticks(radial_ticks);
+        let angular_axis_attributes = axis_attributes.ticks(angular_ticks);
+        let radial_axis = RadialAxis::new().axis_attributes(radial_axis_attributes);
+        let angular_axis = AngularAxis::new().axis_attributes(angular_axis_attributes);
+
+        let polar_layout = LayoutPolar::new()
+            .bg_color(theme.background)
+            .radial_axis(radial_axis)
+            .angular_axis(angular_axis);
+
+        let layout = Layout::new()
+            .auto_size(true)
+            .paper_background_color(theme.background)
+            .polar(polar_layout)
+            .margin(Margin::new().top(30).right(30).bottom(0).left(30))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .trace_order(TraceOrder::Grouped)
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.1),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+/// The measured SIA for one eye side, as double-angle vectors of the keratometric change for each
+/// case, alongside the surgeon's assumed SIA for that side.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SiaData {
+    pub measured: PolarData,
+    pub assumed: PolarPoint,
+}
+
+impl SiaData {
+    /// Create a new [`SiaData`] from the measured vectors and the surgeon's assumed [`Sia`].
+    pub fn new(measured: PolarData, assumed: &Sia) -> Self {
+        Self {
+            measured,
+            assumed: PolarPoint {
+                r: f64::from(assumed.power.inner()) / 100.0,
+                theta: f64::from(assumed.axis.inner()) * 2.0,
+            },
+        }
+    }
+}
+
+impl Display for SiaData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { measured, assumed } = self;
+        let count = measured.points.len();
+
+        if count == 0 {
+            return write!(
+                f,
+                "no cases with postop keratometry (assumed {:.2} D × {:.0}°)",
+                assumed.r,
+                assumed.theta / 2.0
+            );
+        }
+
+        let PolarPoint { r, theta } = &measured.centroid().points[0];
+
+        write!(
+            f,
+            "measured {r:.2} D × {:.0}° (n = {count}), assumed {:.2} D × {:.0}°",
+            theta / 2.0,
+            assumed.r,
+            assumed.theta / 2.0
+        )
+    }
+}
@@ -0,0 +1,620 @@
+//! Hypothesis tests for comparing the surgeon of interest with the cohort. All p-values are
+//! two-sided.
+
+use std::f64::consts::PI;
+use std::f64::consts::SQRT_2;
+use std::fmt::Display;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::plots::AdverseCompare;
+use crate::plots::Cartesian;
+use crate::plots::CartesianData;
+use crate::plots::PolarCompare;
+use crate::plots::PredictionCompare;
+use crate::plots::Variance;
+use crate::plots::mean;
+use crate::plots::variance;
+
+/// A statistical test.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Test {
+    /// Hotelling's two-sample T² test, for a difference between bivariate means.
+    HotellingT2,
+    /// Welch's unequal variances t-test, for a difference between means.
+    WelchT,
+    /// The Mann-Whitney U test (Wilcoxon rank-sum test), for a difference between distributions.
+    MannWhitneyU,
+    /// Pearson's chi-square test, for a difference between proportions.
+    ChiSquare,
+    /// Fisher's exact test, for a difference between proportions with small expected counts.
+    FisherExact,
+}
+
+impl Display for Test {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HotellingT2 => write!(f, "Hotelling's T²"),
+            Self::WelchT => write!(f, "Welch's t"),
+            Self::MannWhitneyU => write!(f, "Mann-Whitney U"),
+            Self::ChiSquare => write!(f, "χ²"),
+            Self::FisherExact => write!(f, "Fisher's exact test"),
+        }
+    }
+}
+
+/// The size of an effect, in a measure appropriate to the test.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Effect {
+    /// The Mahalanobis distance between bivariate means, using the pooled covariance.
+    MahalanobisD(f64),
+    /// The difference between means, in units of the pooled standard deviation.
+    CohensD(f64),
+    /// The rank-biserial correlation (-1.0 to 1.0), where a positive value means the surgeon's
+    /// values tend to be larger.
+    RankBiserial(f64),
+    /// The odds ratio of the surgeon's events relative to the cohort's.
+    OddsRatio(f64),
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MahalanobisD(d) => write!(f, "Mahalanobis D = {d:.2}"),
+            Self::CohensD(d) => write!(f, "Cohen's d = {d:+.2}"),
+            Self::RankBiserial(r) => write!(f, "rank-biserial r = {r:+.2}"),
+            Self::OddsRatio(ratio) => write!(f, "odds ratio = {ratio:.2}"),
+        }
+    }
+}
+
+/// The result of a statistical test comparing the surgeon with the cohort.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TestResult {
+    pub test: Test,
+    /// The test statistic (T², t, U, or χ²). Fisher's exact test has no statistic, so this is the
+    /// probability of the observed table.
+    pub statistic: f64,
+    pub p_value: f64,
+    pub effect: Effect,
+}
+
+impl Display for TestResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            test,
+            statistic,
+            p_value,
+            effect,
+        } = self;
+
+        let p_value = if *p_value < 0.001 {
+            String::from("p < 0.001")
+        } else {
+            format!("p = {p_value:.3}")
+        };
+
+        match test {
+            Test::FisherExact => write!(f, "{test}, {p_value}, {effect}"),
+            _ => write!(f, "{test} = {statistic:.2}, {p_value}, {effect}"),
+        }
+    }
+}
+
+/// Hotelling's two-sample T² test for a difference between the means of two bivariate datasets,
+/// assuming equal covariance. Returns [`None`] if either dataset has fewer than 2 points, or the
+/// pooled covariance is singular.
+pub fn hotelling_t2(a: &CartesianData, b: &CartesianData) -> Option<TestResult> {
+    let (n_a, n_b) = (a.points.len() as f64, b.points.len() as f64);
+
+    if n_a < 2.0 || n_b < 2.0 || n_a + n_b < 4.0 {
+        return None;
+    }
+
+    let ((a_x, a_y), (b_x, b_y)) = (a.split_axes(), b.split_axes());
+    let (dx, dy) = (mean(&a_x) - mean(&b_x), mean(&a_y) - mean(&b_y));
+
+    // Pool the sample covariance matrices, weighted by their degrees of freedom.
+    let pool = |a: f64, b: f64| ((n_a - 1.0) * a + (n_b - 1.0) * b) / (n_a + n_b - 2.0);
+    let sxx = pool(
+        variance(&a_x, Variance::Sample),
+        variance(&b_x, Variance::Sample),
+    );
+    let syy = pool(
+        variance(&a_y, Variance::Sample),
+        variance(&b_y, Variance::Sample),
+    );
+    let sxy = pool(a.covariance(), b.covariance());
+
+    let determinant = (sxx * syy) - (sxy * sxy);
+
+    if determinant <= 0.0 || !determinant.is_finite() {
+        return None;
+    }
+
+    let distance_squared =
+        ((syy * dx * dx) - (2.0 * sxy * dx * dy) + (sxx * dy * dy)) / determinant;
+    let t2 = (n_a * n_b / (n_a + n_b)) * distance_squared;
+
+    // T² converts to an F statistic with (p, n_a + n_b - p - 1) degrees of freedom, where p = 2.
+    let (df1, df2) = (2.0, n_a + n_b - 3.0);
+    let f = t2 * df2 / (df1 * (n_a + n_b - 2.0));
+
+    Some(TestResult {
+        test: Test::HotellingT2,
+        statistic: t2,
+        p_value: f_upper_tail(f, df1, df2),
+        effect: Effect::MahalanobisD(distance_squared.sqrt()),
+    })
+}
+
+/// Welch's t-test for a difference between the means of two datasets, without assuming equal
+/// variances. Returns [`None`] if either dataset has fewer than 2 values, or both have zero
+/// variance.
+pub fn welch_t(a: &[f64], b: &[f64]) -> Option<TestResult> {
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+
+    if n_a < 2.0 || n_b < 2.0 {
+        return None;
+    }
+
+    let (var_a, var_b) = (variance(a, Variance::Sample), variance(b, Variance::Sample));
+    let (se_a, se_b) = (var_a / n_a, var_b / n_b);
+    let se = se_a + se_b;
+
+    if se == 0.0 {
+        return None;
+    }
+
+    let difference = mean(a) - mean(b);
+    let t = difference / se.sqrt();
+    let df = (se * se) / ((se_a * se_a / (n_a - 1.0)) + (se_b * se_b / (n_b - 1.0)));
+    let pooled = f64::sqrt(((n_a - 1.0) * var_a + (n_b - 1.0) * var_b) / (n_a + n_b - 2.0));
+
+    Some(TestResult {
+        test: Test::WelchT,
+        statistic: t,
+        p_value: t_two_tailed(t, df),
+        effect: Effect::CohensD(difference / pooled),
+    })
+}
+
+/// The Mann-Whitney U test for a difference between the distributions of two datasets, using the
+/// normal approximation with tie and continuity corrections. The statistic is U for `a`. Returns
+/// [`None`] if either dataset is empty, or all values are tied.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> Option<TestResult> {
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let mut values = a
+        .iter()
+        .map(|value| (*value, true))
+        .chain(b.iter().map(|value| (*value, false)))
+        .collect::<Vec<_>>();
+
+    values.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Assign average ranks to ties, accumulating the rank sum for `a` and the tie correction.
+    let (mut rank_sum, mut ties, mut start) = (0.0, 0.0, 0);
+
+    while start < values.len() {
+        let end = values[start..]
+            .iter()
+            .position(|(value, _)| *value != values[start].0)
+            .map_or(values.len(), |offset| start + offset);
+
+        let count = (end - start) as f64;
+        let rank = (start + end + 1) as f64 / 2.0;
+
+        rank_sum += rank * values[start..end].iter().filter(|(_, is_a)| *is_a).count() as f64;
+        ties += (count * count * count) - count;
+        start = end;
+    }
+
+    let n = n_a + n_b;
+    let u = rank_sum - (n_a * (n_a + 1.0) / 2.0);
+    let expected = n_a * n_b / 2.0;
+    let variance = (n_a * n_b / 12.0) * ((n + 1.0) - (ties / (n * (n - 1.0))));
+
+    if variance <= 0.0 {
+        return None;
+    }
+
+    let z = f64::max((u - expected).abs() - 0.5, 0.0) / variance.sqrt();
+
+    Some(TestResult {
+        test: Test::MannWhitneyU,
+        statistic: u,
+        p_value: erfc(z / SQRT_2),
+        effect: Effect::RankBiserial((2.0 * u / (n_a * n_b)) - 1.0),
+    })
+}
+
+/// A number of events out of a total, forming one row of a 2 × 2 table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Proportion {
+    pub events: usize,
+    pub total: usize,
+}
+
+/// Compare two proportions, using Pearson's chi-square test, or Fisher's exact test if any
+/// expected count is below 5. Returns [`None`] if either total is zero.
+pub fn proportions(a: Proportion, b: Proportion) -> Option<TestResult> {
+    let (
+        Proportion {
+            events: events_a,
+            total: total_a,
+        },
+        Proportion {
+            events: events_b,
+            total: total_b,
+        },
+    ) = (a, b);
+
+    if total_a == 0 || total_b == 0 || events_a > total_a || events_b > total_b {
+        return None;
+    }
+
+    let table = [
+        [events_a as f64, (total_a - events_a) as f64],
+        [events_b as f64, (total_b - events_b) as f64],
+    ];
+
+    let n = (total_a + total_b) as f64;
+    let rows = [total_a as f64, total_b as f64];
+    let columns = [table[0][0] + table[1][0], table[0][1] + table[1][1]];
+
+    let expected = |row: usize, column: usize| rows[row] * columns[column] / n;
+
+    // The Haldane-Anscombe correction keeps the odds ratio finite when a cell is zero. The cells
+    // are the events and non-events of each proportion.
+    let [[events_a, non_events_a], [events_b, non_events_b]] =
+        if table.iter().flatten().any(|cell| *cell == 0.0) {
+            table.map(|row| row.map(|cell| cell + 0.5))
+        } else {
+            table
+        };
+
+    let effect = Effect::OddsRatio((events_a * non_events_b) / (non_events_a * events_b));
+
+    if (0..2).any(|row| (0..2).any(|column| expected(row, column) < 5.0)) {
+        let (p_observed, p_value) = fisher_exact(a, b);
+
+        return Some(TestResult {
+            test: Test::FisherExact,
+            statistic: p_observed,
+            p_value,
+            effect,
+        });
+    }
+
+    let chi_square = (0..2)
+        .flat_map(|row| (0..2).map(move |column| (row, column)))
+        .map(|(row, column)| {
+            (table[row][column] - expected(row, column)).powi(2) / expected(row, column)
+        })
+        .sum::<f64>();
+
+    Some(TestResult {
+        test: Test::ChiSquare,
+        statistic: chi_square,
+        // The chi-square distribution with 1 degree of freedom is the square of a standard normal.
+        p_value: erfc(f64::sqrt(chi_square / 2.0)),
+        effect,
+    })
+}
+
+/// Fisher's exact test for a 2 × 2 table, returning the probability of the observed table and the
+/// two-sided p-value (the sum of the probabilities of all tables with the same margins that are no
+/// more likely than the observed table).
+fn fisher_exact(a: Proportion, b: Proportion) -> (f64, f64) {
+    let (
+        Proportion {
+            events: events_a,
+            total: total_a,
+        },
+        Proportion {
+            events: events_b,
+            total: total_b,
+        },
+    ) = (a, b);
+
+    let events = events_a + events_b;
+    let n = total_a + total_b;
+
+    let probability = |k: usize| {
+        f64::exp(ln_choose(total_a, k) + ln_choose(total_b, events - k) - ln_choose(n, events))
+    };
+
+    let p_observed = probability(events_a);
+
+    let p_value = (events.saturating_sub(total_b)..=events.min(total_a))
+        .map(probability)
+        .filter(|p| *p <= p_observed * (1.0 + 1e-7))
+        .sum::<f64>();
+
+    (p_observed, p_value.min(1.0))
+}
+
+/// Return the natural logarithm of the binomial coefficient (n choose k).
+fn ln_choose(n: usize, k: usize) -> f64 {
+    ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0)
+}
+
+/// Return the natural logarithm of the gamma function, using the Lanczos approximation (g = 7,
+/// n = 9), which is accurate to around 15 significant figures.
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // The reflection formula.
+        return f64::ln(PI / f64::sin(PI * x)) - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, coefficient)| {
+            sum + coefficient / (x + i as f64 + 1.0)
+        });
+
+    (0.5 * f64::ln(2.0 * PI)) + ((x + 0.5) * t.ln()) - t + series.ln()
+}
+
+/// Return the regularized incomplete beta function Iₓ(a, b), evaluated with Lentz's continued
+/// fraction (Numerical Recipes, 3rd edition, §6.4).
+pub fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front =
+        f64::exp(ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + (a * x.ln()) + (b * (1.0 - x).ln()));
+
+    // The continued fraction converges quickly only for x < (a + 1) / (a + b + 2), so use the
+    // symmetry relation Iₓ(a, b) = 1 - I₁₋ₓ(b, a) otherwise.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - (front * beta_continued_fraction(b, a, 1.0 - x) / b)
+    }
+}
+
+/// Evaluate the continued fraction for [`incomplete_beta`].
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    let nonzero = |value: f64| if value.abs() < TINY { TINY } else { value };
+
+    let (mut c, mut d) = (1.0, 1.0 / nonzero(1.0 - ((a + b) * x / (a + 1.0))));
+    let mut h = d;
+
+    for m in 1..=300 {
+        let m = f64::from(m);
+
+        let even = m * (b - m) * x / ((a + (2.0 * m) - 1.0) * (a + (2.0 * m)));
+        d = 1.0 / nonzero(1.0 + (even * d));
+        c = nonzero(1.0 + (even / c));
+        h *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + (2.0 * m)) * (a + (2.0 * m) + 1.0));
+        d = 1.0 / nonzero(1.0 + (odd * d));
+        c = nonzero(1.0 + (odd / c));
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+/// Return the two-tailed p-value for Student's t distribution.
+pub fn t_two_tailed(t: f64, df: f64) -> f64 { incomplete_beta(df / 2.0, 0.5, df / (df + (t * t))) }
+
+/// Return the upper-tail p-value for the F distribution.
+pub fn f_upper_tail(f: f64, df1: f64, df2: f64) -> f64 {
+    incomplete_beta(df2 / 2.0, df1 / 2.0, df2 / (df2 + (df1 * f)))
+}
+
+/// Return the complementary error function, using the Chebyshev approximation from Numerical
+/// Recipes (fractional error below 1.2 × 10⁻⁷).
+pub fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + (0.5 * z));
+
+    let polynomial = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |sum, coefficient| coefficient + (t * sum));
+
+    let result = t * f64::exp(-(z * z) + polynomial);
+
+    if x >= 0.0 { result } else { 2.0 - result }
+}
+
+impl PolarCompare {
+    /// Test for a difference between the surgeon's and the cohort's double-angle centroids.
+    pub fn significance(&self) -> Option<TestResult> {
+        hotelling_t2(&self.surgeon.cartesian(), &self.cohort.cartesian())
+    }
+}
+
+impl PredictionCompare {
+    /// Test for a difference between the surgeon's and the cohort's mean prediction errors
+    /// (Welch's t), and absolute prediction errors (Mann-Whitney U).
+    pub fn significance(&self) -> Vec<TestResult> {
+        let Self {
+            surgeon, cohort, ..
+        } = self;
+
+        [
+            welch_t(&surgeon.errors, &cohort.errors),
+            mann_whitney_u(&surgeon.absolute_errors(), &cohort.absolute_errors()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+impl AdverseCompare {
+    /// Test for a difference between the surgeon's and the cohort's rates of any adverse event.
+    pub fn significance(&self) -> Option<TestResult> {
+        let Self {
+            surgeon, cohort, ..
+        } = self;
+
+        proportions(
+            Proportion {
+                events: surgeon.events(&None),
+                total: surgeon.cases.len(),
+            },
+            Proportion {
+                events: cohort.events(&None),
+                total: cohort.cases.len(),
+            },
+        )
+    }
+}
+
+/// Describe test results in a sentence, for the information text that accompanies a plot.
+pub fn describe_significance<'a>(results: impl IntoIterator<Item = &'a TestResult>) -> String {
+    let results = results
+        .into_iter()
+        .map(|result| result.to_string())
+        .collect::<Vec<_>>();
+
+    if results.is_empty() {
+        String::from("Too few cases for a significance test.")
+    } else {
+        format!("Surgeon vs peer cohort: {}.", results.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plots::CartesianPoint;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool { (a - b).abs() < tolerance }
+
+    #[test]
+    fn calculates_special_functions() {
+        assert!(close(ln_gamma(5.0), 24_f64.ln(), 1e-12));
+        assert!(close(ln_gamma(0.5), PI.sqrt().ln(), 1e-12));
+        assert!(close(erfc(1.0), 0.157_299_207, 1e-7));
+        assert!(close(erfc(-1.0), 1.842_700_793, 1e-7));
+        // I₀.₅(2, 3) = 11 / 16.
+        assert!(close(incomplete_beta(2.0, 3.0, 0.5), 0.6875, 1e-10));
+    }
+
+    #[test]
+    fn calculates_distribution_tails() {
+        // Reference values from standard t and F tables.
+        assert!(close(t_two_tailed(2.0, 10.0), 0.073_388, 1e-5));
+        assert!(close(t_two_tailed(2.228, 10.0), 0.05, 1e-4));
+        // For df1 = 2, the F upper tail is (1 + 2F / df2)^(-df2 / 2).
+        assert!(close(
+            f_upper_tail(3.0, 2.0, 20.0),
+            1.3_f64.powi(-10),
+            1e-10
+        ));
+    }
+
+    #[test]
+    fn tests_proportions() {
+        // Fisher's "lady tasting tea" table: two-sided p = 34 / 70.
+        let proportion = |events, total| Proportion { events, total };
+
+        let fisher = proportions(proportion(3, 4), proportion(1, 4)).unwrap();
+        assert_eq!(fisher.test, Test::FisherExact);
+        assert!(close(fisher.p_value, 34.0 / 70.0, 1e-9));
+
+        // Expected counts of 25: χ² = 4.0.
+        let chi_square = proportions(proportion(20, 50), proportion(30, 50)).unwrap();
+        assert_eq!(chi_square.test, Test::ChiSquare);
+        assert!(close(chi_square.statistic, 4.0, 1e-9));
+        assert!(close(chi_square.p_value, 0.045_500, 1e-5));
+        assert!(proportions(proportion(0, 0), proportion(1, 10)).is_none());
+    }
+
+    #[test]
+    fn tests_means_and_ranks() {
+        let (a, b) = ([1.0, 2.0, 3.0], [4.0, 5.0, 6.0]);
+
+        let mann_whitney = mann_whitney_u(&a, &b).unwrap();
+        assert_eq!(mann_whitney.statistic, 0.0);
+        assert_eq!(mann_whitney.effect, Effect::RankBiserial(-1.0));
+        // z = (4.5 - 0.5) / sqrt(5.25).
+        assert!(close(
+            mann_whitney.p_value,
+            erfc(4.0 / 5.25_f64.sqrt() / SQRT_2),
+            1e-12
+        ));
+
+        let welch = welch_t(&a, &b).unwrap();
+        // Equal variances of 1.0 and n = 3: t = -3 / sqrt(2 / 3), df = 4.
+        assert!(close(welch.statistic, -3.0 / (2.0_f64 / 3.0).sqrt(), 1e-9));
+        assert!(close(
+            welch.p_value,
+            t_two_tailed(welch.statistic, 4.0),
+            1e-12
+        ));
+        assert_eq!(welch.effect, Effect::CohensD(-3.0));
+    }
+
+    #[test]
+    fn hotelling_detects_shifted_centroids() {
+        let data = |offset: f64| {
+            (0..20)
+                .map(|i| CartesianPoint {
+                    x: f64::from(i % 5) * 0.2 + offset,
+                    y: f64::from(i % 4) * 0.3 - f64::from(i % 3) * 0.1,
+                })
+                .collect::<CartesianData>()
+        };
+
+        let same = hotelling_t2(&data(0.0), &data(0.0)).unwrap();
+        let shifted = hotelling_t2(&data(0.0), &data(0.5)).unwrap();
+
+        assert!(close(same.statistic, 0.0, 1e-12));
+        assert!(close(same.p_value, 1.0, 1e-9));
+        assert!(shifted.p_value < 0.001);
+    }
+}
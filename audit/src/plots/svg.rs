@@ -0,0 +1,720 @@
+//! Standalone SVG rendering of plots, for contexts where Plotly's JavaScript can't run (email and
+//! PDF reports, or networks that block the Plotly CDN).
+//!
+//! The output is byte-stable: every coordinate is written with a fixed precision, and elements are
+//! always emitted in the same order, so the same data and theme always produce the same string.
+
+use std::fmt::Write;
+
+use crate::plots::AsSvg;
+use crate::plots::CartesianCompare;
+use crate::plots::CartesianData;
+use crate::plots::CartesianPoint;
+use crate::plots::ConfidenceParams;
+use crate::plots::PolarCompare;
+use crate::plots::PolarData;
+use crate::plots::PolarPoint;
+use crate::plots::Variance;
+use crate::plots::degrees_to_radians;
+use crate::theme::PlotTheme;
+
+const WIDTH: f64 = 480.0;
+const HEIGHT: f64 = 560.0;
+/// The top of the legend, below the plot area.
+const LEGEND_TOP: f64 = 500.0;
+/// The stroke pattern for dashed lines.
+const DASH: &str = "6 4";
+
+/// A pixel coordinate, with y increasing downwards.
+type Px = (f64, f64);
+
+impl AsSvg for PolarCompare {
+    /// Render a double-angle plot: the angular axis is labelled with the halved angles, so that
+    /// opposite meridians of astigmatism point in opposite directions.
+    fn svg(&self, theme: &PlotTheme) -> String {
+        let Self {
+            surgeon, cohort, ..
+        } = self;
+        let (surgeon_centroid, cohort_centroid) = (surgeon.centroid(), cohort.centroid());
+
+        // A confidence ellipse can't be estimated from fewer than 3 points.
+        let ellipse = |data: &PolarData| (data.points.len() >= 3).then(|| data.confidence(None));
+        let (surgeon_ellipse, cohort_ellipse) = (ellipse(surgeon), ellipse(cohort));
+
+        let r_max = [surgeon, cohort, &surgeon_centroid, &cohort_centroid]
+            .into_iter()
+            .chain(surgeon_ellipse.iter())
+            .chain(cohort_ellipse.iter())
+            .flat_map(|data| data.points.iter().map(|point| point.r))
+            .filter(|r| r.is_finite())
+            .fold(0.0, f64::max);
+
+        let step = tick_step(r_max);
+        let axis_max = (r_max / step).ceil().max(1.0) * step;
+
+        let (center, radius) = ((WIDTH / 2.0, 240.0), 200.0);
+
+        let px = |PolarPoint { r, theta }: &PolarPoint| -> Px {
+            let (sin, cos) = degrees_to_radians(*theta).sin_cos();
+            let scaled = r / axis_max * radius;
+
+            (center.0 + (scaled * cos), center.1 - (scaled * sin))
+        };
+
+        let mut svg = Svg::new(theme);
+
+        // Spokes every 22.5° of axis (45° on the double-angle plot), labelled every 45° of axis.
+        for index in 0..8 {
+            let plotted = f64::from(index) * 45.0;
+            let (sin, cos) = degrees_to_radians(plotted).sin_cos();
+            let end = (center.0 + (radius * cos), center.1 - (radius * sin));
+            svg.line((center, end), Stroke::new(theme.grid, 1.0));
+
+            if index % 2 == 0 {
+                let label = (
+                    center.0 + ((radius + 16.0) * cos),
+                    center.1 - ((radius + 16.0) * sin),
+                );
+                let text = format!("{}°", plotted / 2.0);
+                svg.text(
+                    (label.0, label.1 + 4.0),
+                    Label::new("middle", theme.tick, &text),
+                );
+            }
+        }
+
+        for tick in ticks(0.0, axis_max, step).into_iter().skip(1) {
+            let ring = tick / axis_max * radius;
+            svg.ring((center, ring), theme.grid);
+            svg.text(
+                (center.0 + ring, center.1 - 4.0),
+                Label::new("end", theme.tick, &tick_label(tick)),
+            );
+        }
+
+        for (data, color, opacity) in [(cohort, theme.cohort, 0.4), (surgeon, theme.surgeon, 1.0)] {
+            for point in &data.points {
+                svg.circle((px(point), 3.0), Fill::new(color, opacity));
+            }
+        }
+
+        for (ellipse, color) in [
+            (cohort_ellipse, theme.cohort),
+            (surgeon_ellipse, theme.surgeon_confidence),
+        ] {
+            if let Some(ellipse) = ellipse {
+                let points = ellipse.points.iter().map(px).collect::<Vec<_>>();
+                svg.polyline(&points, Stroke::new(color, 1.5));
+            }
+        }
+
+        for (centroid, color) in [
+            (cohort_centroid, theme.cohort_centroid),
+            (surgeon_centroid, theme.surgeon_centroid),
+        ] {
+            for point in &centroid.points {
+                svg.circle((px(point), 5.0), Fill::new(color, 1.0));
+            }
+        }
+
+        svg.legend(
+            theme,
+            &[
+                (
+                    "Surgeon",
+                    &[
+                        ("cases", Swatch::Marker(Fill::new(theme.surgeon, 1.0))),
+                        (
+                            "centroid",
+                            Swatch::Marker(Fill::new(theme.surgeon_centroid, 1.0)),
+                        ),
+                        (
+                            "confidence",
+                            Swatch::Line(Stroke::new(theme.surgeon_confidence, 1.5)),
+                        ),
+                    ],
+                ),
+                (
+                    "Peer cohort",
+                    &[
+                        ("cases", Swatch::Marker(Fill::new(theme.cohort, 0.4))),
+                        (
+                            "centroid",
+                            Swatch::Marker(Fill::new(theme.cohort_centroid, 1.0)),
+                        ),
+                        ("confidence", Swatch::Line(Stroke::new(theme.cohort, 1.5))),
+                    ],
+                ),
+            ],
+        );
+
+        svg.render()
+    }
+}
+
+impl AsSvg for CartesianCompare {
+    /// Render postop refractive astigmatism against preop corneal astigmatism, with the mean ± 1 SD
+    /// for each 0.25 D bin, the regression lines, and the confidence ellipses.
+    fn svg(&self, theme: &PlotTheme) -> String {
+        let Self {
+            surgeon, cohort, ..
+        } = self;
+
+        let ellipse = |data: &CartesianData| {
+            (data.points.len() >= 3)
+                .then(|| data.confidence(Some(ConfidenceParams::new().variance(Variance::Sample))))
+        };
+
+        let (surgeon_ellipse, cohort_ellipse) = (ellipse(surgeon), ellipse(cohort));
+        let (surgeon_bins, cohort_bins) = (surgeon.bins(0.25), cohort.bins(0.25));
+
+        let bin_extents = surgeon_bins
+            .iter()
+            .chain(cohort_bins.iter())
+            .flat_map(|bin| {
+                [
+                    (bin.x, bin.mean - bin.std_dev),
+                    (bin.x, bin.mean + bin.std_dev),
+                ]
+            });
+
+        let (x_range, y_range) = [surgeon, cohort]
+            .into_iter()
+            .chain(surgeon_ellipse.iter())
+            .chain(cohort_ellipse.iter())
+            .flat_map(|data| data.points.iter().map(|point| (point.x, point.y)))
+            .chain(bin_extents)
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .fold(
+                ((f64::MAX, f64::MIN), (f64::MAX, f64::MIN)),
+                |((x_min, x_max), (y_min, y_max)), (x, y)| {
+                    ((x_min.min(x), x_max.max(x)), (y_min.min(y), y_max.max(y)))
+                },
+            );
+
+        let (x_min, x_max, x_step) = axis_range(x_range);
+        let (y_min, y_max, y_step) = axis_range(y_range);
+
+        // The plot area, leaving room for the tick labels and axis titles.
+        let (left, right, top, bottom) = (60.0, WIDTH - 20.0, 20.0, 420.0);
+
+        let px = |x: f64, y: f64| -> Px {
+            (
+                left + ((x - x_min) / (x_max - x_min) * (right - left)),
+                bottom - ((y - y_min) / (y_max - y_min) * (bottom - top)),
+            )
+        };
+
+        let mut svg = Svg::new(theme);
+
+        for tick in ticks(x_min, x_max, x_step) {
+            let (x, _) = px(tick, y_min);
+            svg.line(((x, top), (x, bottom)), Stroke::new(theme.grid, 1.0));
+            svg.text(
+                (x, bottom + 16.0),
+                Label::new("middle", theme.label, &tick_label(tick)),
+            );
+        }
+
+        for tick in ticks(y_min, y_max, y_step) {
+            let (_, y) = px(x_min, tick);
+            svg.line(((left, y), (right, y)), Stroke::new(theme.grid, 1.0));
+            svg.text(
+                (left - 6.0, y + 4.0),
+                Label::new("end", theme.label, &tick_label(tick)),
+            );
+        }
+
+        svg.text(
+            ((left + right) / 2.0, bottom + 36.0),
+            Label::new("middle", theme.label, "Preop corneal astigmatism (D)"),
+        );
+
+        svg.vertical_text(
+            (16.0, (top + bottom) / 2.0),
+            Label::new(
+                "middle",
+                theme.label,
+                "Postop refractive astigmatism (vertexed D)",
+            ),
+        );
+
+        for (data, color, opacity) in [(cohort, theme.cohort, 0.4), (surgeon, theme.surgeon, 1.0)] {
+            for CartesianPoint { x, y } in &data.points {
+                svg.circle((px(*x, *y), 3.0), Fill::new(color, opacity));
+            }
+        }
+
+        for (data, ellipse, ellipse_color, fit_color) in [
+            (cohort, cohort_ellipse, theme.cohort, theme.cohort_centroid),
+            (
+                surgeon,
+                surgeon_ellipse,
+                theme.surgeon_confidence,
+                theme.surgeon_centroid,
+            ),
+        ] {
+            if let Some(ellipse) = ellipse {
+                let points = ellipse
+                    .points
+                    .iter()
+                    .map(|CartesianPoint { x, y }| px(*x, *y))
+                    .collect::<Vec<_>>();
+
+                svg.polyline(&points, Stroke::new(ellipse_color, 1.5));
+            }
+
+            if let Some(regression) = data.regression() {
+                let (x, _) = data.split_axes();
+                let (min, max) = x.iter().fold((f64::MAX, f64::MIN), |(min, max), x| {
+                    (min.min(*x), max.max(*x))
+                });
+
+                let from = px(min, regression.y(min));
+                let to = px(max, regression.y(max));
+                svg.line((from, to), Stroke::new(fit_color, 1.5).dash(DASH));
+            }
+        }
+
+        for (bins, color) in [
+            (cohort_bins, theme.cohort_centroid),
+            (surgeon_bins, theme.surgeon_centroid),
+        ] {
+            for bin in bins {
+                let from = px(bin.x, bin.mean - bin.std_dev);
+                let to = px(bin.x, bin.mean + bin.std_dev);
+                svg.line((from, to), Stroke::new(color, 1.5));
+                svg.circle((px(bin.x, bin.mean), 5.0), Fill::new(color, 1.0));
+            }
+        }
+
+        svg.legend(
+            theme,
+            &[
+                (
+                    "Surgeon",
+                    &[
+                        ("cases", Swatch::Marker(Fill::new(theme.surgeon, 1.0))),
+                        (
+                            "0.25 D bins",
+                            Swatch::Marker(Fill::new(theme.surgeon_centroid, 1.0)),
+                        ),
+                        (
+                            "regression",
+                            Swatch::Line(Stroke::new(theme.surgeon_centroid, 1.5).dash(DASH)),
+                        ),
+                        (
+                            "confidence",
+                            Swatch::Line(Stroke::new(theme.surgeon_confidence, 1.5)),
+                        ),
+                    ],
+                ),
+                (
+                    "Peer cohort",
+                    &[
+                        ("cases", Swatch::Marker(Fill::new(theme.cohort, 0.4))),
+                        (
+                            "0.25 D bins",
+                            Swatch::Marker(Fill::new(theme.cohort_centroid, 1.0)),
+                        ),
+                        (
+                            "regression",
+                            Swatch::Line(Stroke::new(theme.cohort_centroid, 1.5).dash(DASH)),
+                        ),
+                        ("confidence", Swatch::Line(Stroke::new(theme.cohort, 1.5))),
+                    ],
+                ),
+            ],
+        );
+
+        svg.render()
+    }
+}
+
+/// The symbol beside an entry in the legend.
+enum Swatch<'a> {
+    Marker(Fill<'a>),
+    Line(Stroke<'a>),
+}
+
+/// The fill of a marker.
+#[derive(Clone, Copy)]
+struct Fill<'a> {
+    color: &'a str,
+    opacity: f64,
+}
+
+impl<'a> Fill<'a> {
+    fn new(color: &'a str, opacity: f64) -> Self { Self { color, opacity } }
+}
+
+/// The stroke of a line, which is solid unless a dash pattern is set.
+#[derive(Clone, Copy)]
+struct Stroke<'a> {
+    color: &'a str,
+    width: f64,
+    dash: Option<&'a str>,
+}
+
+impl<'a> Stroke<'a> {
+    fn new(color: &'a str, width: f64) -> Self {
+        Self {
+            color,
+            width,
+            dash: None,
+        }
+    }
+
+    /// Set the SVG `stroke-dasharray` pattern.
+    fn dash(mut self, dash: &'a str) -> Self {
+        self.dash = Some(dash);
+        self
+    }
+}
+
+/// A text label, where `anchor` is an SVG `text-anchor` value (start, middle or end).
+struct Label<'a> {
+    anchor: &'a str,
+    color: &'a str,
+    text: &'a str,
+}
+
+impl<'a> Label<'a> {
+    fn new(anchor: &'a str, color: &'a str, text: &'a str) -> Self {
+        Self {
+            anchor,
+            color,
+            text,
+        }
+    }
+}
+
+/// A minimal SVG document builder. Elements with non-finite coordinates are skipped, so that empty
+/// datasets (whose means are [`f64::NAN`]) don't produce invalid markup.
+struct Svg {
+    elements: Vec<String>,
+}
+
+impl Svg {
+    fn new(theme: &PlotTheme) -> Self {
+        let background = format!(
+            r#"<rect width="{}" height="{}" fill="{}"/>"#,
+            num(WIDTH),
+            num(HEIGHT),
+            theme.background
+        );
+
+        Self {
+            elements: vec![background],
+        }
+    }
+
+    /// Add a filled circle, given its center and radius.
+    fn circle(&mut self, ((x, y), radius): (Px, f64), Fill { color, opacity }: Fill) {
+        if !(x.is_finite() && y.is_finite()) {
+            return;
+        }
+
+        let opacity = if opacity < 1.0 {
+            format!(r#" fill-opacity="{}""#, num(opacity))
+        } else {
+            String::new()
+        };
+
+        self.elements.push(format!(
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{color}"{opacity}/>"#,
+            num(x),
+            num(y),
+            num(radius)
+        ));
+    }
+
+    /// Add an unfilled circle, such as a grid ring on a polar plot.
+    fn ring(&mut self, ((x, y), radius): (Px, f64), color: &str) {
+        self.elements.push(format!(
+            r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{color}"/>"#,
+            num(x),
+            num(y),
+            num(radius)
+        ));
+    }
+
+    fn line(&mut self, (from, to): (Px, Px), stroke: Stroke) {
+        if [from.0, from.1, to.0, to.1]
+            .iter()
+            .any(|value| !value.is_finite())
+        {
+            return;
+        }
+
+        self.elements.push(format!(
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{color}" stroke-width="{}"{}/>"#,
+            num(from.0),
+            num(from.1),
+            num(to.0),
+            num(to.1),
+            num(stroke.width),
+            dash_attribute(stroke.dash),
+            color = stroke.color
+        ));
+    }
+
+    fn polyline(&mut self, points: &[Px], stroke: Stroke) {
+        let points = points
+            .iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(|(x, y)| format!("{},{}", num(*x), num(*y)))
+            .collect::<Vec<_>>();
+
+        if points.len() < 2 {
+            return;
+        }
+
+        self.elements.push(format!(
+            r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="{}"{}/>"#,
+            points.join(" "),
+            num(stroke.width),
+            dash_attribute(stroke.dash),
+            color = stroke.color
+        ));
+    }
+
+    /// Add a text label at the given point.
+    fn text(
+        &mut self,
+        (x, y): Px,
+        Label {
+            anchor,
+            color,
+            text,
+        }: Label,
+    ) {
+        self.elements.push(format!(
+            r#"<text x="{}" y="{}" text-anchor="{anchor}" fill="{color}">{}</text>"#,
+            num(x),
+            num(y),
+            escape(text)
+        ));
+    }
+
+    /// Add a text label anchored on the given point and rotated to read upwards.
+    fn vertical_text(
+        &mut self,
+        (x, y): Px,
+        Label {
+            anchor,
+            color,
+            text,
+        }: Label,
+    ) {
+        self.elements.push(format!(
+            r#"<text x="{x}" y="{y}" text-anchor="{anchor}" fill="{color}" transform="rotate(-90 {x} {y})">{}</text>"#,
+            escape(text),
+            x = num(x),
+            y = num(y)
+        ));
+    }
+
+    /// Add a legend below the plot area, with one row for each group.
+    fn legend(&mut self, theme: &PlotTheme, groups: &[(&str, &[(&str, Swatch)])]) {
+        for (row, (title, entries)) in groups.iter().enumerate() {
+            let y = LEGEND_TOP + (row as f64 * 24.0);
+            self.text((20.0, y + 4.0), Label::new("start", theme.label, title));
+
+            for (column, (name, swatch)) in entries.iter().enumerate() {
+                let x = 110.0 + (column as f64 * 92.0);
+
+                match swatch {
+                    Swatch::Marker(fill) => self.circle(((x + 8.0, y), 4.0), *fill),
+                    Swatch::Line(stroke) => self.line(((x, y), (x + 16.0, y)), *stroke),
+                }
+
+                self.text((x + 22.0, y + 4.0), Label::new("start", theme.legend, name));
+            }
+        }
+    }
+
+    fn render(self) -> String {
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+            w = WIDTH,
+            h = HEIGHT
+        );
+
+        for element in self.elements {
+            // Writing to a String can't fail.
+            let _ = write!(svg, "\n{element}");
+        }
+
+        svg.push_str("\n</svg>\n");
+
+        svg
+    }
+}
+
+/// Format a coordinate with a fixed precision, so that the output is stable. Negative zero is
+/// written as zero.
+fn num(value: f64) -> String {
+    let formatted = format!("{value:.2}");
+
+    if formatted == "-0.00" {
+        String::from("0.00")
+    } else {
+        formatted
+    }
+}
+
+fn dash_attribute(dash: Option<&str>) -> String {
+    dash.map(|dash| format!(r#" stroke-dasharray="{dash}""#))
+        .unwrap_or_default()
+}
+
+/// Escape the characters that are significant in SVG text content and attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Choose a tick spacing (in D) that gives at most 4 intervals across the given span.
+fn tick_step(span: f64) -> f64 {
+    const STEPS: [f64; 9] = [0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0];
+
+    STEPS
+        .into_iter()
+        .find(|step| span / step <= 4.0)
+        .unwrap_or(STEPS[STEPS.len() - 1])
+}
+
+/// Extend a (min, max) range of data outwards to whole ticks, returning the axis minimum, maximum,
+/// and tick spacing. An empty range (as from an empty dataset) becomes 0.0 to 1.0.
+fn axis_range((min, max): (f64, f64)) -> (f64, f64, f64) {
+    if min > max {
+        return (0.0, 1.0, tick_step(1.0));
+    }
+
+    let step = tick_step(max - min);
+    let (lower, upper) = ((min / step).floor() * step, (max / step).ceil() * step);
+
+    if upper > lower {
+        (lower, upper, step)
+    } else {
+        (lower, lower + step, step)
+    }
+}
+
+/// Return the ticks from `min` to `max` inclusive. The ticks are counted, rather than accumulated,
+/// to avoid floating point drift.
+fn ticks(min: f64, max: f64, step: f64) -> Vec<f64> {
+    let count = ((max - min) / step).round() as u32;
+
+    (0..=count)
+        .map(|index| min + (f64::from(index) * step))
+        .collect()
+}
+
+/// Format a tick value without trailing zeros (such as 0.25, 0.5, or 1).
+fn tick_label(value: f64) -> String {
+    let label = format!("{value:.2}");
+    let label = label.trim_end_matches('0').trim_end_matches('.');
+
+    if label == "-0" {
+        String::from("0")
+    } else {
+        label.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polar(points: &[(f64, f64)]) -> PolarData {
+        points
+            .iter()
+            .map(|(r, theta)| PolarPoint {
+                r: *r,
+                theta: *theta,
+            })
+            .collect()
+    }
+
+    fn cartesian(points: &[(f64, f64)]) -> CartesianData {
+        points
+            .iter()
+            .map(|(x, y)| CartesianPoint { x: *x, y: *y })
+            .collect()
+    }
+
+    #[test]
+    fn formats_stable_numbers() {
+        assert_eq!(num(-0.001), "0.00");
+        assert_eq!(num(1.005), "1.00");
+        assert_eq!(num(-1.5), "-1.50");
+        assert_eq!(tick_label(0.5), "0.5");
+        assert_eq!(tick_label(2.0), "2");
+        assert_eq!(tick_label(-0.0), "0");
+        assert_eq!(axis_range((0.1, 1.1)), (0.0, 1.25, 0.25));
+        assert_eq!(axis_range((f64::MAX, f64::MIN)), (0.0, 1.0, 0.25));
+    }
+
+    #[test]
+    fn renders_polar_snapshot() {
+        let compare = PolarCompare::new(polar(&[(1.0, 90.0)]), polar(&[(0.5, 0.0)]));
+
+        assert_eq!(
+            compare.svg(&PlotTheme::DARK),
+            include_str!("../tests/snapshots/polar.svg")
+        );
+    }
+
+    #[test]
+    fn renders_cartesian_snapshot() {
+        let compare = CartesianCompare {
+            surgeon: cartesian(&[(0.5, 0.25), (1.0, 0.5), (1.25, 0.25), (2.0, 0.75)]),
+            cohort: cartesian(&[(0.25, 0.0), (0.75, 0.5), (1.5, 0.25), (3.0, 1.0)]),
+        };
+
+        assert_eq!(
+            compare.svg(&PlotTheme::DARK),
+            include_str!("../tests/snapshots/cartesian.svg")
+        );
+    }
+
+    #[test]
+    fn renders_stable_offline_svg() {
+        let compare = CartesianCompare {
+            surgeon: cartesian(&[(0.5, 0.25), (1.0, 0.5), (1.25, 0.25), (2.0, 0.75)]),
+            cohort: cartesian(&[(0.25, 0.0), (0.75, 0.5), (1.5, 0.25), (3.0, 1.0)]),
+        };
+
+        for theme in [PlotTheme::DARK, PlotTheme::LIGHT, PlotTheme::COLOR_BLIND] {
+            let svg = compare.svg(&theme);
+
+            assert_eq!(svg, compare.svg(&theme));
+            assert!(svg.starts_with("<svg "));
+            assert!(svg.ends_with("</svg>\n"));
+            assert!(!svg.contains("NaN") && !svg.contains("inf"));
+            assert!(!svg.contains("<script"));
+            // The only URL is the SVG namespace.
+            assert_eq!(svg.matches("http").count(), 1);
+            // Two ellipses, with no dash pattern.
+            assert_eq!(svg.matches("<polyline").count(), 2);
+            // Two regression lines, and two legend entries for them.
+            assert_eq!(svg.matches("stroke-dasharray").count(), 4);
+        }
+    }
+
+    #[test]
+    fn renders_empty_data() {
+        let empty = PolarCompare::new(polar(&[]), polar(&[])).svg(&PlotTheme::default());
+
+        assert!(!empty.contains("NaN"));
+        // Only the legend markers are drawn.
+        assert_eq!(
+            empty.matches("<circle").count() - empty.matches("fill=\"none\"").count(),
+            4
+        );
+    }
+}
@@ -0,0 +1,630 @@
+use std::collections::BTreeMap;
+
+use audit_macro::RangeBounded;
+use chrono::Datelike;
+use chrono::NaiveDate;
+use gel_protocol::descriptors::TypePos;
+use gel_protocol::errors::DecodeError;
+use gel_protocol::errors::decode_error;
+use gel_protocol::queryable::Decoder;
+use gel_protocol::queryable::DescriptorContext;
+use gel_protocol::queryable::DescriptorMismatch;
+use gel_protocol::queryable::Queryable;
+use gel_tokio::Client;
+use plotly::Configuration;
+use plotly::Layout;
+use plotly::Plot;
+use plotly::Scatter;
+use plotly::common::Anchor;
+use plotly::common::DashType;
+use plotly::common::Fill;
+use plotly::common::Font;
+use plotly::common::HoverInfo;
+use plotly::common::Line;
+use plotly::common::Marker;
+use plotly::common::Mode;
+use plotly::common::Orientation;
+use plotly::configuration::ModeBarButtonName;
+use plotly::configuration::ToImageButtonOptions;
+use plotly::layout::Axis;
+use plotly::layout::Legend;
+use plotly::layout::Margin;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bounded::Bounded;
+use crate::error::AppError;
+use crate::model::Case;
+use crate::model::RefCyl;
+use crate::model::SurgeonCase;
+use crate::plots::AsPlot;
+use crate::plots::VertexK;
+use crate::plots::mean;
+use crate::plots::se_prediction_error;
+use crate::query::query_select_trend;
+use crate::repository::repository;
+use crate::theme::PlotTheme;
+
+/// The number of consecutive cases averaged for each point of a rolling (learning curve) trend.
+#[derive(Clone, Copy, Debug, PartialEq, RangeBounded)]
+pub struct RollingWindow(#[bounded(range = 2..=500, default = 20)] u32);
+
+/// The length of the periods used to aggregate a trend.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Period {
+    Month,
+    #[default]
+    Quarter,
+}
+
+impl Period {
+    /// Return the first day of the period containing the given date.
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        let month = match self {
+            Self::Month => date.month(),
+            Self::Quarter => (date.month0() / 3) * 3 + 1,
+        };
+
+        // Safety: the first day of any month in a valid date is always valid.
+        NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap_or(date)
+    }
+
+    /// Return a human-readable label for the period starting on the given date.
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Self::Month => start.format("%b %Y").to_string(),
+            Self::Quarter => format!("Q{} {}", start.month0() / 3 + 1, start.year()),
+        }
+    }
+}
+
+/// The outcome measures available for trend analysis.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TrendMetric {
+    /// The absolute spherical equivalent prediction error, in diopters.
+    PredictionError,
+    /// The magnitude of the postoperative refractive cylinder, vertexed to the corneal plane, in
+    /// diopters.
+    Cylinder,
+    /// The percentage of cases with an adverse event.
+    Adverse,
+}
+
+impl TrendMetric {
+    /// Return the value of the metric for a single case. Averaging the values for
+    /// [`TrendMetric::Adverse`] gives the adverse event rate as a percentage.
+    pub fn value(&self, case: &Case) -> f64 {
+        match self {
+            Self::PredictionError => se_prediction_error(case).abs(),
+
+            Self::Cylinder => case
+                .refraction
+                .after
+                .cyl
+                .map(|RefCyl { power, .. }| power.vertex().abs())
+                .unwrap_or(0.0),
+
+            Self::Adverse => {
+                if case.adverse.is_some() {
+                    100.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Return the axis title for the metric.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::PredictionError => "Absolute SE prediction error (D)",
+            Self::Cylinder => "Postop refractive cylinder (vertexed D)",
+            Self::Adverse => "Adverse event rate (%)",
+        }
+    }
+
+    /// Format a value of the metric for display.
+    fn format(&self, value: f64) -> String {
+        match self {
+            Self::PredictionError | Self::Cylinder => format!("{value:.2} D"),
+            Self::Adverse => format!("{value:.1}%"),
+        }
+    }
+}
+
+/// The count, sum, and sum of squares of a metric's values, from which the mean and standard
+/// deviation can be calculated without the values themselves.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MetricSums {
+    pub count: usize,
+    pub sum: f64,
+    pub sum_squares: f64,
+}
+
+/// The [`MetricSums`] as returned by the DB, where `count()` is an `int64`.
+#[derive(gel_derive::Queryable)]
+struct DbMetricSums {
+    count: i64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Queryable for MetricSums {
+    type Args = <DbMetricSums as Queryable>::Args;
+
+    fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8]) -> Result<Self, DecodeError> {
+        let DbMetricSums {
+            count,
+            sum,
+            sum_squares,
+        } = DbMetricSums::decode(decoder, args, buf)?;
+
+        Ok(Self {
+            count: usize::try_from(count).map_err(decode_error)?,
+            sum,
+            sum_squares,
+        })
+    }
+
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Self::Args, DescriptorMismatch> {
+        DbMetricSums::check_descriptor(ctx, type_pos)
+    }
+}
+
+impl MetricSums {
+    pub fn new(values: &[f64]) -> Self {
+        Self {
+            count: values.len(),
+            sum: values.iter().sum(),
+            sum_squares: values.iter().map(|value| value * value).sum(),
+        }
+    }
+
+    pub fn mean(&self) -> f64 { self.sum / self.count as f64 }
+
+    /// The sample standard deviation, or zero for fewer than 2 values (for which the sample
+    /// variance is undefined).
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+
+        let n = self.count as f64;
+        let variance = (self.sum_squares - self.sum * self.sum / n) / (n - 1.0);
+
+        // Rounding can make the variance of (nearly) identical values slightly negative.
+        variance.max(0.0).sqrt()
+    }
+}
+
+/// The sums of each [`TrendMetric`] over the peer cases from a single year. The dates of peer
+/// cases are not available, so the cohort can only be aggregated by year, and the aggregation is
+/// done by the DB so that the peer cases themselves are never fetched.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, gel_derive::Queryable)]
+pub struct CohortYear {
+    pub year: i32,
+    pub prediction_error: MetricSums,
+    pub cylinder: MetricSums,
+    pub adverse: MetricSums,
+}
+
+impl CohortYear {
+    /// Sum the metrics over the given cases, as the DB does in [`query_select_trend`].
+    pub fn new(year: i32, cases: &[&Case]) -> Self {
+        let sums = |metric: TrendMetric| {
+            MetricSums::new(
+                &cases
+                    .iter()
+                    .map(|case| metric.value(case))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        Self {
+            year,
+            prediction_error: sums(TrendMetric::PredictionError),
+            cylinder: sums(TrendMetric::Cylinder),
+            adverse: sums(TrendMetric::Adverse),
+        }
+    }
+
+    pub fn sums(&self, metric: TrendMetric) -> &MetricSums {
+        match metric {
+            TrendMetric::PredictionError => &self.prediction_error,
+            TrendMetric::Cylinder => &self.cylinder,
+            TrendMetric::Adverse => &self.adverse,
+        }
+    }
+}
+
+/// The surgeon's dated cases across all years, and the peer cohort's sums for the same years.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, gel_derive::Queryable)]
+pub struct TrendCases {
+    pub surgeon: Vec<SurgeonCase>,
+    pub cohort: Vec<CohortYear>,
+}
+
+/// The metric, period, and rolling window of a [`Trend`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrendParams {
+    pub metric: TrendMetric,
+    pub period: Period,
+    pub window: RollingWindow,
+}
+
+impl TrendCases {
+    /// Aggregate the cases into a [`Trend`] with the given [`TrendParams`].
+    pub fn trend(&self, params: TrendParams) -> Trend {
+        let TrendParams {
+            metric,
+            period,
+            window,
+        } = params;
+
+        let mut surgeon = self
+            .surgeon
+            .iter()
+            .map(|SurgeonCase { date, case, .. }| (*date, metric.value(case)))
+            .collect::<Vec<_>>();
+
+        surgeon.sort_by_key(|(date, _)| *date);
+
+        let mut periods = BTreeMap::<NaiveDate, Vec<f64>>::new();
+        for (date, value) in &surgeon {
+            periods.entry(period.start(*date)).or_default().push(*value);
+        }
+
+        let periods = periods
+            .into_iter()
+            .map(|(date, values)| TrendPoint {
+                date,
+                count: values.len(),
+                mean: mean(&values),
+            })
+            .collect();
+
+        let window = window.inner() as usize;
+        let rolling = surgeon
+            .windows(window)
+            .map(|cases| {
+                let values = cases.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+
+                TrendPoint {
+                    // Safety: `windows` never yields an empty slice.
+                    date: cases[cases.len() - 1].0,
+                    count: values.len(),
+                    mean: mean(&values),
+                }
+            })
+            .collect();
+
+        let mut cohort = self
+            .cohort
+            .iter()
+            .filter(|year| year.sums(metric).count > 0)
+            .map(|year| {
+                let sums = year.sums(metric);
+
+                CohortBand {
+                    year: year.year,
+                    count: sums.count,
+                    mean: sums.mean(),
+                    std_dev: sums.std_dev(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        cohort.sort_by_key(|band| band.year);
+
+        Trend {
+            metric,
+            period,
+            periods,
+            rolling,
+            cohort,
+        }
+    }
+}
+
+/// A single aggregated value in a trend. The [`date`](Self::date) is the first day of a period, or
+/// the date of the last case in a rolling window.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TrendPoint {
+    pub date: NaiveDate,
+    pub count: usize,
+    pub mean: f64,
+}
+
+/// The cohort mean and standard deviation of a metric for a single year.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CohortBand {
+    pub year: i32,
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+/// An outcome trend for the surgeon, aggregated by period and as a rolling mean over consecutive
+/// cases, with a band showing the cohort mean ± 1 SD for each year.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Trend {
+    pub metric: TrendMetric,
+    pub period: Period,
+    pub periods: Vec<TrendPoint>,
+    pub rolling: Vec<TrendPoint>,
+    pub cohort: Vec<CohortBand>,
+}
+
+impl AsPlot for Trend {
+    fn plot(&self, theme: &PlotTheme) -> Plot {
+        /// Split the cohort bands into x values (the first and last days of each year) and the
+        /// matching y values from the given function, so that each band is drawn as a step.
+        fn steps(cohort: &[CohortBand], f: fn(&CohortBand) -> f64) -> (Vec<String>, Vec<f64>) {
+            cohort
+                .iter()
+                .flat_map(|band| {
+                    [
+                        (format!("{}-01-01", band.year), f(band)),
+                        (format!("{}-12-31", band.year), f(band)),
+                    ]
+                })
+                .unzip()
+        }
+
+        let Self {
+            metric,
+            period,
+            periods,
+            rolling,
+            cohort,
+        } = self;
+
+        let (upper_x, upper_y) = steps(cohort, |band| band.mean + band.std_dev);
+        let (lower_x, lower_y) = steps(cohort, |band| band.mean - band.std_dev);
+        let (mean_x, mean_y) = steps(cohort, |band| band.mean);
+
+        let cohort_upper = Scatter::new(upper_x, upper_y)
+            .name("cohort ± 1 SD")
+            .legend_group("cohort")
+            .mode(Mode::Lines)
+            .line(Line::new().width(0.0))
+            .show_legend(false)
+            .hover_info(HoverInfo::Skip);
+
+        let cohort_lower = Scatter::new(lower_x, lower_y)
+            .name("peer cohort ± 1 SD")
+            .legend_group("cohort")
+            .mode(Mode::Lines)
+            .line(Line::new().width(0.0))
+            .fill(Fill::ToNextY)
+            .fill_color(theme.cohort_band)
+            .hover_info(HoverInfo::Skip);
+
+        let cohort_labels = cohort
+            .iter()
+            .flat_map(
+                |CohortBand {
+                     year, count, mean, ..
+                 }| {
+                    let label = format!("Peers {year}: {} (n = {count})", metric.format(*mean));
+                    [label.clone(), label]
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let cohort_mean = Scatter::new(mean_x, mean_y)
+            .name("peer cohort mean")
+            .legend_group("cohort")
+            .mode(Mode::Lines)
+            .line(Line::new().color(theme.cohort).dash(DashType::Dash))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(cohort_labels);
+
+        let (x, y, labels) = periods.iter().fold(
+            (Vec::new(), Vec::new(), Vec::new()),
+            |(mut x, mut y, mut labels), TrendPoint { date, count, mean }| {
+                x.push(date.to_string());
+                y.push(*mean);
+                labels.push(format!(
+                    "{}: {} (n = {count})",
+                    period.label(*date),
+                    metric.format(*mean)
+                ));
+
+                (x, y, labels)
+            },
+        );
+
+        let surgeon_periods = Scatter::new(x, y)
+            .name(match period {
+                Period::Month => "monthly mean",
+                Period::Quarter => "quarterly mean",
+            })
+            .legend_group("surgeon")
+            .mode(Mode::LinesMarkers)
+            .marker(Marker::new().color(theme.surgeon).size(8))
+            .line(Line::new().color(theme.surgeon))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels);
+
+        let (x, y, labels) = rolling.iter().fold(
+            (Vec::new(), Vec::new(), Vec::new()),
+            |(mut x, mut y, mut labels), TrendPoint { date, count, mean }| {
+                x.push(date.to_string());
+                y.push(*mean);
+                labels.push(format!(
+                    "Last {count} cases to {date}: {}",
+                    metric.format(*mean)
+                ));
+
+                (x, y, labels)
+            },
+        );
+
+        let surgeon_rolling = Scatter::new(x, y)
+            .name("rolling mean")
+            .legend_group("surgeon")
+            .mode(Mode::Lines)
+            .line(Line::new().color(theme.surgeon_centroid).width(1.5))
+            .hover_info(HoverInfo::Text)
+            .hover_text_array(labels);
+
+        let mut plot = Plot::new();
+
+        plot.set_configuration(
+            Configuration::new()
+                .autosizable(true)
+                .display_logo(false)
+                .mode_bar_buttons_to_remove(vec![
+                    ModeBarButtonName::Lasso2d,
+                    ModeBarButtonName::Pan2d,
+                ])
+                .to_image_button_options(
+                    ToImageButtonOptions::new()
+                        .filename("cataract-audit-plot")
+                        .scale(4),
+                ),
+        );
+
+        // The lower band must immediately follow the upper band, to fill the area between them.
+        plot.add_traces(vec![cohort_upper, cohort_lower, cohort_mean]);
+        plot.add_traces(vec![surgeon_periods, surgeon_rolling]);
+
+        let x_axis = Axis::new()
+            .title("Date of surgery")
+            .color(theme.label)
+            .show_line(false)
+            .grid_color(theme.grid);
+
+        let y_axis = Axis::new()
+            .title(metric.title())
+            .color(theme.label)
+            .show_line(false)
+            .zero_line(false)
+            .grid_color(theme.grid);
+
+        let layout = Layout::new()
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .paper_background_color(theme.background)
+            .plot_background_color(theme.background)
+            .margin(Margin::new().top(30).right(5).bottom(0).left(50))
+            .legend(
+                Legend::new()
+                    .font(Font::new().color(theme.legend))
+                    .orientation(Orientation::Horizontal)
+                    .x_anchor(Anchor::Center)
+                    .x(0.5)
+                    .y_anchor(Anchor::Top)
+                    .y(-0.2),
+            );
+
+        plot.set_layout(layout);
+
+        plot
+    }
+}
+
+/// Query the repository for the current surgeon's cases across all years, for trend analysis.
+pub async fn get_trend_cases() -> Result<TrendCases, AppError> { repository()?.trend_cases().await }
+
+// Query the database for trend cases, using a custom [`gel_tokio::Client`]. See
+// [`get_compare_with_client`](crate::plots::get_compare_with_client).
+#[doc(hidden)]
+pub(crate) async fn get_trend_cases_with_client(client: &Client) -> Result<TrendCases, AppError> {
+    if let Some(trend_cases) = client.query_single(query_select_trend(), &()).await? {
+        Ok(trend_cases)
+    } else {
+        Err(AppError::Db(
+            "the query for TrendCases was not successful".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Adverse;
+    use crate::plots::Variance;
+    use crate::plots::variance;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    fn surgeon_case(month: u32, day: u32, adverse: bool) -> SurgeonCase {
+        SurgeonCase {
+            date: date(month, day),
+            case: Case {
+                adverse: adverse.then_some(Adverse::Pc),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_period_start() {
+        assert_eq!(Period::Month.start(date(5, 17)), date(5, 1));
+        assert_eq!(Period::Quarter.start(date(5, 17)), date(4, 1));
+        assert_eq!(Period::Quarter.start(date(12, 31)), date(10, 1));
+        assert_eq!(Period::Quarter.label(date(10, 1)), "Q4 2025");
+    }
+
+    #[test]
+    fn aggregates_trend_by_period_and_window() {
+        let cases = TrendCases {
+            surgeon: vec![
+                surgeon_case(2, 1, true),
+                surgeon_case(1, 1, false),
+                surgeon_case(4, 1, false),
+                surgeon_case(5, 1, false),
+            ],
+            cohort: vec![CohortYear::new(
+                2025,
+                &[
+                    &Case {
+                        adverse: Some(Adverse::Rhexis),
+                        ..Default::default()
+                    },
+                    &Case::default(),
+                ],
+            )],
+        };
+
+        let trend = cases.trend(TrendParams {
+            metric: TrendMetric::Adverse,
+            period: Period::Quarter,
+            window: RollingWindow::new(2).unwrap(),
+        });
+
+        assert_eq!(trend.periods.len(), 2);
+        assert_eq!(trend.periods[0].date, date(1, 1));
+        assert_eq!(trend.periods[0].count, 2);
+        assert!((trend.periods[0].mean - 50.0).abs() < 1e-9);
+        assert!(trend.periods[1].mean.abs() < 1e-9);
+
+        // Cases are sorted by date before the rolling window is applied.
+        let rolling = trend.rolling.iter().map(|p| p.mean).collect::<Vec<_>>();
+        assert_eq!(rolling, vec![50.0, 50.0, 0.0]);
+        assert_eq!(trend.rolling[0].date, date(2, 1));
+
+        assert_eq!(trend.cohort.len(), 1);
+        assert!((trend.cohort[0].mean - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn matches_sample_stats_from_sums() {
+        let values = [0.25, 0.5, 1.0, 0.75];
+        let sums = MetricSums::new(&values);
+
+        assert!((sums.mean() - mean(&values)).abs() < 1e-9);
+        assert!((sums.std_dev() - variance(&values, Variance::Sample).sqrt()).abs() < 1e-9);
+
+        assert!(MetricSums::new(&[0.5]).std_dev().abs() < 1e-9);
+        assert!(MetricSums::new(&[0.1, 0.1, 0.1]).std_dev().abs() < 1e-9);
+    }
+}
@@ -1,8 +1,13 @@
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::bounded::Bounded;
+use crate::model::Biometry;
 use crate::model::RefCylPower;
 use crate::model::RefSph;
 use crate::model::TargetCylPower;
 use crate::model::TargetSe;
+use crate::model::ToricPower;
 
 /// A marker trait for measurements with the following properties:
 ///
@@ -39,3 +44,100 @@ impl<T: Spectacle> VertexK for T {
         spectacle / (1.0 - (spectacle * 0.013))
     }
 }
+
+/// The refractive index of aqueous humour, as used in the Haigis formula.
+const AQUEOUS_INDEX: f64 = 1.336;
+
+/// The method used to convert a toric IOL cylinder from the IOL plane to the corneal plane.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ToricConversion {
+    /// Divide the IOL-plane cylinder by a fixed ratio. A ratio of 1.46 is the commonly-quoted
+    /// average for a phakic eye of typical dimensions.
+    Ratio(f64),
+    /// Calculate the ratio for each eye, from its biometry and an effective lens position (ELP)
+    /// estimated with the Haigis regression, using the given (optimized) A-constant.
+    Elp { a_constant: f64 },
+}
+
+impl Default for ToricConversion {
+    fn default() -> Self { Self::Ratio(1.46) }
+}
+
+impl ToricConversion {
+    /// Return the ratio of IOL-plane cylinder to corneal-plane cylinder for the given
+    /// [`Biometry`].
+    ///
+    /// For [`ToricConversion::Elp`], we use the first-order vergence relation:
+    ///
+    /// Ratio = 1 / (1 - (ELP in meters)(mean K) / n(aqueous))²
+    ///
+    /// where ELP = a0 + (0.4)(ACD) + (0.1)(AL), in mm, and a0 = (0.62467)(A-constant) - 72.434.
+    pub fn ratio(&self, biometry: &Biometry) -> f64 {
+        match self {
+            Self::Ratio(ratio) => *ratio,
+
+            Self::Elp { a_constant } => {
+                let Biometry { al, ks, acd, .. } = biometry;
+                let a0 = (0.62467 * a_constant) - 72.434;
+                let acd = f64::from(acd.inner()) / 100.0;
+                let al = f64::from(al.inner()) / 100.0;
+                let elp = (a0 + (0.4 * acd) + (0.1 * al)) / 1000.0;
+                let k = f64::from(ks.flat_power() + ks.steep_power()) / 200.0;
+
+                1.0 / (1.0 - (elp * k / AQUEOUS_INDEX)).powi(2)
+            }
+        }
+    }
+}
+
+/// Types that can be converted from the IOL plane to the corneal plane. Unlike [`VertexK`], the
+/// conversion depends on the dimensions of the eye, so the [`Biometry`] must be supplied.
+pub trait IolToCorneal {
+    /// Returns the power in diopters at the corneal plane.
+    fn corneal(&self, biometry: &Biometry, conversion: &ToricConversion) -> f64;
+}
+
+impl IolToCorneal for ToricPower {
+    fn corneal(&self, biometry: &Biometry, conversion: &ToricConversion) -> f64 {
+        f64::from(self.inner()) / 100.0 / conversion.ratio(biometry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Acd;
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 1e-9 }
+
+    #[test]
+    fn converts_toric_power_with_fixed_ratio() {
+        let power = ToricPower::new(150).unwrap();
+        let conversion = ToricConversion::Ratio(1.5);
+
+        assert!(close(power.corneal(&Biometry::default(), &conversion), 1.0));
+    }
+
+    #[test]
+    fn elp_ratio_is_plausible_for_typical_eye() {
+        // Default biometry: AL 24.00 mm, ACD 3.50 mm, K 44.00 D.
+        let ratio = ToricConversion::Elp { a_constant: 119.0 }.ratio(&Biometry::default());
+
+        assert!((1.3..1.6).contains(&ratio), "{ratio}");
+    }
+
+    #[test]
+    fn elp_ratio_increases_with_deeper_lens_position() {
+        let conversion = ToricConversion::Elp { a_constant: 119.0 };
+        let shallow = Biometry {
+            acd: Acd::new(250).unwrap(),
+            ..Default::default()
+        };
+        let deep = Biometry {
+            acd: Acd::new(450).unwrap(),
+            ..Default::default()
+        };
+
+        assert!(conversion.ratio(&deep) > conversion.ratio(&shallow));
+    }
+}
@@ -1,23 +1,82 @@
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Utc;
+use gel_protocol::named_args;
+use gel_protocol::value_opt::ValueOpt;
+
 use crate::bounded::Bounded;
+use crate::model::CaseFilter;
+use crate::model::Cohort;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::ReportFrequency;
+use crate::model::Surgeon;
+use crate::model::SurgeonConstant;
+use crate::model::SurgeonDefaults;
 use crate::model::Year;
 
-pub fn query_select_compare(year: Year) -> String {
-    format!(
+/// Named arguments for a query, keyed by parameter name (without the `$`). Values are sent to Gel
+/// separately from the query text, so user input can never change the meaning of a query. Pass them
+/// to the client along with the query, such as `client.query::<Surgeon, _>(query, &args)` or
+/// `client.query_single::<Surgeon, _>(query, &args)`.
+pub type NamedArgs = HashMap<&'static str, ValueOpt>;
+
+/// The surgeon whose cases a query selects.
+#[derive(Clone, Copy, Debug)]
+pub enum QuerySurgeon<'a> {
+    /// The surgeon identified by the auth token of the current request.
+    Current,
+    /// The surgeon with the given email, for server tasks (such as report emails) that run without
+    /// an auth token, on a client that doesn't apply access policies.
+    Email(&'a str),
+}
+
+impl QuerySurgeon<'_> {
+    /// Return an EdgeQL condition matching a `SurgeonCas` to the surgeon, and add its value to
+    /// `args`.
+    fn condition(&self, args: &mut NamedArgs) -> &'static str {
+        match self {
+            Self::Current => ".surgeon = global cur_surgeon",
+            Self::Email(email) => {
+                args.insert("email", email.to_string().into());
+                ".surgeon.email = <str>$email"
+            }
+        }
+    }
+}
+
+/// Select the current surgeon's cases from the given year, along with the cases of their peers
+/// from the same year, restricted to the subgroup in the [`CaseFilter`].
+pub fn query_select_compare(year: Year, filter: &CaseFilter) -> (String, NamedArgs) {
+    let mut args = named_args! { "year" => i64::from(year.inner()) };
+
+    let (surgeon_filter, surgeon_args) = case_filter(filter, ".cas", Some(".site"));
+    let (cohort_filter, cohort_args) = case_filter(filter, "", None);
+    args.extend(surgeon_args.into_iter().chain(cohort_args));
+
+    let query = format!(
         r#"
 with
     QuerySurgeonCas := (
-        select SurgeonCas filter .surgeon = global cur_surgeon and .cas.year = {year}
+        select SurgeonCas
+        filter .surgeon = global cur_surgeon and .cas.year = <int64>$year{surgeon_filter}
     ),
 
-    QueryCohortCas := (select Cas except QuerySurgeonCas.cas filter .year = {year})
+    QueryCohortCas := (
+        with surgeon_cases := (select SurgeonCas filter .surgeon = global cur_surgeon)
+
+        select Cas except surgeon_cases.cas
+        filter .year = <int64>$year{cohort_filter}
+    )
 
 select {{
     surgeon := QuerySurgeonCas {{
         number,
-        side,
         date,
         site: {{ name }},
-        cas: {{
+        case := .cas {{
             side,
 
             biometry: {{
@@ -28,6 +87,8 @@ select {{
                 cct,
                 wtw
             }},
+
+            ks_after: {{ flat: {{ power, axis }}, steep: {{ power, axis }} }},
             
             target: {{
                 formula,
@@ -36,7 +97,6 @@ select {{
                 cyl: {{ power, axis }}
             }},
             
-            year,
             main,
             sia: {{ power, axis }},
             
@@ -72,6 +132,8 @@ select {{
             cct,
             wtw
         }},
+
+        ks_after: {{ flat: {{ power, axis }}, steep: {{ power, axis }} }},
         
         target: {{
             formula,
@@ -80,7 +142,6 @@ select {{
             cyl: {{ power, axis }}
         }},
         
-        year,
         main,
         sia: {{ power, axis }},
         
@@ -105,23 +166,34 @@ select {{
     }}
 }};
         "#
-    )
+    );
+
+    (query, args)
 }
 
-pub fn query_select_self_compare(year: Year) -> String {
-    let prior_year = year.inner() - 1;
+/// Select the current surgeon's cases from the given year, along with their own cases from the
+/// prior year, restricted to the subgroup in the [`CaseFilter`].
+pub fn query_select_self_compare(year: Year, filter: &CaseFilter) -> (String, NamedArgs) {
+    let mut args = named_args! {
+        "year" => i64::from(year.inner()),
+        "prior_year" => i64::from(year.inner()) - 1,
+    };
 
-    format!(
+    let (surgeon_filter, surgeon_args) = case_filter(filter, ".cas", Some(".site"));
+    args.extend(surgeon_args);
+
+    let query = format!(
         r#"
 with
     QuerySurgeonCas := (
-        select SurgeonCas filter .surgeon = global cur_surgeon and .cas.year = {year}
+        select SurgeonCas
+        filter .surgeon = global cur_surgeon and .cas.year = <int64>$year{surgeon_filter}
     ),
 
     QueryCohortCas := (
         with cases := (
             select SurgeonCas
-            filter .surgeon = global cur_surgeon and .cas.year = {prior_year}
+            filter .surgeon = global cur_surgeon and .cas.year = <int64>$prior_year{surgeon_filter}
         )
 
         select cases.cas
@@ -130,10 +202,180 @@ with
 select {{
     surgeon := QuerySurgeonCas {{
         number,
+        date,
+        site: {{ name }},
+        case := .cas {{
+            side,
+
+            biometry: {{
+                al,
+                ks: {{ flat: {{ power, axis}}, steep: {{ power, axis }} }},
+                acd,
+                lt,
+                cct,
+                wtw
+            }},
+
+            ks_after: {{ flat: {{ power, axis }}, steep: {{ power, axis }} }},
+            
+            target: {{
+                formula,
+                custom_constant,
+                se,
+                cyl: {{ power, axis }}
+            }},
+            
+            main,
+            sia: {{ power, axis }},
+            
+            iol: {{
+                iol: {{ model, name, company, focus, toric }},
+                se,
+                axis
+            }},
+
+            adverse,
+
+            va: {{
+                before: {{ best: {{ num, den }}, raw: {{ num, den }} }},
+                after: {{ best: {{ num, den }}, raw: {{ num, den }} }}
+            }},
+
+            refraction: {{
+                before: {{ sph, cyl: {{ power, axis }} }},
+                after: {{ sph, cyl: {{ power, axis }} }},
+            }}
+
+        }}
+    }},
+
+    cohort := QueryCohortCas {{
         side,
+
+        biometry: {{
+            al,
+            ks: {{ flat: {{ power, axis}}, steep: {{ power, axis }} }},
+            acd,
+            lt,
+            cct,
+            wtw
+        }},
+
+        ks_after: {{ flat: {{ power, axis }}, steep: {{ power, axis }} }},
+        
+        target: {{
+            formula,
+            custom_constant,
+            se,
+            cyl: {{ power, axis }}
+        }},
+        
+        main,
+        sia: {{ power, axis }},
+        
+        iol: {{
+            iol: {{ model, name, company, focus, toric }},
+            se,
+            axis
+        }},
+
+        adverse,
+
+        va: {{
+            before: {{ best: {{ num, den }}, raw: {{ num, den }} }},
+            after: {{ best: {{ num, den }}, raw: {{ num, den }} }}
+        }},
+
+        refraction: {{
+            before: {{ sph, cyl: {{ power, axis }} }},
+            after: {{ sph, cyl: {{ power, axis }} }},
+        }}
+
+    }}
+}};
+        "#
+    );
+
+    (query, args)
+}
+
+/// Select the surgeon's cases from the given [`DateRange`], along with either the peer cohort's
+/// cases from the same years (which have a year, but not a date), or the surgeon's own cases from
+/// outside the range. Both are restricted to the subgroup in the [`CaseFilter`].
+pub fn query_select_range_compare(
+    params: &CompareParams<DateRange>,
+    surgeon: QuerySurgeon,
+) -> (String, NamedArgs) {
+    let CompareParams {
+        period,
+        cohort,
+        filter,
+    } = params;
+
+    let (start, end) = (period.start(), period.end());
+
+    let mut args = named_args! {
+        "start" => start.to_string(),
+        "end" => end.to_string(),
+    };
+
+    let surgeon = surgeon.condition(&mut args);
+
+    let (surgeon_filter, surgeon_args) = case_filter(filter, ".cas", Some(".site"));
+    let (cohort_filter, cohort_args) = case_filter(filter, "", None);
+    args.extend(surgeon_args.into_iter().chain(cohort_args));
+
+    let cohort_cas = match cohort {
+        Cohort::Peers => {
+            args.extend(named_args! {
+                "start_year" => i64::from(start.year()),
+                "end_year" => i64::from(end.year()),
+            });
+
+            format!(
+                r#"(
+        with surgeon_cases := (select SurgeonCas filter {surgeon})
+
+        select Cas except surgeon_cases.cas
+        filter .year >= <int64>$start_year and .year <= <int64>$end_year{cohort_filter}
+    )"#
+            )
+        }
+        Cohort::Surgeon => format!(
+            r#"(
+        with cases := (
+            select SurgeonCas
+            filter {surgeon}
+                and (.date < range_start or .date > range_end)
+                {surgeon_filter}
+        )
+
+        select cases.cas
+    )"#
+        ),
+    };
+
+    let query = format!(
+        r#"
+with
+    range_start := <cal::local_date><str>$start,
+    range_end := <cal::local_date><str>$end,
+
+    QuerySurgeonCas := (
+        select SurgeonCas
+        filter {surgeon}
+            and .date >= range_start
+            and .date <= range_end{surgeon_filter}
+    ),
+
+    QueryCohortCas := {cohort_cas}
+
+select {{
+    surgeon := QuerySurgeonCas {{
+        number,
         date,
         site: {{ name }},
-        cas: {{
+        case := .cas {{
             side,
 
             biometry: {{
@@ -144,6 +386,8 @@ select {{
                 cct,
                 wtw
             }},
+
+            ks_after: {{ flat: {{ power, axis }}, steep: {{ power, axis }} }},
             
             target: {{
                 formula,
@@ -152,7 +396,6 @@ select {{
                 cyl: {{ power, axis }}
             }},
             
-            year,
             main,
             sia: {{ power, axis }},
             
@@ -188,6 +431,8 @@ select {{
             cct,
             wtw
         }},
+
+        ks_after: {{ flat: {{ power, axis }}, steep: {{ power, axis }} }},
         
         target: {{
             formula,
@@ -196,7 +441,6 @@ select {{
             cyl: {{ power, axis }}
         }},
         
-        year,
         main,
         sia: {{ power, axis }},
         
@@ -221,5 +465,636 @@ select {{
     }}
 }};
         "#
+    );
+
+    (query, args)
+}
+
+/// Return EdgeQL conditions restricting cases to the subgroup in the [`CaseFilter`], each prefixed
+/// with `and`, so they can be appended to an existing `filter` clause, along with their values, to
+/// be merged into the query's args. `cas` is the path from the filtered object to its `Cas` (such
+/// as `.cas` for a `SurgeonCas`, or an empty string for a `Cas`), and `site` is the path to its
+/// `Site`, if it has one.
+fn case_filter(filter: &CaseFilter, cas: &str, site: Option<&str>) -> (String, NamedArgs) {
+    let CaseFilter {
+        iol,
+        focus,
+        toric,
+        formula,
+        site: site_name,
+        side,
+        al_min,
+        al_max,
+    } = filter;
+
+    let (mut conditions, mut args) = (Vec::new(), NamedArgs::new());
+
+    if let Some(iol) = iol {
+        conditions.push(format!("{cas}.iol.iol.model = <str>$iol"));
+        args.insert("iol", iol.clone().into());
+    }
+
+    if let Some(focus) = focus {
+        conditions.push(format!("{cas}.iol.iol.focus = <Focus><str>$focus"));
+        args.insert("focus", focus.to_string().into());
+    }
+
+    if let Some(toric) = toric {
+        let exists = if *toric { "exists" } else { "not exists" };
+        conditions.push(format!("{exists} {cas}.iol.iol.toric"));
+    }
+
+    if let Some(formula) = formula {
+        conditions.push(format!("{cas}.target.formula = <Formula><str>$formula"));
+        args.insert("formula", formula.to_string().into());
+    }
+
+    if let (Some(site), Some(name)) = (site, site_name) {
+        conditions.push(format!("{site}.name = <str>$site"));
+        args.insert("site", name.clone().into());
+    }
+
+    if let Some(side) = side {
+        conditions.push(format!("{cas}.side = <Side><str>$side"));
+        args.insert("side", side.to_string().into());
+    }
+
+    if let Some(al) = al_min {
+        conditions.push(format!("{cas}.biometry.al >= <int64>$al_min"));
+        args.insert("al_min", i64::from(al.inner()).into());
+    }
+
+    if let Some(al) = al_max {
+        conditions.push(format!("{cas}.biometry.al <= <int64>$al_max"));
+        args.insert("al_max", i64::from(al.inner()).into());
+    }
+
+    let conditions = conditions
+        .into_iter()
+        .map(|condition| format!(" and {condition}"))
+        .collect();
+
+    (conditions, args)
+}
+
+/// Select all of the current surgeon's cases (for any year), ordered by date, along with the sums
+/// of each [`TrendMetric`](crate::plots::TrendMetric) over their peers' cases from the same years
+/// (see [`CohortYear`](crate::plots::CohortYear)). Peer cases only carry their `year`, as the date
+/// is only available on the (access-restricted) `SurgeonCas`, and they are aggregated here rather
+/// than fetched.
+pub fn query_select_trend() -> String {
+    String::from(
+        r#"
+with
+    QuerySurgeonCas := (
+        select SurgeonCas filter .surgeon = global cur_surgeon order by .date
+    ),
+
+    QueryCohortCas := (
+        select Cas except QuerySurgeonCas.cas filter .year in QuerySurgeonCas.cas.year
+    ),
+
+    QueryCohortYears := (group QueryCohortCas using year := .year by year)
+
+select {
+    surgeon := QuerySurgeonCas {
+        number,
+        side,
+        date,
+        site: { name },
+        cas: {
+            side,
+
+            biometry: {
+                al,
+                ks: { flat: { power, axis}, steep: { power, axis } },
+                acd,
+                lt,
+                cct,
+                wtw
+            },
+
+            ks_after: { flat: { power, axis }, steep: { power, axis } },
+            
+            target: {
+                formula,
+                custom_constant,
+                se,
+                cyl: { power, axis }
+            },
+            
+            year,
+            main,
+            sia: { power, axis },
+            
+            iol: {
+                iol: { model, name, company, focus, toric },
+                se,
+                axis
+            },
+
+            adverse,
+
+            va: {
+                before: { best: { num, den }, raw: { num, den } },
+                after: { best: { num, den }, raw: { num, den } }
+            },
+
+            refraction: {
+                before: { sph, cyl: { power, axis } },
+                after: { sph, cyl: { power, axis } },
+            }
+
+        }
+    },
+
+    cohort := (
+        for year_group in QueryCohortYears union (
+            with
+                cases := year_group.elements,
+
+                prediction_error := (
+                    for cas in cases union math::abs(
+                        cas.refraction.after.sph
+                        + (cas.refraction.after.cyl.power ?? 0) / 2
+                        - cas.target.se
+                    ) / 100
+                ),
+
+                cylinder := (
+                    for cas in cases union (
+                        with spectacle := (cas.refraction.after.cyl.power ?? 0) / 100
+                        select math::abs(spectacle / (1 - spectacle * 0.013))
+                    )
+                ),
+
+                adverse := (for cas in cases union (100.0 if exists cas.adverse else 0.0))
+
+            select {
+                year := year_group.key.year,
+
+                prediction_error := {
+                    count := count(prediction_error),
+                    sum := sum(prediction_error),
+                    sum_squares := sum(prediction_error ^ 2)
+                },
+
+                cylinder := {
+                    count := count(cylinder),
+                    sum := sum(cylinder),
+                    sum_squares := sum(cylinder ^ 2)
+                },
+
+                adverse := {
+                    count := count(adverse),
+                    sum := sum(adverse),
+                    sum_squares := sum(adverse ^ 2)
+                }
+            }
+        )
+    )
+};
+        "#,
+    )
+}
+
+/// Insert the current surgeon's optimized constant for an IOL model and formula, replacing any
+/// existing constant for that pair.
+pub fn query_upsert_surgeon_constant(constant: &SurgeonConstant) -> (String, NamedArgs) {
+    let SurgeonConstant {
+        iol,
+        formula,
+        constant,
+        lower,
+        upper,
+        cases,
+    } = constant;
+
+    let query = String::from(
+        r#"
+with
+    constant := <float64>$constant,
+    lower := <float64>$lower,
+    upper := <float64>$upper,
+    cases := <int32><int64>$cases
+
+insert SurgeonConstant {
+    surgeon := global cur_surgeon,
+    iol := assert_exists((select Iol filter .model = <str>$iol)),
+    formula := <Formula><str>$formula,
+    constant := constant,
+    lower := lower,
+    upper := upper,
+    cases := cases
+}
+unless conflict on (.surgeon, .iol, .formula)
+else (
+    update SurgeonConstant set {
+        constant := constant,
+        lower := lower,
+        upper := upper,
+        cases := cases
+    }
+);
+        "#,
+    );
+
+    let args = named_args! {
+        "iol" => iol.clone(),
+        "formula" => formula.to_string(),
+        "constant" => *constant,
+        "lower" => *lower,
+        "upper" => *upper,
+        "cases" => i64::from(*cases),
+    };
+
+    (query, args)
+}
+
+/// Select the current surgeon's optimized constants, ordered by IOL model and formula.
+pub fn query_select_surgeon_constants() -> String {
+    String::from(
+        r#"
+select SurgeonConstant {
+    iol := .iol.model,
+    formula,
+    constant,
+    lower,
+    upper,
+    cases
+}
+filter .surgeon = global cur_surgeon
+order by .iol.model then .formula;
+        "#,
+    )
+}
+
+/// Select the surgeon identified by the auth token of the current client.
+pub fn query_select_current_surgeon() -> String {
+    String::from(
+        r#"
+select global cur_surgeon {
+    email,
+    terms,
+    full_name,
+    preferred_name,
+
+    defaults: {
+        site: { name },
+        iol: { model, name, company, focus, toric },
+        formula,
+        custom_constant,
+        main
+    },
+
+    sia: { right: { power, axis }, left: { power, axis } },
+    report_frequency
+};
+        "#,
+    )
+}
+
+/// Insert the surgeon identified by the auth token of the current client, along with their
+/// defaults, or select the existing surgeon if one with the same email has already signed up. The
+/// default IOL is only linked if it is already in the DB.
+pub fn query_insert_surgeon(surgeon: &Surgeon) -> (String, NamedArgs) {
+    let Surgeon {
+        email,
+        full_name,
+        preferred_name,
+        defaults,
+        sia,
+        ..
+    } = surgeon;
+
+    let SurgeonDefaults {
+        site,
+        iol,
+        formula,
+        custom_constant,
+        main,
+    } = defaults.clone().unwrap_or_default();
+
+    let query = String::from(
+        r#"
+with QuerySurgeon := (insert Surgeon {
+        identity := (select global ext::auth::ClientTokenIdentity),
+        email := <str>$email,
+        full_name := <optional str>$full_name,
+        preferred_name := <optional str>$preferred_name,
+
+        defaults := (select (insert SurgeonDefaults {
+            site := (
+                for name in <optional str>$default_site union (
+                    select (insert Site {
+                        name := name
+                    } unless conflict on .name else (select Site))
+                )
+            ),
+
+            iol := (select Iol filter .model = <optional str>$default_iol),
+            formula := <Formula><optional str>$default_formula,
+            custom_constant := <bool>$custom_constant,
+            main := <int64>$main
+        })),
+
+        sia := (select(insert SurgeonSia {
+            right := (select(insert Sia {
+                power := <int64>$sia_right_power, axis := <int64>$sia_right_axis
+            })),
+
+            left := (select(insert Sia {
+                power := <int64>$sia_left_power, axis := <int64>$sia_left_axis
+            }))
+        }))
+    } unless conflict on .email else (select Surgeon))
+
+select QuerySurgeon {
+    email,
+    terms,
+    full_name,
+    preferred_name,
+
+    defaults: {
+        site: { name },
+        iol: { model, name, company, focus, toric },
+        formula,
+        custom_constant,
+        main
+    },
+
+    sia: { right: { power, axis }, left: { power, axis } },
+    report_frequency
+};
+        "#,
+    );
+
+    let args = named_args! {
+        "email" => email.inner(),
+        "full_name" => full_name.clone(),
+        "preferred_name" => preferred_name.clone(),
+        "default_site" => site.map(|site| site.name),
+        "default_iol" => iol.map(|iol| iol.model),
+        "default_formula" => formula.map(|formula| formula.to_string()),
+        "custom_constant" => custom_constant,
+        "main" => i64::from(main.inner()),
+        "sia_right_power" => i64::from(sia.right.power.inner()),
+        "sia_right_axis" => i64::from(sia.right.axis.inner()),
+        "sia_left_power" => i64::from(sia.left.power.inner()),
+        "sia_left_axis" => i64::from(sia.left.axis.inner()),
+    };
+
+    (query, args)
+}
+
+/// Record that the surgeon identified by the auth token of the current client has agreed to the
+/// terms.
+pub fn query_update_terms() -> String {
+    // In theory, you could select only the terms field and update that, rather than replacing the
+    // entire Surgeon here, but that can be optimized later.
+    String::from(
+        r#"
+select (
+    update Surgeon
+    filter .identity = (select global ext::auth::ClientTokenIdentity)
+    set { terms := datetime_current() }
+) {
+    email,
+    terms,
+    full_name,
+    preferred_name,
+
+    defaults: {
+        site: { name },
+        iol: { model, name, company, focus, toric },
+        formula,
+        custom_constant,
+        main
+    },
+
+    sia: { right: { power, axis }, left: { power, axis } },
+    report_frequency
+};
+        "#,
     )
 }
+
+/// Select all IOLs.
+pub fn query_select_iols() -> String {
+    String::from("select Iol { model, name, company, focus, toric } order by .model;")
+}
+
+/// Select the IOL with the given model.
+pub fn query_select_iol(model: &str) -> (String, NamedArgs) {
+    let query = String::from(
+        r#"
+select Iol {
+    model, name, company, focus, toric
+} filter .model = <str>$model;
+        "#,
+    );
+
+    (query, named_args! { "model" => model.to_string() })
+}
+
+/// Select all sites.
+pub fn query_select_sites() -> String { String::from("select Site { name } order by .name;") }
+
+/// Select every surgeon who has opted in to report emails, with when their last report was sent.
+/// Surgeons are selected directly rather than through `cur_surgeon`, as this runs without a
+/// surgeon's auth token.
+pub fn query_select_report_surgeons() -> String {
+    String::from(
+        r#"
+select Surgeon {
+    surgeon := Surgeon {
+        email,
+        terms,
+        full_name,
+        preferred_name,
+
+        defaults: {
+            site: { name },
+            iol: { model, name, company, focus, toric },
+            formula,
+            custom_constant,
+            main
+        },
+
+        sia: { right: { power, axis }, left: { power, axis } },
+        report_frequency
+    },
+    report_sent
+}
+filter exists .report_frequency
+order by .email;
+        "#,
+    )
+}
+
+/// Record that the surgeon's report for the current period has been attempted, but only if the
+/// last attempt is still `last_sent`. Returns the number of surgeons updated, which is 0 if another
+/// check has already claimed the report, so that each report is attempted at most once.
+pub fn query_update_report_sent(
+    email: &str,
+    last_sent: Option<DateTime<Utc>>,
+) -> (String, NamedArgs) {
+    let query = String::from(
+        r#"
+select count((
+    update Surgeon
+    filter .email = <str>$email
+        and .report_sent ?= <datetime><optional str>$last_sent
+    set { report_sent := datetime_current() }
+));
+        "#,
+    );
+
+    let args = named_args! {
+        "email" => email.to_string(),
+        "last_sent" => last_sent.map(|sent| sent.to_rfc3339()),
+    };
+
+    (query, args)
+}
+
+/// Set how often the current surgeon receives report emails, where [`None`] opts out. Returns the
+/// updated surgeon.
+pub fn query_update_report_frequency(frequency: Option<ReportFrequency>) -> (String, NamedArgs) {
+    let query = String::from(
+        r#"
+select (
+    update Surgeon
+    filter Surgeon = global cur_surgeon
+    set { report_frequency := <ReportFrequency><optional str>$frequency }
+) {
+    email,
+    terms,
+    full_name,
+    preferred_name,
+
+    defaults: {
+        site: { name },
+        iol: { model, name, company, focus, toric },
+        formula,
+        custom_constant,
+        main
+    },
+
+    sia: { right: { power, axis }, left: { power, axis } },
+    report_frequency
+};
+        "#,
+    );
+
+    let args = named_args! {
+        "frequency" => frequency.map(|frequency| frequency.to_string()),
+    };
+
+    (query, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Al;
+    use crate::model::Formula;
+    use crate::model::Side;
+
+    #[test]
+    fn builds_case_filter_conditions() {
+        let filter = CaseFilter {
+            toric: Some(true),
+            formula: Some(Formula::Barrett),
+            site: Some(String::from("Eye Hospital")),
+            side: Some(Side::Left),
+            al_min: Some(Al::new(2200).unwrap()),
+            ..Default::default()
+        };
+
+        let (conditions, args) = case_filter(&filter, ".cas", Some(".site"));
+
+        assert_eq!(
+            conditions,
+            " and exists .cas.iol.iol.toric and .cas.target.formula = <Formula><str>$formula and \
+             .site.name = <str>$site and .cas.side = <Side><str>$side and \
+             .cas.biometry.al >= <int64>$al_min"
+        );
+
+        let mut keys = args.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, ["al_min", "formula", "side", "site"]);
+
+        // The cohort has no site.
+        let (conditions, args) = case_filter(&filter, "", None);
+        assert!(!conditions.contains("site"));
+        assert!(!args.contains_key("site"));
+
+        let (conditions, args) = case_filter(&CaseFilter::default(), ".cas", Some(".site"));
+        assert!(conditions.is_empty());
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn selects_report_surgeon_by_email() {
+        for cohort in [Cohort::Peers, Cohort::Surgeon] {
+            let params = CompareParams {
+                cohort,
+                ..Default::default()
+            };
+
+            let (query, args) =
+                query_select_range_compare(&params, QuerySurgeon::Email("surgeon@example.com"));
+
+            assert!(!query.contains("cur_surgeon"));
+            assert!(query.contains(".surgeon.email = <str>$email"));
+            assert!(!query.contains("surgeon@example.com"));
+            assert!(args.contains_key("email"));
+
+            let (query, args) = query_select_range_compare(&params, QuerySurgeon::Current);
+
+            assert!(query.contains(".surgeon = global cur_surgeon"));
+            assert!(!args.contains_key("email"));
+        }
+    }
+
+    #[test]
+    fn passes_hostile_strings_as_arguments() {
+        let hostile = [
+            r#"Eye" or true or "x"#,
+            r#"\"; delete Surgeon; select "#,
+            "{ } filter select insert update",
+            "' union (select Surgeon { email }) '",
+        ];
+
+        for value in hostile {
+            let filter = CaseFilter {
+                iol: Some(value.to_string()),
+                site: Some(value.to_string()),
+                ..Default::default()
+            };
+
+            for (query, args) in [
+                query_select_compare(Year::new(2025).unwrap(), &filter),
+                query_select_self_compare(Year::new(2025).unwrap(), &filter),
+            ] {
+                assert!(!query.contains(value));
+                assert!(query.contains("<str>$iol") && args.contains_key("iol"));
+                assert!(query.contains("<str>$site") && args.contains_key("site"));
+            }
+
+            let constant = SurgeonConstant {
+                iol: value.to_string(),
+                formula: Formula::Kane,
+                constant: 119.1,
+                lower: 118.9,
+                upper: 119.3,
+                cases: 40,
+            };
+
+            let (query, args) = query_upsert_surgeon_constant(&constant);
+            assert!(!query.contains(value));
+            assert!(query.contains("<str>$iol") && args.contains_key("iol"));
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Scheduled outcome report emails. Surgeons opt in on their profile by choosing a
+//! [`ReportFrequency`], and each report summarizes their cases from the most recent complete period
+//! against the peer cohort, with the plots attached as SVG files.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Utc;
+use gel_derive::Queryable;
+use leptos::logging::log;
+
+use crate::error::AppError;
+use crate::mail::EmailType;
+use crate::mail::MailAttachment;
+use crate::mail::Mailer;
+use crate::mail::transactional_email_with_mailer;
+use crate::model::DateRange;
+use crate::model::ReportFrequency;
+use crate::model::Surgeon;
+use crate::plots::AsSvg;
+use crate::plots::CaseCompare;
+use crate::plots::describe_significance;
+use crate::repository::Repository;
+use crate::theme::PlotTheme;
+
+/// How often to check for surgeons whose report is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// An outcome report for a single surgeon and period, ready to be sent with
+/// [`EmailType::Report`].
+#[derive(Clone, Debug)]
+pub struct OutcomeReport {
+    pub frequency: ReportFrequency,
+    pub period: DateRange,
+    /// The key statistics against the peer cohort, as plain text.
+    pub summary: String,
+    pub attachments: Vec<MailAttachment>,
+}
+
+impl OutcomeReport {
+    /// Summarize the surgeon's cases for the period against the peer cohort. Returns [`None`] if
+    /// the surgeon has no cases in the period.
+    pub fn new(
+        frequency: ReportFrequency,
+        period: DateRange,
+        compare: &CaseCompare,
+    ) -> Option<Self> {
+        let prediction = compare.prediction_error();
+
+        if prediction.surgeon.errors.is_empty() {
+            return None;
+        }
+
+        let (cyl_after, adverse) = (compare.polar_cyl_after(), compare.adverse());
+
+        let summary = [
+            format!(
+                "Cases: {} (peer cohort: {}).",
+                prediction.surgeon.errors.len(),
+                prediction.cohort.errors.len()
+            ),
+            format!(
+                "Spherical equivalent prediction error. Surgeon: {}. Peer cohort: {}. {}",
+                prediction.surgeon,
+                prediction.cohort,
+                describe_significance(&prediction.significance())
+            ),
+            format!(
+                "Postop refractive astigmatism. {}",
+                describe_significance(&cyl_after.significance())
+            ),
+            format!(
+                "Adverse events. Surgeon: {}. Peer cohort: {}. {}",
+                adverse.surgeon.rate(None),
+                adverse.cohort.rate(None),
+                describe_significance(&adverse.significance())
+            ),
+        ]
+        .join("\n\n");
+
+        // Email clients display attachments on a light background.
+        let theme = PlotTheme::LIGHT;
+
+        let attachment = |filename: &str, content: String| MailAttachment {
+            filename: filename.to_string(),
+            content_type: String::from("image/svg+xml"),
+            content: content.into_bytes(),
+        };
+
+        let attachments = vec![
+            attachment("postop-astigmatism.svg", cyl_after.svg(&theme)),
+            attachment(
+                "astigmatism-magnitude.svg",
+                compare.cartesian_delta_cyl().svg(&theme),
+            ),
+        ];
+
+        Some(Self {
+            frequency,
+            period,
+            summary,
+            attachments,
+        })
+    }
+
+    pub fn subject(&self) -> String {
+        format!(
+            "Your {} cataract audit report: {}",
+            self.frequency.label().to_lowercase(),
+            self.period_label()
+        )
+    }
+
+    /// The plain text body of the email, after the greeting.
+    pub fn body(&self) -> String {
+        format!(
+            "Here is your {} cataract audit report, for your cases from {}, compared with the \
+             peer cohort's cases from {}.\n\n{}\n\nYour postop astigmatism plots are attached. \
+             You can change how often you receive this report, or opt out, on your profile page.",
+            self.frequency.label().to_lowercase(),
+            self.period_label(),
+            self.cohort_label(),
+            self.summary
+        )
+    }
+
+    /// The period covered by the report, such as "1 Feb 2026 to 28 Feb 2026".
+    fn period_label(&self) -> String {
+        format!(
+            "{} to {}",
+            self.period.start().format("%-d %b %Y"),
+            self.period.end().format("%-d %b %Y")
+        )
+    }
+
+    /// The whole calendar years of the peer cohort, such as "2026" or "2025 to 2026". Peer cases
+    /// have a year but not a date, so the cohort can't be restricted to the report's period.
+    fn cohort_label(&self) -> String {
+        let (start, end) = (self.period.start().year(), self.period.end().year());
+
+        if start == end {
+            start.to_string()
+        } else {
+            format!("{start} to {end}")
+        }
+    }
+}
+
+/// A [`Surgeon`] who has opted in to report emails.
+#[derive(Clone, Debug, Queryable)]
+pub struct ReportRecipient {
+    pub surgeon: Surgeon,
+    /// When the surgeon's last report was attempted, if ever.
+    pub report_sent: Option<DateTime<Utc>>,
+}
+
+/// A report that is due for a single surgeon.
+struct DueReport<'a> {
+    surgeon: &'a Surgeon,
+    frequency: ReportFrequency,
+    /// When the surgeon's last report was attempted, which is used to claim this one.
+    last_sent: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+}
+
+/// Check for due reports every [`CHECK_INTERVAL`], for the lifetime of the server. Intended to be
+/// spawned as a background task from the main function.
+pub async fn schedule_reports(repository: Arc<dyn Repository>, mailer: Arc<Mailer>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match send_due_reports(repository.as_ref(), Arc::clone(&mailer), Utc::now()).await {
+            Ok(sent) if sent > 0 => log!("sent {sent} report emails"),
+            Ok(_) => {}
+            Err(err) => log!("unable to send report emails: {err}"),
+        }
+    }
+}
+
+/// Send a report to each surgeon whose report is due at `now`, and return the number sent. A
+/// failure for one surgeon is logged, and doesn't prevent sending to the others.
+#[doc(hidden)]
+pub(crate) async fn send_due_reports(
+    repository: &dyn Repository,
+    mailer: Arc<Mailer>,
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let recipients = repository.report_recipients().await?;
+    let mut sent = 0;
+
+    for ReportRecipient {
+        surgeon,
+        report_sent,
+    } in recipients
+    {
+        let Some(frequency) = surgeon.report_frequency else {
+            continue;
+        };
+
+        if !frequency.is_due(report_sent, now) {
+            continue;
+        }
+
+        let due = DueReport {
+            surgeon: &surgeon,
+            frequency,
+            last_sent: report_sent,
+            now,
+        };
+
+        match send_report(repository, due, Arc::clone(&mailer)).await {
+            Ok(true) => sent += 1,
+            Ok(false) => {}
+            Err(err) => log!("unable to send a report email to {}: {err}", surgeon.email),
+        }
+    }
+
+    Ok(sent)
+}
+
+/// Send a single surgeon's report, and return whether it was sent. The report is claimed by
+/// recording the period before it is built, so a later failure is logged and the report skipped,
+/// rather than retried at every check. No email is sent if another check has already claimed the
+/// report, or if the surgeon had no cases in the period.
+async fn send_report(
+    repository: &dyn Repository,
+    due: DueReport<'_>,
+    mailer: Arc<Mailer>,
+) -> Result<bool, AppError> {
+    let DueReport {
+        surgeon,
+        frequency,
+        last_sent,
+        now,
+    } = due;
+
+    if !repository.claim_report(&surgeon.email, last_sent).await? {
+        return Ok(false);
+    }
+
+    let period = frequency.period(now.date_naive());
+    let compare = repository.report_compare(&surgeon.email, period).await?;
+
+    let Some(report) = OutcomeReport::new(frequency, period, &compare) else {
+        return Ok(false);
+    };
+
+    transactional_email_with_mailer(surgeon, EmailType::Report(report), mailer).await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn describes_the_period() {
+        let date = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+
+        let report = OutcomeReport {
+            frequency: ReportFrequency::Monthly,
+            period: DateRange::new(date(2, 1), date(2, 28)).unwrap(),
+            summary: String::from("Cases: 12 (peer cohort: 340)."),
+            attachments: Vec::new(),
+        };
+
+        assert_eq!(
+            report.subject(),
+            "Your monthly cataract audit report: 1 Feb 2026 to 28 Feb 2026"
+        );
+        assert!(
+            report
+                .body()
+                .contains("\n\nCases: 12 (peer cohort: 340).\n\n")
+        );
+        assert!(report.body().contains("peer cohort's cases from 2026."));
+    }
+}
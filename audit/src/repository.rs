@@ -0,0 +1,140 @@
+//! Storage for surgeons and their cases. Server functions use the [`Repository`] held in
+//! [`AppState`], which is a [`GelRepository`] in prod, and tests can use a `MemoryRepository` to
+//! run offline.
+//!
+//! Every implementation must honour the same access rules as the DB's access policies: a surgeon
+//! can only read and write their own [`SurgeonCase`]s (and constants), while the anonymized
+//! [`Case`](crate::model::Case) of every surgeon is readable by all, for cohort comparisons. The
+//! exceptions are the report methods, which act for the server's report scheduler rather than a
+//! session, and so select the surgeon by email.
+
+mod gel;
+#[cfg(test)] mod memory;
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Utc;
+use futures::future::BoxFuture;
+pub use gel::*;
+use leptos::prelude::use_context;
+#[cfg(test)] pub use memory::*;
+
+use crate::auth::AuthSession;
+use crate::auth::auth_session;
+use crate::error::AppError;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::Email;
+use crate::model::Iol;
+use crate::model::ReportFrequency;
+use crate::model::Site;
+use crate::model::Surgeon;
+use crate::model::SurgeonCase;
+use crate::model::SurgeonConstant;
+use crate::model::Year;
+use crate::plots::CaseCompare;
+use crate::plots::TrendCases;
+use crate::report::ReportRecipient;
+use crate::state::AppState;
+
+/// A store of surgeons, cases and reference data, acting on behalf of the surgeon of a single
+/// session. Without a session, reads of the surgeon's own data return nothing, and writes fail.
+pub trait Repository: Debug + Send + Sync {
+    /// Return a repository that shares this one's storage, acting on behalf of the surgeon
+    /// identified by `session`.
+    fn with_session(&self, session: Option<&AuthSession>) -> Arc<dyn Repository>;
+
+    /// The current surgeon, or [`None`] if they haven't signed up.
+    fn current_surgeon(&self) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>>;
+
+    /// Insert the current surgeon. If a surgeon with the same email already exists, they are
+    /// returned unchanged.
+    fn insert_surgeon<'a>(
+        &'a self,
+        surgeon: &'a Surgeon,
+    ) -> BoxFuture<'a, Result<Surgeon, AppError>>;
+
+    /// Record that the current surgeon has agreed to the terms.
+    fn accept_terms(&self) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>>;
+
+    /// Set how often the current surgeon receives report emails, or opt them out with [`None`].
+    fn update_report_frequency(
+        &self,
+        frequency: Option<ReportFrequency>,
+    ) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>>;
+
+    fn iols(&self) -> BoxFuture<'_, Result<Vec<Iol>, AppError>>;
+
+    /// The [`Iol`] with the given model, if it exists.
+    fn iol<'a>(&'a self, model: &'a str) -> BoxFuture<'a, Result<Option<Iol>, AppError>>;
+
+    fn sites(&self) -> BoxFuture<'_, Result<Vec<Site>, AppError>>;
+
+    /// Insert a case for the current surgeon, returning it with its assigned `number`.
+    fn insert_case(&self, case: SurgeonCase) -> BoxFuture<'_, Result<SurgeonCase, AppError>>;
+
+    /// The current surgeon's cases from the given [`Year`], compared with the
+    /// [`Cohort`](crate::model::Cohort), restricted to the subgroup in the
+    /// [`CaseFilter`](crate::model::CaseFilter).
+    fn compare<'a>(
+        &'a self,
+        params: &'a CompareParams<Year>,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>>;
+
+    /// The current surgeon's cases from the given [`DateRange`], compared with the
+    /// [`Cohort`](crate::model::Cohort), restricted to the subgroup in the
+    /// [`CaseFilter`](crate::model::CaseFilter).
+    fn range_compare<'a>(
+        &'a self,
+        params: &'a CompareParams<DateRange>,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>>;
+
+    /// The current surgeon's cases across all years, and the peer cases from the same years.
+    fn trend_cases(&self) -> BoxFuture<'_, Result<TrendCases, AppError>>;
+
+    /// The current surgeon's stored constants, ordered by IOL model and formula.
+    fn constants(&self) -> BoxFuture<'_, Result<Vec<SurgeonConstant>, AppError>>;
+
+    /// Store the current surgeon's constants, replacing any previous values for the same IOL and
+    /// formula. Returns all of the surgeon's stored constants.
+    fn upsert_constants(
+        &self,
+        constants: Vec<SurgeonConstant>,
+    ) -> BoxFuture<'_, Result<Vec<SurgeonConstant>, AppError>>;
+
+    /// Every surgeon who has opted in to report emails, ordered by email, with when their last
+    /// report was attempted.
+    fn report_recipients(&self) -> BoxFuture<'_, Result<Vec<ReportRecipient>, AppError>>;
+
+    /// Claim the surgeon's due report by recording the attempt, but only if their last attempt is
+    /// still `last_sent`. Returns `false` if the report has already been claimed.
+    fn claim_report<'a>(
+        &'a self,
+        email: &'a Email,
+        last_sent: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<bool, AppError>>;
+
+    /// The surgeon's cases from the given [`DateRange`], for their report, compared with the peer
+    /// cohort from the whole calendar years that the range spans (peer cases have no date).
+    fn report_compare<'a>(
+        &'a self,
+        email: &'a Email,
+        period: DateRange,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>>;
+}
+
+/// Return the [`Repository`] for the current request, acting on behalf of the surgeon that
+/// [`require_auth`](crate::auth::require_auth) validated (if the route is protected). Each call
+/// derives a new repository from the shared one in [`AppState`], so concurrent requests never see
+/// each other's identity.
+pub fn repository() -> Result<Arc<dyn Repository>, AppError> {
+    if let Some(AppState { repository, .. }) = use_context::<AppState>() {
+        Ok(repository.with_session(auth_session().as_ref()))
+    } else {
+        Err(AppError::State(
+            "unable to get the repository from context".to_string(),
+        ))
+    }
+}
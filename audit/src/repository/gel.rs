@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use gel_tokio::Client;
+
+use super::Repository;
+use crate::auth::AuthSession;
+use crate::components::insert_surgeon_case;
+use crate::db::with_client_token;
+use crate::error::AppError;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::Email;
+use crate::model::Iol;
+use crate::model::ReportFrequency;
+use crate::model::Site;
+use crate::model::Surgeon;
+use crate::model::SurgeonCase;
+use crate::model::SurgeonConstant;
+use crate::model::Year;
+use crate::plots::CaseCompare;
+use crate::plots::TrendCases;
+use crate::plots::get_compare_with_client;
+use crate::plots::get_range_compare_with_client;
+use crate::plots::get_surgeon_constants_with_client;
+use crate::plots::get_trend_cases_with_client;
+use crate::plots::upsert_surgeon_constants_with_client;
+use crate::query::QuerySurgeon;
+use crate::query::query_insert_surgeon;
+use crate::query::query_select_current_surgeon;
+use crate::query::query_select_iol;
+use crate::query::query_select_iols;
+use crate::query::query_select_report_surgeons;
+use crate::query::query_select_sites;
+use crate::query::query_update_report_frequency;
+use crate::query::query_update_report_sent;
+use crate::query::query_update_terms;
+use crate::report::ReportRecipient;
+
+/// A [`Repository`] backed by the Gel DB, where access rules are enforced by the schema's access
+/// policies for the surgeon identified by the client's auth token.
+#[derive(Clone, Debug)]
+pub struct GelRepository {
+    client: Client,
+}
+
+impl GelRepository {
+    /// The client should not have an auth token, as each session derives its own client from it.
+    pub fn new(client: Client) -> Self { Self { client } }
+
+    // Reports are sent outside any request, so there is no auth token (and so no `cur_surgeon`).
+    // This client bypasses the access policies, so every query it runs must select the surgeon
+    // explicitly.
+    fn report_client(&self) -> Client {
+        self.client
+            .with_config(|config| config.set("apply_access_policies", false))
+    }
+}
+
+impl Repository for GelRepository {
+    fn with_session(&self, session: Option<&AuthSession>) -> Arc<dyn Repository> {
+        let auth_token = session.map(|session| session.auth_token.as_str());
+
+        Arc::new(Self {
+            client: with_client_token(&self.client, auth_token),
+        })
+    }
+
+    fn current_surgeon(&self) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>> {
+        Box::pin(async move {
+            let surgeon = self
+                .client
+                .query_single::<Surgeon, _>(query_select_current_surgeon(), &())
+                .await?;
+
+            Ok(surgeon)
+        })
+    }
+
+    fn insert_surgeon<'a>(
+        &'a self,
+        surgeon: &'a Surgeon,
+    ) -> BoxFuture<'a, Result<Surgeon, AppError>> {
+        Box::pin(async move {
+            let (query, args) = query_insert_surgeon(surgeon);
+
+            self.client
+                .query_single::<Surgeon, _>(query, &args)
+                .await?
+                .ok_or(AppError::Db("unable to insert the surgeon".to_string()))
+        })
+    }
+
+    fn accept_terms(&self) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>> {
+        Box::pin(async move {
+            let surgeon = self
+                .client
+                .query_single::<Surgeon, _>(query_update_terms(), &())
+                .await?;
+
+            Ok(surgeon)
+        })
+    }
+
+    fn update_report_frequency(
+        &self,
+        frequency: Option<ReportFrequency>,
+    ) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>> {
+        Box::pin(async move {
+            let (query, args) = query_update_report_frequency(frequency);
+
+            let surgeon = self.client.query_single::<Surgeon, _>(query, &args).await?;
+
+            Ok(surgeon)
+        })
+    }
+
+    fn iols(&self) -> BoxFuture<'_, Result<Vec<Iol>, AppError>> {
+        Box::pin(async move {
+            let iols = self
+                .client
+                .query::<Iol, _>(query_select_iols(), &())
+                .await?;
+
+            Ok(iols)
+        })
+    }
+
+    fn iol<'a>(&'a self, model: &'a str) -> BoxFuture<'a, Result<Option<Iol>, AppError>> {
+        Box::pin(async move {
+            let (query, args) = query_select_iol(model);
+            let iol = self.client.query_single::<Iol, _>(query, &args).await?;
+
+            Ok(iol)
+        })
+    }
+
+    fn sites(&self) -> BoxFuture<'_, Result<Vec<Site>, AppError>> {
+        Box::pin(async move {
+            let sites = self
+                .client
+                .query::<Site, _>(query_select_sites(), &())
+                .await?;
+
+            Ok(sites)
+        })
+    }
+
+    fn insert_case(&self, case: SurgeonCase) -> BoxFuture<'_, Result<SurgeonCase, AppError>> {
+        Box::pin(insert_surgeon_case(&self.client, case))
+    }
+
+    fn compare<'a>(
+        &'a self,
+        params: &'a CompareParams<Year>,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>> {
+        Box::pin(get_compare_with_client(&self.client, params))
+    }
+
+    fn range_compare<'a>(
+        &'a self,
+        params: &'a CompareParams<DateRange>,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>> {
+        Box::pin(get_range_compare_with_client(
+            &self.client,
+            params,
+            QuerySurgeon::Current,
+        ))
+    }
+
+    fn trend_cases(&self) -> BoxFuture<'_, Result<TrendCases, AppError>> {
+        Box::pin(get_trend_cases_with_client(&self.client))
+    }
+
+    fn constants(&self) -> BoxFuture<'_, Result<Vec<SurgeonConstant>, AppError>> {
+        Box::pin(get_surgeon_constants_with_client(&self.client))
+    }
+
+    fn upsert_constants(
+        &self,
+        constants: Vec<SurgeonConstant>,
+    ) -> BoxFuture<'_, Result<Vec<SurgeonConstant>, AppError>> {
+        Box::pin(
+            async move { upsert_surgeon_constants_with_client(&self.client, &constants).await },
+        )
+    }
+
+    fn report_recipients(&self) -> BoxFuture<'_, Result<Vec<ReportRecipient>, AppError>> {
+        Box::pin(async move {
+            let recipients = self
+                .report_client()
+                .query(query_select_report_surgeons(), &())
+                .await?;
+
+            Ok(recipients)
+        })
+    }
+
+    fn claim_report<'a>(
+        &'a self,
+        email: &'a Email,
+        last_sent: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<bool, AppError>> {
+        Box::pin(async move {
+            let (query, args) = query_update_report_sent(&email.inner(), last_sent);
+
+            let claimed = self
+                .report_client()
+                .query_required_single::<i64, _>(query, &args)
+                .await?;
+
+            Ok(claimed > 0)
+        })
+    }
+
+    fn report_compare<'a>(
+        &'a self,
+        email: &'a Email,
+        period: DateRange,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>> {
+        Box::pin(async move {
+            let params = CompareParams {
+                period,
+                ..Default::default()
+            };
+
+            let email = email.inner();
+
+            get_range_compare_with_client(
+                &self.report_client(),
+                &params,
+                QuerySurgeon::Email(&email),
+            )
+            .await
+        })
+    }
+}
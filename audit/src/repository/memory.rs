@@ -0,0 +1,562 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use futures::future::ready;
+use uuid::Uuid;
+
+use super::Repository;
+use crate::auth::AuthSession;
+use crate::bounded::Bounded;
+use crate::error::AppError;
+use crate::model::Case;
+use crate::model::CaseFilter;
+use crate::model::Cohort;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::Email;
+use crate::model::Formula;
+use crate::model::Iol;
+use crate::model::ReportFrequency;
+use crate::model::Site;
+use crate::model::Surgeon;
+use crate::model::SurgeonCase;
+use crate::model::SurgeonConstant;
+use crate::model::Year;
+use crate::plots::CaseCompare;
+use crate::plots::CohortYear;
+use crate::plots::TrendCases;
+use crate::report::ReportRecipient;
+
+/// The records of a [`MemoryRepository`], shared by every session derived from it. Surgeons and
+/// their records are keyed by the identity of the surgeon's auth session.
+#[derive(Debug, Default)]
+struct Store {
+    surgeons: Vec<(Uuid, Surgeon)>,
+    cases: Vec<(Uuid, SurgeonCase)>,
+    constants: Vec<(Uuid, SurgeonConstant)>,
+    iols: Vec<Iol>,
+    sites: Vec<Site>,
+    /// When each surgeon's last report was attempted.
+    report_sent: BTreeMap<Uuid, DateTime<Utc>>,
+    /// The last case number assigned. As with the sequence in the DB, numbers are unique across
+    /// all surgeons.
+    number: i64,
+}
+
+impl Store {
+    fn has_surgeon(&self, identity: Uuid) -> bool {
+        self.surgeons.iter().any(|(owner, _)| *owner == identity)
+    }
+
+    /// The identity of the surgeon with the given email, for the report methods, which act without
+    /// a session.
+    fn surgeon_with_email(&self, email: &Email) -> Result<Uuid, AppError> {
+        self.surgeons
+            .iter()
+            .find(|(_, surgeon)| surgeon.email == *email)
+            .map(|(identity, _)| *identity)
+            .ok_or_else(|| AppError::Db(format!("there is no surgeon with the email {email}")))
+    }
+
+    fn surgeon_cases(&self, identity: Option<Uuid>) -> impl Iterator<Item = &SurgeonCase> {
+        self.cases
+            .iter()
+            .filter(move |(owner, _)| Some(*owner) == identity)
+            .map(|(_, case)| case)
+    }
+
+    /// The surgeon's constants, ordered by IOL model, then by the order of the formulas (as in
+    /// the DB).
+    fn surgeon_constants(&self, identity: Option<Uuid>) -> Vec<SurgeonConstant> {
+        let mut constants = self
+            .constants
+            .iter()
+            .filter(|(owner, _)| Some(*owner) == identity)
+            .map(|(_, constant)| constant.clone())
+            .collect::<Vec<_>>();
+
+        constants.sort_by_key(|constant| {
+            let formula = Formula::ALL
+                .iter()
+                .position(|formula| *formula == constant.formula);
+
+            (constant.iol.clone(), formula)
+        });
+
+        constants
+    }
+
+    /// Every other surgeon's [`Case`], with its year. As in the DB, the date and site of peer
+    /// cases are not available.
+    fn cohort_cases(&self, identity: Option<Uuid>) -> impl Iterator<Item = (i32, &Case)> {
+        self.cases
+            .iter()
+            .filter(move |(owner, _)| Some(*owner) != identity)
+            .map(|(_, case)| (case.date.year(), &case.case))
+    }
+}
+
+/// Return whether one of the surgeon's own cases falls within the filter. Unlike
+/// [`CaseFilter::matches`], this includes the site, which is only recorded against the surgeon's
+/// own cases.
+fn matches_surgeon_case(filter: &CaseFilter, case: &SurgeonCase) -> bool {
+    filter.matches(&case.case)
+        && filter
+            .site
+            .as_ref()
+            .is_none_or(|name| case.site.as_ref().is_some_and(|site| site.name == *name))
+}
+
+/// A [`Repository`] that keeps its records in memory, for running tests without a DB.
+/// The access rules of the DB are enforced in code, so that tests of server functions exercise the
+/// same behaviour as prod. It trusts the identity claimed by each session's auth token (see
+/// [`AuthSession`]), so it is only compiled for tests.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRepository {
+    store: Arc<Mutex<Store>>,
+    identity: Option<Uuid>,
+}
+
+impl MemoryRepository {
+    /// An empty repository, where cases can be recorded with the given IOLs.
+    pub fn with_iols(iols: Vec<Iol>) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(Store {
+                iols,
+                ..Default::default()
+            })),
+            identity: None,
+        }
+    }
+
+    /// The identity of the session, which is required for writes.
+    fn identity(&self) -> Result<Uuid, AppError> {
+        self.identity.ok_or(AppError::Auth(
+            "there is no auth session for the repository".to_string(),
+        ))
+    }
+
+    /// The identity of the session, if the surgeon has signed up.
+    fn surgeon_identity(&self, store: &Store) -> Result<Uuid, AppError> {
+        let identity = self.identity()?;
+
+        if store.has_surgeon(identity) {
+            Ok(identity)
+        } else {
+            Err(AppError::Db(
+                "the current surgeon has not signed up".to_string(),
+            ))
+        }
+    }
+
+    fn current(&self) -> Result<Option<Surgeon>, AppError> {
+        let store = self.store.lock()?;
+
+        let surgeon = store
+            .surgeons
+            .iter()
+            .find(|(owner, _)| Some(*owner) == self.identity)
+            .map(|(_, surgeon)| surgeon.clone());
+
+        Ok(surgeon)
+    }
+
+    /// Apply `update` to the current surgeon, returning the updated surgeon, or [`None`] if they
+    /// haven't signed up.
+    fn update_current(
+        &self,
+        update: impl FnOnce(&mut Surgeon),
+    ) -> Result<Option<Surgeon>, AppError> {
+        let mut store = self.store.lock()?;
+
+        let surgeon = store
+            .surgeons
+            .iter_mut()
+            .find(|(owner, _)| Some(*owner) == self.identity)
+            .map(|(_, surgeon)| {
+                update(surgeon);
+                surgeon.clone()
+            });
+
+        Ok(surgeon)
+    }
+
+    fn insert(&self, surgeon: &Surgeon) -> Result<Surgeon, AppError> {
+        let identity = self.identity()?;
+        let mut store = self.store.lock()?;
+
+        // As in the DB, a conflicting email returns the existing surgeon.
+        if let Some((_, existing)) = store
+            .surgeons
+            .iter()
+            .find(|(_, existing)| existing.email == surgeon.email)
+        {
+            return Ok(existing.clone());
+        }
+
+        if store.has_surgeon(identity) {
+            return Err(AppError::Db(
+                "the identity already belongs to a surgeon".to_string(),
+            ));
+        }
+
+        let mut surgeon = surgeon.clone();
+
+        // As in the DB, the terms are only set once the surgeon accepts them.
+        surgeon.terms = None;
+
+        if let Some(defaults) = surgeon.defaults.as_mut() {
+            defaults.iol = defaults.iol.take().and_then(|iol| {
+                store
+                    .iols
+                    .iter()
+                    .find(|known| known.model == iol.model)
+                    .cloned()
+            });
+
+            if let Some(site) = &defaults.site
+                && !store.sites.contains(site)
+            {
+                store.sites.push(site.clone());
+            }
+        }
+
+        store.surgeons.push((identity, surgeon.clone()));
+
+        Ok(surgeon)
+    }
+
+    fn insert_surgeon_case(&self, mut case: SurgeonCase) -> Result<SurgeonCase, AppError> {
+        let mut store = self.store.lock()?;
+        let identity = self.surgeon_identity(&store)?;
+
+        let iol = store
+            .iols
+            .iter()
+            .find(|iol| iol.model == case.case.iol.iol.model)
+            .cloned()
+            .ok_or(AppError::Db("the Iol is not present in the DB".to_string()))?;
+
+        if let Some(site) = &case.site
+            && !store.sites.contains(site)
+        {
+            store.sites.push(site.clone());
+        }
+
+        store.number += 1;
+
+        case.number = store.number;
+        case.case.iol.iol = iol;
+
+        store.cases.push((identity, case.clone()));
+
+        Ok(case)
+    }
+
+    fn year_compare(&self, params: &CompareParams<Year>) -> Result<CaseCompare, AppError> {
+        let CompareParams {
+            period,
+            cohort,
+            filter,
+        } = params;
+
+        let store = self.store.lock()?;
+        let year = i64::from(period.inner());
+
+        let in_year = |case: &SurgeonCase, year: i64| i64::from(case.date.year()) == year;
+
+        let surgeon = store
+            .surgeon_cases(self.identity)
+            .filter(|case| in_year(case, year) && matches_surgeon_case(filter, case))
+            .cloned()
+            .collect();
+
+        let cohort = match cohort {
+            Cohort::Peers => store
+                .cohort_cases(self.identity)
+                .filter(|(case_year, case)| i64::from(*case_year) == year && filter.matches(case))
+                .map(|(_, case)| case.clone())
+                .collect(),
+            Cohort::Surgeon => store
+                .surgeon_cases(self.identity)
+                .filter(|case| in_year(case, year - 1) && matches_surgeon_case(filter, case))
+                .map(|case| case.case.clone())
+                .collect(),
+        };
+
+        Ok(CaseCompare::new(surgeon, cohort))
+    }
+
+    /// Compare the cases of the surgeon with the given identity, which is the session's identity
+    /// unless this is a report.
+    fn date_range_compare(
+        &self,
+        identity: Option<Uuid>,
+        params: &CompareParams<DateRange>,
+    ) -> Result<CaseCompare, AppError> {
+        let CompareParams {
+            period,
+            cohort,
+            filter,
+        } = params;
+
+        // The range may have been deserialized from a request, bypassing the check in `new()`.
+        let range = DateRange::new(period.start(), period.end())?;
+        let store = self.store.lock()?;
+
+        let surgeon = store
+            .surgeon_cases(identity)
+            .filter(|case| range.contains(&case.date) && matches_surgeon_case(filter, case))
+            .cloned()
+            .collect();
+
+        let cohort = match cohort {
+            Cohort::Peers => {
+                let years = range.start().year()..=range.end().year();
+
+                store
+                    .cohort_cases(identity)
+                    .filter(|(year, case)| years.contains(year) && filter.matches(case))
+                    .map(|(_, case)| case.clone())
+                    .collect()
+            }
+            Cohort::Surgeon => store
+                .surgeon_cases(identity)
+                .filter(|case| !range.contains(&case.date) && matches_surgeon_case(filter, case))
+                .map(|case| case.case.clone())
+                .collect(),
+        };
+
+        Ok(CaseCompare::new(surgeon, cohort))
+    }
+
+    fn trend(&self) -> Result<TrendCases, AppError> {
+        let store = self.store.lock()?;
+
+        let mut surgeon = store
+            .surgeon_cases(self.identity)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        surgeon.sort_by_key(|case| case.date);
+
+        let years = surgeon
+            .iter()
+            .map(|case| case.date.year())
+            .collect::<Vec<_>>();
+
+        let mut cohort = BTreeMap::<i32, Vec<&Case>>::new();
+        for (year, case) in store.cohort_cases(self.identity) {
+            if years.contains(&year) {
+                cohort.entry(year).or_default().push(case);
+            }
+        }
+
+        let cohort = cohort
+            .into_iter()
+            .map(|(year, cases)| CohortYear::new(year, &cases))
+            .collect();
+
+        Ok(TrendCases { surgeon, cohort })
+    }
+
+    fn recipients(&self) -> Result<Vec<ReportRecipient>, AppError> {
+        let store = self.store.lock()?;
+
+        let mut recipients = store
+            .surgeons
+            .iter()
+            .filter(|(_, surgeon)| surgeon.report_frequency.is_some())
+            .map(|(identity, surgeon)| ReportRecipient {
+                surgeon: surgeon.clone(),
+                report_sent: store.report_sent.get(identity).copied(),
+            })
+            .collect::<Vec<_>>();
+
+        recipients.sort_by_key(|recipient| recipient.surgeon.email.inner());
+
+        Ok(recipients)
+    }
+
+    fn claim(&self, email: &Email, last_sent: Option<DateTime<Utc>>) -> Result<bool, AppError> {
+        let mut store = self.store.lock()?;
+        let identity = store.surgeon_with_email(email)?;
+
+        if store.report_sent.get(&identity).copied() != last_sent {
+            return Ok(false);
+        }
+
+        store.report_sent.insert(identity, Utc::now());
+
+        Ok(true)
+    }
+
+    fn report(&self, email: &Email, period: DateRange) -> Result<CaseCompare, AppError> {
+        let identity = self.store.lock()?.surgeon_with_email(email)?;
+
+        let params = CompareParams {
+            period,
+            ..Default::default()
+        };
+
+        self.date_range_compare(Some(identity), &params)
+    }
+
+    fn upsert(&self, constants: Vec<SurgeonConstant>) -> Result<Vec<SurgeonConstant>, AppError> {
+        let mut store = self.store.lock()?;
+        let identity = self.surgeon_identity(&store)?;
+
+        for constant in constants {
+            if !store.iols.iter().any(|iol| iol.model == constant.iol) {
+                return Err(AppError::Db(format!(
+                    "the Iol {:?} is not present in the DB",
+                    constant.iol
+                )));
+            }
+
+            let existing = store.constants.iter_mut().find(|(owner, existing)| {
+                *owner == identity
+                    && existing.iol == constant.iol
+                    && existing.formula == constant.formula
+            });
+
+            match existing {
+                Some((_, existing)) => *existing = constant,
+                None => store.constants.push((identity, constant)),
+            }
+        }
+
+        Ok(store.surgeon_constants(Some(identity)))
+    }
+}
+
+impl Repository for MemoryRepository {
+    fn with_session(&self, session: Option<&AuthSession>) -> Arc<dyn Repository> {
+        Arc::new(Self {
+            store: Arc::clone(&self.store),
+            // The claimed identity is trusted here, which is only safe because this repository is
+            // never used outside tests.
+            identity: session.map(|session| session.unverified_identity_id),
+        })
+    }
+
+    fn current_surgeon(&self) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>> {
+        Box::pin(ready(self.current()))
+    }
+
+    fn insert_surgeon<'a>(
+        &'a self,
+        surgeon: &'a Surgeon,
+    ) -> BoxFuture<'a, Result<Surgeon, AppError>> {
+        Box::pin(ready(self.insert(surgeon)))
+    }
+
+    fn accept_terms(&self) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>> {
+        Box::pin(ready(
+            self.update_current(|surgeon| surgeon.terms = Some(Utc::now())),
+        ))
+    }
+
+    fn update_report_frequency(
+        &self,
+        frequency: Option<ReportFrequency>,
+    ) -> BoxFuture<'_, Result<Option<Surgeon>, AppError>> {
+        Box::pin(ready(
+            self.update_current(|surgeon| surgeon.report_frequency = frequency),
+        ))
+    }
+
+    fn iols(&self) -> BoxFuture<'_, Result<Vec<Iol>, AppError>> {
+        let iols = self
+            .store
+            .lock()
+            .map(|store| store.iols.clone())
+            .map_err(AppError::from);
+
+        Box::pin(ready(iols))
+    }
+
+    fn iol<'a>(&'a self, model: &'a str) -> BoxFuture<'a, Result<Option<Iol>, AppError>> {
+        let iol = self
+            .store
+            .lock()
+            .map(|store| store.iols.iter().find(|iol| iol.model == model).cloned())
+            .map_err(AppError::from);
+
+        Box::pin(ready(iol))
+    }
+
+    fn sites(&self) -> BoxFuture<'_, Result<Vec<Site>, AppError>> {
+        let sites = self
+            .store
+            .lock()
+            .map(|store| store.sites.clone())
+            .map_err(AppError::from);
+
+        Box::pin(ready(sites))
+    }
+
+    fn insert_case(&self, case: SurgeonCase) -> BoxFuture<'_, Result<SurgeonCase, AppError>> {
+        Box::pin(ready(self.insert_surgeon_case(case)))
+    }
+
+    fn compare<'a>(
+        &'a self,
+        params: &'a CompareParams<Year>,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>> {
+        Box::pin(ready(self.year_compare(params)))
+    }
+
+    fn range_compare<'a>(
+        &'a self,
+        params: &'a CompareParams<DateRange>,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>> {
+        Box::pin(ready(self.date_range_compare(self.identity, params)))
+    }
+
+    fn trend_cases(&self) -> BoxFuture<'_, Result<TrendCases, AppError>> {
+        Box::pin(ready(self.trend()))
+    }
+
+    fn constants(&self) -> BoxFuture<'_, Result<Vec<SurgeonConstant>, AppError>> {
+        let constants = self
+            .store
+            .lock()
+            .map(|store| store.surgeon_constants(self.identity))
+            .map_err(AppError::from);
+
+        Box::pin(ready(constants))
+    }
+
+    fn upsert_constants(
+        &self,
+        constants: Vec<SurgeonConstant>,
+    ) -> BoxFuture<'_, Result<Vec<SurgeonConstant>, AppError>> {
+        Box::pin(ready(self.upsert(constants)))
+    }
+
+    fn report_recipients(&self) -> BoxFuture<'_, Result<Vec<ReportRecipient>, AppError>> {
+        Box::pin(ready(self.recipients()))
+    }
+
+    fn claim_report<'a>(
+        &'a self,
+        email: &'a Email,
+        last_sent: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<bool, AppError>> {
+        Box::pin(ready(self.claim(email, last_sent)))
+    }
+
+    fn report_compare<'a>(
+        &'a self,
+        email: &'a Email,
+        period: DateRange,
+    ) -> BoxFuture<'a, Result<CaseCompare, AppError>> {
+        Box::pin(ready(self.report(email, period)))
+    }
+}
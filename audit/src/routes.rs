@@ -5,6 +5,7 @@ mod gateway;
 mod instructions;
 mod landing;
 mod list;
+mod profile;
 mod protected;
 mod report;
 mod sign_up;
@@ -17,6 +18,7 @@ pub use gateway::*;
 pub use instructions::*;
 pub use landing::*;
 pub use list::*;
+pub use profile::*;
 pub use protected::*;
 pub use report::*;
 pub use sign_up::*;
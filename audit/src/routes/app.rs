@@ -23,6 +23,7 @@ use crate::routes::Gateway;
 use crate::routes::Instructions;
 use crate::routes::Landing;
 use crate::routes::List;
+use crate::routes::Profile;
 use crate::routes::Protected;
 use crate::routes::Report;
 use crate::routes::SignUp;
@@ -79,6 +80,7 @@ pub fn App() -> impl IntoView {
                             <Route path=StaticSegment("add") view=Add />
                             <Route path=StaticSegment("instructions") view=Instructions />
                             <Route path=StaticSegment("list") view=List />
+                            <Route path=StaticSegment("profile") view=Profile />
                             <Route path=StaticSegment("report") view=Report />
                         </ParentRoute>
                     </ParentRoute>
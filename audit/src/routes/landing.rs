@@ -3,13 +3,13 @@ use leptos::prelude::component;
 use leptos::prelude::view;
 
 use crate::components::Hero;
-use crate::components::MARKDOWN_PATH;
 use crate::components::Markdown;
+use crate::components::MarkdownPage;
 
 #[component]
 pub fn Landing() -> impl IntoView {
     view! {
         <Hero />
-        <Markdown md=MARKDOWN_PATH.join("landing.md") />
+        <Markdown md=MarkdownPage("landing.md") />
     }
 }
@@ -0,0 +1,92 @@
+use leptos::prelude::ActionForm;
+use leptos::prelude::ElementChild;
+use leptos::prelude::Get;
+use leptos::prelude::GetUntracked;
+use leptos::prelude::IntoView;
+use leptos::prelude::RwSignal;
+use leptos::prelude::ServerAction;
+use leptos::prelude::ServerFnError;
+use leptos::prelude::component;
+use leptos::prelude::server;
+use leptos::prelude::use_context;
+use leptos::prelude::view;
+
+#[cfg(feature = "ssr")] use crate::error::AppError;
+use crate::model::ReportFrequency;
+use crate::model::Surgeon;
+#[cfg(feature = "ssr")] use crate::repository::repository;
+
+/// The surgeon's profile, where they can opt in to outcome report emails and choose how often they
+/// receive them.
+#[component]
+pub fn Profile() -> impl IntoView {
+    let update_report_frequency = ServerAction::<UpdateReportFrequency>::new();
+
+    // The current surgeon is provided as context by the `Protected` route.
+    let current = use_context::<RwSignal<Option<Surgeon>>>()
+        .and_then(|surgeon| surgeon.get_untracked())
+        .and_then(|surgeon| surgeon.report_frequency);
+
+    let off_selected = current.is_none();
+
+    let frequency_options = ReportFrequency::ALL
+        .into_iter()
+        .map(|frequency| {
+            let selected = current == Some(frequency);
+            view! {
+                <option value=frequency.to_string() selected=selected>
+                    {frequency.label().to_string()}
+                </option>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let saved = move || {
+        update_report_frequency
+            .value()
+            .get()
+            .map(|result| match result {
+                Ok(Some(frequency)) => format!(
+                    "Saved. You will receive {} reports.",
+                    frequency.label().to_lowercase()
+                ),
+                Ok(None) => String::from("Saved. You have opted out of report emails."),
+                Err(_) => String::from("Unable to save your report settings."),
+            })
+    };
+
+    view! {
+        <ActionForm action=update_report_frequency>
+            <label>
+                "Outcome report emails, summarizing your cases against the peer cohort"
+                <select name="frequency">
+                    <option value="Off" selected=off_selected>
+                        "Off"
+                    </option>
+                    {frequency_options}
+                </select>
+            </label>
+            <input type="submit" value="Save" />
+        </ActionForm>
+        <p>{saved}</p>
+    }
+}
+
+/// Set how often the current surgeon receives report emails. Any value other than a
+/// [`ReportFrequency`] opts out.
+#[server]
+pub async fn update_report_frequency(
+    frequency: String,
+) -> Result<Option<ReportFrequency>, ServerFnError> {
+    let frequency = ReportFrequency::from_param(&frequency);
+
+    if repository()?
+        .update_report_frequency(frequency)
+        .await?
+        .is_some()
+    {
+        Ok(frequency)
+    } else {
+        Err(AppError::Db("unable to update the report frequency".to_string()).into())
+    }
+}
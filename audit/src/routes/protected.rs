@@ -1,4 +1,3 @@
-#[cfg(feature = "ssr")] use gel_tokio::create_client;
 use leptos::either::Either;
 use leptos::prelude::IntoView;
 use leptos::prelude::Resource;
@@ -9,17 +8,15 @@ use leptos::prelude::Suspense;
 use leptos::prelude::component;
 use leptos::prelude::provide_context;
 use leptos::prelude::server;
-#[cfg(feature = "ssr")] use leptos::prelude::use_context;
 use leptos::prelude::view;
 #[cfg(feature = "ssr")] use leptos_axum::redirect;
 use leptos_router::components::Outlet;
 
-#[cfg(feature = "ssr")] use crate::auth::get_jwt_cookie;
+#[cfg(feature = "ssr")] use crate::auth::auth_session;
 use crate::components::SignedOut;
-#[cfg(feature = "ssr")] use crate::db::db;
 use crate::error::AppError;
 use crate::model::Surgeon;
-#[cfg(feature = "ssr")] use crate::state::AppState;
+#[cfg(feature = "ssr")] use crate::repository::repository;
 
 #[component]
 pub fn Protected() -> impl IntoView {
@@ -48,57 +45,18 @@ pub fn Protected() -> impl IntoView {
 
 #[server]
 pub async fn get_authorized_surgeon() -> Result<Option<Surgeon>, AppError> {
-    let state = if let Some(state) = use_context::<AppState>() {
-        state
-    } else {
-        return Err(AppError::State(
-            "the call to `use_context::<AppState>()` in `get_authorized_surgeon()` returned `None`"
-                .to_string(),
-        ));
-    };
-
-    let auth_token = if let Ok(Some(auth_token)) = get_jwt_cookie().await {
-        auth_token
-    } else {
+    // `require_auth` has already rejected the request if there is no valid auth token, so this is
+    // only reached without a session if the route isn't protected.
+    if auth_session().is_none() {
         return Err(AppError::Auth(
-            "the call to `get_jwt_cookie` in `get_authorized_surgeon` returned `None`".to_string(),
+            "no auth session is attached to the request in `get_authorized_surgeon`".to_string(),
         ));
-    };
-
-    // `create_client()` errors if a connection can't immediately be established, so it
-    // isn't necessary to call `ensure_connection()` if the client was created through this
-    // convenience method.
-    let client = create_client()
-        .await?
-        .with_globals_fn(|client| client.set("ext::auth::client_token", &auth_token));
-
-    state.db.set(client)?;
-
-    let query = r#"
-select global cur_surgeon {
-    email,
-    terms,
-    full_name,
-    preferred_name,
-
-    defaults: {
-        site: { name },
-        iol: { model, name, company, focus, toric },
-        formula,
-        custom_constant,
-        main
-    },
-
-    sia: { right: { power, axis }, left: { power, axis } }
-};
-        "#;
-
-    if let Some(json) = db().await?.query_single_json(query, &()).await? {
-        let surgeon = serde_json::from_str::<Surgeon>(json.as_ref())?;
+    }
 
+    // The repository is created for this request only, with the auth token from the request's
+    // cookie.
+    if let Some(surgeon) = repository()?.current_surgeon().await? {
         if surgeon.terms.is_some() {
-            state.surgeon.set(Some(surgeon.clone()))?;
-
             Ok(Some(surgeon))
         } else {
             redirect("/terms");
@@ -3,8 +3,14 @@ use leptos::prelude::component;
 use leptos::prelude::view;
 
 use crate::components::PlotSet;
+use crate::components::RangePlotSet;
+use crate::components::ReportForm;
 
 #[component]
 pub fn Report() -> impl IntoView {
-    view! { <PlotSet /> }
+    view! {
+        <ReportForm />
+        <PlotSet />
+        <RangePlotSet />
+    }
 }
@@ -9,13 +9,19 @@ use leptos::prelude::server;
 use leptos::prelude::view;
 #[cfg(feature = "ssr")] use leptos_axum::redirect;
 
-#[cfg(feature = "ssr")] use crate::db::db;
-#[cfg(feature = "ssr")] use crate::db::to_centi;
+#[cfg(feature = "ssr")] use crate::bounded::Bounded;
+#[cfg(feature = "ssr")] use crate::model::Axis;
 #[cfg(feature = "ssr")] use crate::model::Email;
 use crate::model::FormSurgeon;
+#[cfg(feature = "ssr")] use crate::model::Formula;
+#[cfg(feature = "ssr")] use crate::model::Main;
+#[cfg(feature = "ssr")] use crate::model::Sia;
+#[cfg(feature = "ssr")] use crate::model::SiaPower;
+#[cfg(feature = "ssr")] use crate::model::Site;
 #[cfg(feature = "ssr")] use crate::model::Surgeon;
-#[cfg(feature = "ssr")] use crate::model::set_current_surgeon;
-#[cfg(feature = "ssr")] use crate::some_or_empty;
+#[cfg(feature = "ssr")] use crate::model::SurgeonDefaults;
+#[cfg(feature = "ssr")] use crate::model::SurgeonSia;
+#[cfg(feature = "ssr")] use crate::repository::repository;
 
 #[component]
 pub fn SignUp() -> impl IntoView {
@@ -105,93 +111,55 @@ pub async fn insert_surgeon(surgeon: FormSurgeon) -> Result<(), ServerFnError> {
         sia_left_axis,
     } = surgeon;
 
-    let email = Email::new(&email)?.inner();
-
-    some_or_empty!(
-        full_name,
-        preferred_name,
-        default_site,
-        default_iol,
-        default_formula
-    );
-
-    fn to_db_formula(formula: &str) -> &str {
+    fn to_formula(formula: &str) -> Formula {
         match formula.to_lowercase().as_str() {
-            "ascrskrs" => "Formula.AscrsKrs",
-            "barrett" | "barretttoric" => "Formula.Barrett",
-            "barretttruek" => "Formula.BarrettTrueK",
-            "evo" => "Formula.Evo",
-            "haigis" => "Formula.Haigis",
-            "haigisl" => "Formula.HaigisL",
-            "hillrbf" => "Formula.HillRbf",
-            "hofferq" => "Formula.HofferQ",
-            "holladay1" => "Formula.Holladay1",
-            "holladay2" => "Formula.Holladay2",
-            "kane" => "Formula.Kane",
-            "okulix" => "Formula.Okulix",
-            "olsen" => "Formula.Olsen",
-            "srkt" => "Formula.SrkT",
-            _ => "Formula.Other",
+            "ascrskrs" => Formula::AscrsKrs,
+            "barrett" | "barretttoric" => Formula::Barrett,
+            "barretttruek" => Formula::BarrettTrueK,
+            "evo" => Formula::Evo,
+            "haigis" => Formula::Haigis,
+            "haigisl" => Formula::HaigisL,
+            "hillrbf" => Formula::HillRbf,
+            "hofferq" => Formula::HofferQ,
+            "holladay1" => Formula::Holladay1,
+            "holladay2" => Formula::Holladay2,
+            "kane" => Formula::Kane,
+            "okulix" => Formula::Okulix,
+            "olsen" => Formula::Olsen,
+            "srkt" => Formula::SrkT,
+            _ => Formula::Other,
         }
     }
 
-    let default_formula = to_db_formula(&default_formula);
-    let custom_constant = custom_constant.is_some_and(|value| value.as_str() == "true");
-    let main = to_centi(main);
-    let sia_power = to_centi(sia_power);
-
-    let query = format!(
-        r#"
-with QuerySurgeon := (insert Surgeon {{
-        identity := (select global ext::auth::ClientTokenIdentity),
-        email := "{email}",
-        full_name := {full_name},
-        preferred_name := {preferred_name},
-
-        defaults := (select (insert SurgeonDefaults {{
-            site := (select(insert Site {{
-                name := {default_site}
-            }} unless conflict on .name else (select Site))),
+    let repository = repository()?;
 
-            iol := (select Iol filter .model = {default_iol}),
-            formula := {default_formula},
-            custom_constant := {custom_constant},
-            main := {main}
-        }})),
+    // As in the DB, an unknown IOL model is left out of the defaults, rather than being an error.
+    let iol = match default_iol {
+        Some(model) => repository.iol(&model).await?,
+        None => None,
+    };
 
-        sia := (select(insert SurgeonSia {{
-            right := (select(insert Sia {{
-                power := {sia_power}, axis := {sia_right_axis}
-            }})),
+    let sia_power = SiaPower::new((sia_power * 100.0) as u32)?;
 
-            left := (select(insert Sia {{
-                power := {sia_power}, axis := {sia_left_axis}
-            }}))
-        }}))
-    }} unless conflict on .email else (select Surgeon))
-
-select QuerySurgeon {{
-    email,
-    terms,
-    full_name,
-    preferred_name,
-
-    defaults: {{
-        site: {{ name }},
-        iol: {{ model, name, company, focus, toric }},
-        formula,
-        custom_constant,
-        main
-    }},
-
-    sia: {{ right: {{ power, axis }}, left: {{ power, axis }} }}
-}};
-        "#
-    );
-
-    if let Ok(Some(json)) = db().await?.query_single_json(query, &()).await {
-        let surgeon = serde_json::from_str::<Surgeon>(json.as_ref())?;
-        set_current_surgeon(Some(surgeon)).await?;
+    let surgeon = Surgeon {
+        email: Email::new(&email)?,
+        full_name,
+        preferred_name,
+        defaults: Some(SurgeonDefaults {
+            site: default_site.map(|name| Site { name }),
+            iol,
+            formula: default_formula.map(|formula| to_formula(&formula)),
+            custom_constant: custom_constant.is_some_and(|value| value.as_str() == "true"),
+            main: Main::new((main * 100.0) as u32)?,
+        }),
+        sia: SurgeonSia {
+            right: Sia::new(sia_power, Axis::new(sia_right_axis)?),
+            left: Sia::new(sia_power, Axis::new(sia_left_axis)?),
+        },
+        ..Default::default()
+    };
+
+    if repository.insert_surgeon(&surgeon).await.is_ok() {
         redirect("/terms");
     } else {
         // if we fail on the insert, then:
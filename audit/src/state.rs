@@ -1,18 +1,18 @@
 use std::sync::Arc;
-use std::sync::RwLock;
 
 use axum_macros::FromRef;
-use gel_tokio::Client;
 use leptos::prelude::LeptosOptions;
 
 use crate::mail::Mailer;
-use crate::model::Surgeon;
+use crate::repository::Repository;
 
 // `derive(FromRef)` is needed to make use of `leptos_axum`'s `extract_with_state()`
 #[derive(Clone, Debug, FromRef)]
 pub struct AppState {
     pub leptos_options: LeptosOptions,
-    pub db: Arc<RwLock<Client>>,
+    /// The shared storage, without any surgeon's identity. Each request derives its own repository
+    /// from this one with [`repository`](crate::repository::repository), so this one is never
+    /// modified, and is otherwise only used by the report scheduler.
+    pub repository: Arc<dyn Repository>,
     pub mailer: Arc<Mailer>,
-    pub surgeon: Arc<RwLock<Option<Surgeon>>>,
 }
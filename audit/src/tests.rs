@@ -1,3 +1,6 @@
 #[cfg(feature = "ssr")] mod common;
 #[cfg(feature = "ssr")] mod mail;
 #[cfg(feature = "ssr")] mod plots;
+#[cfg(feature = "ssr")] mod queries;
+#[cfg(feature = "ssr")] mod repository;
+#[cfg(feature = "ssr")] mod session;
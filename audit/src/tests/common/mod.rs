@@ -1,21 +1,23 @@
 use std::env;
+use std::sync::Arc;
 use std::sync::LazyLock;
 
 use dotenvy::dotenv;
 use gel_tokio::Client;
 use gel_tokio::create_client;
-use mailgun_rs::Mailgun;
 
 use crate::components::insert_surgeon_case;
+use crate::db::with_client_token;
 use crate::mail::EmailSender;
 use crate::mail::Mailer;
+use crate::mail::MemoryTransport;
 use crate::mock::gen_mocks;
 use crate::model::Email;
 use crate::model::SurgeonCase;
 
 pub struct TestJwt {
-    surgeon: String,
-    cohort: String,
+    pub surgeon: String,
+    pub cohort: String,
 }
 
 pub static TEST_JWTS: LazyLock<TestJwt> = LazyLock::new(|| {
@@ -31,12 +33,10 @@ pub static TEST_JWTS: LazyLock<TestJwt> = LazyLock::new(|| {
 });
 
 pub async fn test_db() -> Client {
-    let jwt = &*TEST_JWTS.surgeon;
-
-    create_client()
-        .await
-        .unwrap()
-        .with_globals_fn(|client| client.set("ext::auth::client_token", jwt))
+    with_client_token(
+        &create_client().await.unwrap(),
+        Some(TEST_JWTS.surgeon.as_str()),
+    )
 }
 
 /// Add 110 mock cases to a test branch of the DB. The first 10 cases use a JWT representing
@@ -87,21 +87,17 @@ pub async fn drop_test_db(client: &Client, branch: &str) {
         .unwrap();
 }
 
-pub async fn test_mailer() -> Mailer {
-    dotenv().ok();
-
-    let api_key = env::var("MAILGUN_API_KEY")
-        .expect("expected MAILGUN_API_KEY environment variable to be present");
-
-    let domain = env::var("MAILGUN_DOMAIN")
-        .expect("expected MAILGUN_DOMAIN environment variable to be present");
+/// A [`Mailer`] that captures emails in memory, along with its transport for inspecting them.
+pub fn test_mailer() -> (Arc<Mailer>, Arc<MemoryTransport>) {
+    let transport = Arc::new(MemoryTransport::default());
 
-    Mailer {
+    let mailer = Mailer {
         sender: EmailSender {
             name: "Test EmailSender".to_string(),
             email: Email::new("no-reply@test.com").unwrap(),
         },
+        transport: Arc::clone(&transport) as _,
+    };
 
-        mailgun: Mailgun { api_key, domain },
-    }
+    (Arc::new(mailer), transport)
 }
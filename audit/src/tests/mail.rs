@@ -1,16 +1,83 @@
 use std::env;
 use std::sync::Arc;
 
+use chrono::NaiveDate;
 use dotenvy::dotenv;
 
 use crate::mail::EmailType;
+use crate::mail::MailAttachment;
+use crate::mail::Mailer;
 use crate::mail::transactional_email_with_mailer;
 use crate::mock::Mock;
+use crate::model::DateRange;
 use crate::model::Email;
+use crate::model::ReportFrequency;
 use crate::model::Surgeon;
+use crate::report::OutcomeReport;
 use crate::tests::common::test_mailer;
 
-// We ignore this test by default, to avoid developing a spam reputation for our domain.
+fn report() -> OutcomeReport {
+    let date = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+
+    OutcomeReport {
+        frequency: ReportFrequency::Monthly,
+        period: DateRange::new(date(2, 1), date(2, 28)).unwrap(),
+        summary: String::from("Cases: 1 (peer cohort: 1)."),
+        attachments: vec![MailAttachment {
+            filename: String::from("test.svg"),
+            content_type: String::from("image/svg+xml"),
+            content: br#"<svg xmlns="http://www.w3.org/2000/svg"></svg>"#.to_vec(),
+        }],
+    }
+}
+
+#[tokio::test]
+async fn captures_welcome_email() {
+    let mut surgeon = Surgeon::mock();
+    surgeon.email = Email::new("jane@example.com").unwrap();
+    surgeon.full_name = Some(String::from("Jane Surgeon"));
+
+    let (mailer, transport) = test_mailer();
+
+    transactional_email_with_mailer(&surgeon, EmailType::Welcome, mailer)
+        .await
+        .unwrap();
+
+    let messages = transport.messages();
+    assert_eq!(messages.len(), 1);
+
+    let message = &messages[0];
+    assert_eq!(message.to.len(), 1);
+    assert_eq!(message.to[0].email, surgeon.email);
+    assert_eq!(message.text, "Thanks for signing up, Jane Surgeon!");
+    assert!(message.attachments.is_empty());
+}
+
+#[tokio::test]
+async fn captures_report_email() {
+    let mut surgeon = Surgeon::mock();
+    surgeon.preferred_name = Some(String::from("Jane"));
+
+    let (mailer, transport) = test_mailer();
+    let report = report();
+
+    transactional_email_with_mailer(&surgeon, EmailType::Report(report.clone()), mailer)
+        .await
+        .unwrap();
+
+    let messages = transport.messages();
+    assert_eq!(messages.len(), 1);
+
+    let message = &messages[0];
+    assert_eq!(message.to[0].email, surgeon.email);
+    assert_eq!(message.subject, report.subject());
+    assert!(message.text.starts_with("Hi Jane,\n\n"));
+    assert!(message.text.contains("Cases: 1 (peer cohort: 1)."));
+    assert_eq!(message.attachments, report.attachments);
+}
+
+// We ignore the tests that send real email by default, to avoid developing a spam reputation for
+// our domain. They use the transport configured in the environment.
 #[tokio::test]
 #[ignore]
 async fn test_email() {
@@ -22,9 +89,27 @@ async fn test_email() {
         .expect("expected TEST_EMAIL_RECIPIENT environment variable to be present");
 
     surgeon.email = Email::new(&email_recipient).unwrap();
-    let mailer = Arc::new(test_mailer().await);
+    let mailer = Arc::new(Mailer::from_env().unwrap());
 
     transactional_email_with_mailer(&surgeon, EmailType::Welcome, mailer)
         .await
         .unwrap();
 }
+
+#[tokio::test]
+#[ignore]
+async fn test_report_email() {
+    dotenv().ok();
+
+    let mut surgeon = Surgeon::mock();
+
+    let email_recipient = env::var("TEST_EMAIL_RECIPIENT")
+        .expect("expected TEST_EMAIL_RECIPIENT environment variable to be present");
+
+    surgeon.email = Email::new(&email_recipient).unwrap();
+    let mailer = Arc::new(Mailer::from_env().unwrap());
+
+    transactional_email_with_mailer(&surgeon, EmailType::Report(report()), mailer)
+        .await
+        .unwrap();
+}
@@ -1,10 +1,11 @@
 use crate::bounded::Bounded;
+use crate::model::CompareParams;
 use crate::model::Year;
 use crate::plots::AsPlot;
-use crate::plots::Cohort;
 use crate::plots::get_compare_with_client;
 #[allow(unused_imports)] use crate::tests::common::populate_test_db;
 use crate::tests::common::test_db;
+use crate::theme::PlotTheme;
 
 #[tokio::test]
 async fn creates_plots() {
@@ -24,12 +25,15 @@ async fn creates_plots() {
         // assert!(client.ensure_connected().await.is_ok());
     }
 
-    let compare = get_compare_with_client(&client, Year::new(2025).unwrap(), Cohort::Peers)
-        .await
-        .unwrap();
+    let params = CompareParams {
+        period: Year::new(2025).unwrap(),
+        ..Default::default()
+    };
+
+    let compare = get_compare_with_client(&client, &params).await.unwrap();
     // let scatter_plot = compare.cartesian_delta_cyl().plot();
     #[allow(unused_variables)]
-    let polar_plot = compare.polar_cyl_before().plot();
+    let polar_plot = compare.polar_cyl_before().plot(&PlotTheme::default());
     // scatter_plot.show();
     // polar_plot.show();
 }
@@ -0,0 +1,259 @@
+use gel_protocol::named_args;
+use gel_tokio::Client;
+use gel_tokio::create_client;
+use uuid::Uuid;
+
+use crate::bounded::Bounded;
+use crate::components::insert_surgeon_case;
+use crate::db::with_client_token;
+use crate::mock::Mock;
+use crate::model::Al;
+use crate::model::CaseFilter;
+use crate::model::Cohort;
+use crate::model::CompareParams;
+use crate::model::Email;
+use crate::model::Ks;
+use crate::model::Site;
+use crate::model::Surgeon;
+use crate::model::SurgeonCase;
+use crate::model::SurgeonDefaults;
+use crate::model::Year;
+use crate::plots::get_compare_with_client;
+use crate::query::query_insert_surgeon;
+use crate::query::query_select_current_surgeon;
+use crate::tests::common::TEST_JWTS;
+use crate::tests::common::test_db;
+
+/// Values that would break out of a string literal, or add clauses, if they were interpolated into
+/// the query text.
+const HOSTILE: [&str; 4] = [
+    r#"Eye" or true or "x"#,
+    r#"\"; delete Surgeon; select ""#,
+    "{ } filter select insert update",
+    "' union (select Surgeon { email }) '",
+];
+
+async fn count_surgeons(client: &Client) -> i64 {
+    client
+        .query_required_single("select count(Surgeon);", &())
+        .await
+        .unwrap()
+}
+
+/// Count the objects of the given type, which is never user input.
+async fn count(client: &Client, object: &str) -> i64 {
+    client
+        .query_required_single(format!("select count({object});"), &())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn inserts_hostile_strings_verbatim() {
+    let client = test_db().await;
+
+    for value in HOSTILE {
+        // Cases can only use an IOL that is already in the DB.
+        client
+            .execute(
+                "insert Iol { model := <str>$model } unless conflict on .model;",
+                &named_args! { "model" => value.to_string() },
+            )
+            .await
+            .unwrap();
+
+        let (surgeons, iols, cases) = (
+            count_surgeons(&client).await,
+            count(&client, "Iol").await,
+            count(&client, "SurgeonCas").await,
+        );
+
+        let current = client
+            .query_single::<Surgeon, _>(query_select_current_surgeon(), &())
+            .await
+            .unwrap();
+
+        let mut case = SurgeonCase::mock();
+        case.site = Some(Site {
+            name: value.to_string(),
+        });
+        case.case.iol.iol.model = value.to_string();
+
+        let inserted = insert_surgeon_case(&client, case).await.unwrap();
+
+        assert_eq!(inserted.site.as_ref().unwrap().name, value);
+        assert_eq!(inserted.case.iol.iol.model, value);
+
+        // Only the case itself was added.
+        assert_eq!(count_surgeons(&client).await, surgeons);
+        assert_eq!(count(&client, "Iol").await, iols);
+        assert_eq!(count(&client, "SurgeonCas").await, cases + 1);
+        assert_eq!(
+            client
+                .query_single::<Surgeon, _>(query_select_current_surgeon(), &())
+                .await
+                .unwrap(),
+            current
+        );
+
+        client
+            .execute(
+                "delete SurgeonCas filter .number = <int64>$number;",
+                &named_args! { "number" => inserted.number },
+            )
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn signs_up_with_hostile_strings_verbatim() {
+    // The cohort identity has no surgeon, so it can sign up.
+    let client = with_client_token(
+        &create_client().await.unwrap(),
+        Some(TEST_JWTS.cohort.as_str()),
+    );
+
+    let current = test_db()
+        .await
+        .query_single::<Surgeon, _>(query_select_current_surgeon(), &())
+        .await
+        .unwrap();
+
+    for value in HOSTILE {
+        let before = count_surgeons(&client).await;
+        let email = Email::new(&format!("hostile-{}@example.com", Uuid::new_v4())).unwrap();
+
+        let surgeon = Surgeon {
+            email: email.clone(),
+            full_name: Some(value.to_string()),
+            preferred_name: Some(value.to_string()),
+            defaults: Some(SurgeonDefaults {
+                site: Some(Site {
+                    name: value.to_string(),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let (query, args) = query_insert_surgeon(&surgeon);
+        let inserted = client
+            .query_single::<Surgeon, _>(query, &args)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(inserted.email, email);
+        assert_eq!(inserted.full_name.as_deref(), Some(value));
+        assert_eq!(inserted.preferred_name.as_deref(), Some(value));
+        assert_eq!(inserted.defaults.unwrap().site.unwrap().name, value);
+        assert_eq!(count_surgeons(&client).await, before + 1);
+
+        client
+            .execute(
+                "delete Surgeon filter .email = <str>$email;",
+                &named_args! { "email" => email.inner() },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(count_surgeons(&client).await, before);
+    }
+
+    // The other surgeon is unchanged.
+    assert_eq!(
+        test_db()
+            .await
+            .query_single::<Surgeon, _>(query_select_current_surgeon(), &())
+            .await
+            .unwrap(),
+        current
+    );
+}
+
+#[tokio::test]
+async fn filters_on_hostile_strings_as_values() {
+    let client = test_db().await;
+
+    let before = count_surgeons(&client).await;
+
+    for value in HOSTILE {
+        let filter = CaseFilter {
+            iol: Some(value.to_string()),
+            site: Some(value.to_string()),
+            ..Default::default()
+        };
+
+        for cohort in [Cohort::Peers, Cohort::Surgeon] {
+            let params = CompareParams {
+                period: Year::new(2025).unwrap(),
+                cohort,
+                filter: filter.clone(),
+            };
+
+            let compare = get_compare_with_client(&client, &params).await.unwrap();
+
+            // No IOL or site has these names, so the filter matches nothing.
+            let prediction = compare.prediction_error();
+            assert!(prediction.surgeon.errors.is_empty());
+            assert!(prediction.cohort.errors.is_empty());
+        }
+    }
+
+    assert_eq!(count_surgeons(&client).await, before);
+}
+
+#[tokio::test]
+async fn decodes_current_surgeon() {
+    let client = test_db().await;
+
+    let surgeon = client
+        .query_single::<Surgeon, _>(query_select_current_surgeon(), &())
+        .await
+        .unwrap();
+
+    assert!(surgeon.is_some());
+}
+
+#[tokio::test]
+async fn validates_bounded_values_when_decoding() {
+    let client = test_db().await;
+
+    let al = client
+        .query_required_single::<Al, _>("select <int32>2400;", &())
+        .await
+        .unwrap();
+
+    assert_eq!(al, Al::new(2400).unwrap());
+
+    // Below the bounds of Al, and negative values can't be unsigned.
+    for query in ["select <int32>100;", "select <int32>-2400;"] {
+        assert!(
+            client
+                .query_required_single::<Al, _>(query, &())
+                .await
+                .is_err()
+        );
+    }
+}
+
+#[tokio::test]
+async fn orders_ks_when_decoding() {
+    let client = test_db().await;
+
+    // The flat K is steeper than the steep K, as could happen with a row inserted by another app.
+    let ks = client
+        .query_required_single::<Ks, _>(
+            r#"select {
+                flat := { power := <int32>4500, axis := <int32>90 },
+                steep := { power := <int32>4200, axis := <int32>0 }
+            };"#,
+            &(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!((ks.flat_power(), ks.flat_axis()), (4200, 0));
+    assert_eq!((ks.steep_power(), ks.steep_axis()), (4500, 90));
+}
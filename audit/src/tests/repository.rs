@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::auth::AuthSession;
+use crate::bounded::Bounded;
+use crate::mock::Mock;
+use crate::mock::gen_mocks;
+use crate::model::CompareParams;
+use crate::model::DateRange;
+use crate::model::Email;
+use crate::model::Iol;
+use crate::model::ReportFrequency;
+use crate::model::Surgeon;
+use crate::model::SurgeonCase;
+use crate::model::Year;
+use crate::plots::CaseCompare;
+use crate::report::send_due_reports;
+use crate::repository::MemoryRepository;
+use crate::repository::Repository;
+use crate::tests::common::test_mailer;
+
+fn session() -> AuthSession {
+    AuthSession {
+        unverified_identity_id: Uuid::new_v4(),
+        auth_token: String::from("memory"),
+        expires_at: Utc::now(),
+    }
+}
+
+/// Sign up a new surgeon in `repository`, returning a repository acting on their behalf.
+async fn signed_up(repository: &MemoryRepository, email: &str) -> Arc<dyn Repository> {
+    let repository = repository.with_session(Some(&session()));
+
+    let mut surgeon = Surgeon::mock();
+    surgeon.email = Email::new(email).unwrap();
+    repository.insert_surgeon(&surgeon).await.unwrap();
+
+    repository
+}
+
+/// Insert mocked cases from 2025, returning them as stored.
+async fn insert_cases(repository: &dyn Repository, n: u32) -> Vec<SurgeonCase> {
+    let mut inserted = Vec::new();
+
+    for mut case in gen_mocks::<SurgeonCase>(n) {
+        case.date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        inserted.push(repository.insert_case(case).await.unwrap());
+    }
+
+    inserted
+}
+
+#[tokio::test]
+async fn isolates_surgeons_cases() {
+    let repository = MemoryRepository::with_iols(vec![Iol::mock()]);
+    let jane = signed_up(&repository, "jane@example.com").await;
+    let john = signed_up(&repository, "john@example.com").await;
+
+    let jane_cases = insert_cases(jane.as_ref(), 3).await;
+    let john_cases = insert_cases(john.as_ref(), 2).await;
+
+    // Each surgeon sees only their own cases, and the other surgeon's cases only as the cohort.
+    let params = CompareParams {
+        period: Year::new(2025).unwrap(),
+        ..Default::default()
+    };
+
+    let compare = jane.compare(&params).await.unwrap();
+
+    let expected = CaseCompare::new(
+        jane_cases.clone(),
+        john_cases.iter().map(|sc| sc.case.clone()).collect(),
+    );
+
+    assert_eq!(compare, expected);
+
+    let trend = john.trend_cases().await.unwrap();
+    assert_eq!(trend.surgeon, john_cases);
+    assert_eq!(trend.cohort.len(), 1);
+    assert_eq!(trend.cohort[0].prediction_error.count, jane_cases.len());
+
+    // Without a session, there are no cases of the surgeon's own, and every case is in the cohort.
+    let signed_out = repository.with_session(None);
+    let params = CompareParams {
+        period: DateRange::new(
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+        )
+        .unwrap(),
+        ..Default::default()
+    };
+
+    let compare = signed_out.range_compare(&params).await.unwrap();
+
+    let expected = CaseCompare::new(
+        Vec::new(),
+        jane_cases
+            .iter()
+            .chain(&john_cases)
+            .map(|sc| sc.case.clone())
+            .collect(),
+    );
+
+    assert_eq!(compare, expected);
+}
+
+#[tokio::test]
+async fn rejects_writes_without_a_surgeon() {
+    let repository = MemoryRepository::with_iols(vec![Iol::mock()]);
+    let case = SurgeonCase::mock();
+
+    // Signed out
+    let signed_out = repository.with_session(None);
+    assert!(signed_out.insert_case(case.clone()).await.is_err());
+    assert_eq!(signed_out.current_surgeon().await.unwrap(), None);
+
+    // Signed in, but not signed up
+    let signed_in = repository.with_session(Some(&session()));
+    assert!(signed_in.insert_case(case).await.is_err());
+    assert_eq!(signed_in.accept_terms().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn keeps_existing_surgeon_on_email_conflict() {
+    let repository = MemoryRepository::with_iols(vec![Iol::mock()]);
+    let jane = signed_up(&repository, "jane@example.com").await;
+    let existing = jane.current_surgeon().await.unwrap().unwrap();
+
+    let mut surgeon = Surgeon::mock();
+    surgeon.email = existing.email.clone();
+
+    let other = repository.with_session(Some(&session()));
+    assert_eq!(other.insert_surgeon(&surgeon).await.unwrap(), existing);
+    assert_eq!(other.current_surgeon().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn sends_each_due_report_once() {
+    let repository = MemoryRepository::with_iols(vec![Iol::mock()]);
+    let jane = signed_up(&repository, "jane@example.com").await;
+    let john = signed_up(&repository, "john@example.com").await;
+
+    insert_cases(jane.as_ref(), 3).await;
+
+    for surgeon in [&jane, &john] {
+        surgeon
+            .update_report_frequency(Some(ReportFrequency::Monthly))
+            .await
+            .unwrap();
+    }
+
+    // The report covers June 2025, when Jane's cases are dated. John has no cases, so his report is
+    // claimed, but not sent.
+    let now = Utc.with_ymd_and_hms(2025, 7, 15, 9, 0, 0).unwrap();
+    let (mailer, transport) = test_mailer();
+
+    let sent = send_due_reports(&repository, Arc::clone(&mailer), now)
+        .await
+        .unwrap();
+
+    assert_eq!(sent, 1);
+
+    let messages = transport.messages();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0].to[0].email,
+        Email::new("jane@example.com").unwrap()
+    );
+    assert_eq!(
+        messages[0].subject,
+        "Your monthly cataract audit report: 1 Jun 2025 to 30 Jun 2025"
+    );
+
+    // Both reports are claimed, so a later check sends nothing.
+    let sent = send_due_reports(&repository, mailer, now).await.unwrap();
+
+    assert_eq!(sent, 0);
+    assert_eq!(transport.messages().len(), 1);
+}
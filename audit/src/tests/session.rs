@@ -0,0 +1,66 @@
+use futures::future::join_all;
+use gel_tokio::Client;
+use gel_tokio::create_client;
+use serde::Deserialize;
+
+use crate::db::with_client_token;
+use crate::tests::common::TEST_JWTS;
+
+/// What a session can see: the current surgeon, and the owner of every `SurgeonCas` visible to
+/// them.
+#[derive(Debug, Deserialize)]
+struct SessionView {
+    current: Option<String>,
+    owners: Vec<String>,
+}
+
+async fn session_view(client: &Client) -> SessionView {
+    let query = r#"
+select {
+    current := global cur_surgeon.email,
+    owners := array_agg(SurgeonCas.surgeon.email)
+};
+    "#;
+
+    let json = client.query_required_single_json(query, &()).await.unwrap();
+
+    serde_json::from_str(json.as_ref()).unwrap()
+}
+
+#[tokio::test]
+async fn concurrent_sessions_are_isolated() {
+    // As in production, every session derives its client from one shared client.
+    let shared = create_client().await.unwrap();
+
+    let sessions = [
+        with_client_token(&shared, Some(TEST_JWTS.surgeon.as_str())),
+        with_client_token(&shared, Some(TEST_JWTS.cohort.as_str())),
+    ];
+
+    let (surgeon, cohort) = (
+        session_view(&sessions[0]).await.current.unwrap(),
+        session_view(&sessions[1]).await.current.unwrap(),
+    );
+
+    assert_ne!(surgeon, cohort);
+
+    // Interleave many requests from both sessions.
+    let views = join_all((0..40).map(|i| {
+        let client = &sessions[i % 2];
+        async move { (i % 2, session_view(client).await) }
+    }))
+    .await;
+
+    for (session, view) in views {
+        let expected = if session == 0 { &surgeon } else { &cohort };
+
+        assert_eq!(view.current.as_ref(), Some(expected));
+        assert!(view.owners.iter().all(|owner| owner == expected));
+    }
+
+    // Deriving the session clients left the shared client without an identity.
+    let view = session_view(&shared).await;
+
+    assert_eq!(view.current, None);
+    assert!(view.owners.is_empty());
+}
@@ -0,0 +1,178 @@
+//! Colour themes for plots. The [`Theme`] is chosen in the UI and sent to the server, which renders
+//! each plot with the corresponding [`PlotTheme`].
+
+use std::fmt::Display;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A colour theme, selectable in the UI.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// A dark theme using the Okabe-Ito palette, which remains distinguishable with the common
+    /// forms of colour-vision deficiency.
+    ColorBlind,
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dark => write!(f, "Dark"),
+            Self::Light => write!(f, "Light"),
+            Self::ColorBlind => write!(f, "ColorBlind"),
+        }
+    }
+}
+
+impl Theme {
+    pub const ALL: [Self; 3] = [Self::Dark, Self::Light, Self::ColorBlind];
+
+    /// A human-readable name, for labels in the UI.
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+            Self::ColorBlind => "Colour-blind safe",
+        }
+    }
+
+    /// Parse a theme from its [`Display`] representation, falling back to the default.
+    pub fn from_param(value: Option<&str>) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|theme| Some(theme.to_string().as_str()) == value)
+            .unwrap_or_default()
+    }
+
+    pub fn plot_theme(&self) -> PlotTheme {
+        match self {
+            Self::Dark => PlotTheme::DARK,
+            Self::Light => PlotTheme::LIGHT,
+            Self::ColorBlind => PlotTheme::COLOR_BLIND,
+        }
+    }
+}
+
+/// The colours used by every [`AsPlot`](crate::plots::AsPlot) implementation, as CSS hex strings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlotTheme {
+    /// The paper and plot background, and table cells.
+    pub background: &'static str,
+    /// Grid lines and table headers.
+    pub grid: &'static str,
+    /// Axis labels, legend group titles, and error bars.
+    pub label: &'static str,
+    /// Tick labels on polar plots.
+    pub tick: &'static str,
+    /// Legend entries.
+    pub legend: &'static str,
+    /// The surgeon's cases.
+    pub surgeon: &'static str,
+    /// A secondary colour for the surgeon's cases (such as preop values, beside postop values).
+    pub surgeon_muted: &'static str,
+    /// Summary markers for the surgeon's cases (such as centroids and rolling means).
+    pub surgeon_centroid: &'static str,
+    /// Confidence regions for the surgeon's cases, and any series contrasting with
+    /// [`surgeon`](PlotTheme::surgeon).
+    pub surgeon_confidence: &'static str,
+    /// The cohort's cases, and their confidence regions.
+    pub cohort: &'static str,
+    /// A secondary colour for the cohort's cases.
+    pub cohort_muted: &'static str,
+    /// Summary markers for the cohort's cases, and reference values.
+    pub cohort_centroid: &'static str,
+    /// A translucent fill for bands around the cohort's summary values (such as ±1 SD), with the
+    /// alpha as the last two hex digits.
+    pub cohort_band: &'static str,
+}
+
+impl Default for PlotTheme {
+    fn default() -> Self { Self::DARK }
+}
+
+impl PlotTheme {
+    /// The Okabe-Ito palette, on the dark background. The surgeon's cases (orange) contrast with
+    /// their confidence regions (sky blue) and centroids (bluish green) in hue and lightness.
+    pub const COLOR_BLIND: Self = Self {
+        background: "#252833",
+        grid: "#363a48",
+        label: "#eaebed",
+        tick: "#acafb9",
+        legend: "#caccd1",
+        surgeon: "#e69f00",
+        surgeon_muted: "#f0e442",
+        surgeon_centroid: "#009e73",
+        surgeon_confidence: "#56b4e9",
+        cohort: "#848998",
+        cohort_muted: "#52576b",
+        cohort_centroid: "#f5f5f6",
+        cohort_band: "#8489984d",
+    };
+    pub const DARK: Self = Self {
+        background: "#252833",
+        grid: "#363a48",
+        label: "#eaebed",
+        tick: "#acafb9",
+        legend: "#caccd1",
+        surgeon: "#ff7b00",
+        surgeon_muted: "#fcb889",
+        surgeon_centroid: "#00f115",
+        surgeon_confidence: "#f100dc",
+        cohort: "#848998",
+        cohort_muted: "#52576b",
+        cohort_centroid: "#f5f5f6",
+        cohort_band: "#8489984d",
+    };
+    pub const LIGHT: Self = Self {
+        background: "#ffffff",
+        grid: "#e1e3e8",
+        label: "#252833",
+        tick: "#52576b",
+        legend: "#363a48",
+        surgeon: "#e06c00",
+        surgeon_muted: "#f7b27a",
+        surgeon_centroid: "#008a0c",
+        surgeon_confidence: "#b8009f",
+        cohort: "#848998",
+        cohort_muted: "#c3c6cf",
+        cohort_centroid: "#252833",
+        cohort_band: "#84899833",
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_theme_params() {
+        for theme in Theme::ALL {
+            assert_eq!(Theme::from_param(Some(theme.to_string().as_str())), theme);
+        }
+
+        assert_eq!(Theme::from_param(Some("Sepia")), Theme::Dark);
+        assert_eq!(Theme::from_param(None), Theme::Dark);
+    }
+
+    #[test]
+    fn color_blind_theme_uses_okabe_ito() {
+        const OKABE_ITO: [&str; 8] = [
+            "#000000", "#e69f00", "#56b4e9", "#009e73", "#f0e442", "#0072b2", "#d55e00", "#cc79a7",
+        ];
+
+        let PlotTheme {
+            surgeon,
+            surgeon_muted,
+            surgeon_centroid,
+            surgeon_confidence,
+            ..
+        } = Theme::ColorBlind.plot_theme();
+
+        for color in [surgeon, surgeon_muted, surgeon_centroid, surgeon_confidence] {
+            assert!(OKABE_ITO.contains(&color));
+        }
+    }
+}
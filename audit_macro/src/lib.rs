@@ -12,7 +12,9 @@ use syn::parse_macro_input;
 use syn::punctuated::Punctuated;
 
 /// Implement [`Bounded`](audit::bounded::Bounded), [`Display`] (as the inner type), [`Default`],
-/// and [`MockRange`](audit::mock::MockRange) for range-bounded numeric tuple-structs.
+/// [`MockRange`](audit::mock::MockRange) and, for integer inner types, `Queryable` for
+/// range-bounded numeric tuple-structs. Bounded integers are stored as `int32` in the DB, and
+/// decoding a value checks its bounds in the same way as `new`.
 ///
 /// ```
 /// use audit_macro::RangeBounded;
@@ -134,6 +136,51 @@ pub fn range_bounded(item: TokenStream) -> TokenStream {
         quote! {}
     };
 
+    // Floats (such as plot parameters) are never stored in the DB, and can't be decoded from
+    // `int32`.
+    let is_integer = matches!(
+        &ty,
+        Type::Path(path) if path.path.get_ident().is_some_and(|ident| {
+            ["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64"].contains(&ident.to_string().as_str())
+        })
+    );
+
+    let queryable = if is_integer {
+        quote! {
+            #[cfg(feature = "ssr")]
+            impl ::gel_protocol::queryable::Queryable for #name {
+                type Args = <i32 as ::gel_protocol::queryable::Queryable>::Args;
+
+                fn decode(
+                    decoder: &::gel_protocol::queryable::Decoder,
+                    args: &Self::Args,
+                    buf: &[u8],
+                ) -> ::core::result::Result<Self, ::gel_protocol::errors::DecodeError> {
+                    let value =
+                        <i32 as ::gel_protocol::queryable::Queryable>::decode(decoder, args, buf)?;
+
+                    let value =
+                        <#ty>::try_from(value).map_err(::gel_protocol::errors::decode_error)?;
+
+                    <Self as crate::bounded::Bounded>::new(value)
+                        .map_err(::gel_protocol::errors::decode_error)
+                }
+
+                fn check_descriptor(
+                    ctx: &::gel_protocol::queryable::DescriptorContext,
+                    type_pos: ::gel_protocol::descriptors::TypePos,
+                ) -> ::core::result::Result<
+                    Self::Args,
+                    ::gel_protocol::queryable::DescriptorMismatch,
+                > {
+                    <i32 as ::gel_protocol::queryable::Queryable>::check_descriptor(ctx, type_pos)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     let output = quote! {
         impl crate::bounded::Bounded for #name {
             type Idx = #ty;
@@ -172,6 +219,8 @@ pub fn range_bounded(item: TokenStream) -> TokenStream {
 
         #[cfg(feature = "ssr")]
         impl crate::mock::MockRange for #name { #mock_range }
+
+        #queryable
     };
 
     output.into()